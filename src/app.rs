@@ -0,0 +1,2904 @@
+use eframe::{
+    egui::{
+        self, scroll_area::ScrollBarVisibility, Align2, Color32, Context, Key, KeyboardShortcut,
+        Modifiers, RichText, Ui,
+    },
+    App, Frame,
+};
+use egui_file::{DialogType, FileDialog};
+use egui_float_scroller::FixedScrollbar;
+use egui_toast::{Toast, ToastKind, ToastOptions, Toasts};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
+    thread,
+    time::Duration,
+};
+use unicode_width::UnicodeWidthChar;
+
+use crate::preview::Preview;
+use asciinema_editor::asciicast_egui::{Event, EventData, Header, Theme};
+use asciinema_editor::cast::{
+    AdvancedModificationAction, CastError, CastFile, EventPositioned, ModificationAction, Snapshot,
+};
+use asciinema_editor::cleanup::{self, TypoFix};
+use asciinema_editor::concat::ConcatOptions;
+use asciinema_editor::export::GifOptions;
+use asciinema_editor::lint::{Problem, Report, Severity, MAX_PROBLEMS};
+use asciinema_editor::redact::{Confidence, Finding, Redactor};
+use asciinema_editor::scan::Progress;
+use asciinema_editor::search::{
+    self, ReplacePlan, Search, SearchMatch, SearchOptions, MAX_MATCHES,
+};
+use asciinema_editor::split::{self, SplitPart, SplitPoints};
+use asciinema_editor::themes;
+use asciinema_editor::timing::{round_time, IdleCompression, TimeTransform};
+
+// todo: Add general UI scaling depending on some zoom
+const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+const REDO_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+const EVENTS_PER_PAGE: usize = 50;
+/// Approximate height of a row of the event grid, used so one scroll step moves about one event
+const EVENT_ROW_HEIGHT: f32 = 20.0;
+const MIN_HANDLE_HEIGHT: f32 = 10.0;
+/// Seconds after the last event a line inserted at the end of the file is put at
+const APPENDED_EVENT_DELAY: f64 = 1.0;
+const TIME_EDIT_WIDTH: f32 = 80.0;
+/// Event types offered by the type dropdown, anything else is entered as a custom code
+const EVENT_CODES: [(char, &str); 5] = [
+    ('o', "Output"),
+    ('i', "Input"),
+    ('r', "Resize"),
+    ('m', "Marker"),
+    ('x', "Exit"),
+];
+/// Idle time limit a header starts with when the limit is turned on
+const DEFAULT_IDLE_TIME_LIMIT: f64 = 2.0;
+/// The header panel scrolls past this height so opening its sections doesn't squeeze out the events
+const HEADER_MAX_HEIGHT: f32 = 300.0;
+const SEARCH_RESULTS_HEIGHT: f32 = 150.0;
+const MATCH_HIGHLIGHT: Color32 = Color32::from_rgb(0x70, 0x60, 0x10);
+/// Highlight of the matches in the event last stepped to
+const CURRENT_MATCH_HIGHLIGHT: Color32 = Color32::from_rgb(0xc0, 0x70, 0x10);
+/// How often the window repaints while work runs in the background, which is how often its progress bars move
+const TASK_REPAINT: Duration = Duration::from_millis(100);
+const TASK_PROGRESS_WIDTH: f32 = 160.0;
+
+/// Opens the editor window
+pub fn run() {
+    let native_options = eframe::NativeOptions::default();
+    eframe::run_native(
+        "Asciinema Editor",
+        native_options,
+        Box::new(|cc| {
+            // Gives us image support
+            egui_extras::install_image_loaders(&cc.egui_ctx);
+            Ok(Box::new(MyEguiApp::new(cc)))
+        }),
+    )
+    .expect("eframe failed");
+}
+
+/// Header being edited in the header panel. Like typo reviews the draft belongs to one revision of the file and is started over once the file changes
+struct HeaderDraft {
+    revision: u64,
+    header: Header,
+    /// Environment variables as editable pairs sorted by name, written back into `header.env` when the draft is applied
+    env: Vec<(String, String)>,
+}
+
+impl HeaderDraft {
+    fn new(cast_file: &CastFile) -> Self {
+        let header = cast_file.header.clone();
+        let mut env: Vec<(String, String)> = header
+            .env
+            .iter()
+            .flatten()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        env.sort();
+        Self {
+            revision: cast_file.revision(),
+            header,
+            env,
+        }
+    }
+
+    /// The header the draft would apply. Variables without a name are left out
+    fn to_header(&self) -> Header {
+        let env: HashMap<String, String> = self
+            .env
+            .iter()
+            .filter(|(key, _)| !key.is_empty())
+            .cloned()
+            .collect();
+        let mut header = self.header.clone();
+        // An empty environment that was in the file is kept as it was
+        if !env.is_empty() || header.env.as_ref().is_some_and(|env| !env.is_empty()) {
+            header.env = (!env.is_empty()).then_some(env);
+        }
+        header
+    }
+}
+
+/// Redaction choices kept between scans
+struct RedactionSettings {
+    /// Character secrets are masked with
+    mask: String,
+    /// User defined secret patterns, one regular expression per line
+    patterns: String,
+    /// Refuse to save while high confidence secrets are neither redacted nor reviewed
+    strict: bool,
+    /// Detector and secret of the findings that were reviewed and deliberately left in
+    reviewed: HashSet<(String, String)>,
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        Self {
+            mask: "*".to_string(),
+            patterns: String::new(),
+            strict: false,
+            reviewed: HashSet::new(),
+        }
+    }
+}
+
+impl RedactionSettings {
+    fn redactor(&self) -> Result<Redactor, String> {
+        let patterns: Vec<String> = self
+            .patterns
+            .lines()
+            .map(|line| line.trim().to_string())
+            .collect();
+        let mut redactor = Redactor::with_patterns(&patterns)
+            .map_err(|e| format!("Invalid secret pattern: {}", e))?;
+        redactor.mask = self
+            .mask
+            .chars()
+            .next()
+            .filter(|c| c.width() == Some(1))
+            .ok_or_else(|| "The mask must be a single one column character".to_string())?;
+        Ok(redactor)
+    }
+}
+
+/// Secrets found by a scan with whether each is selected to be masked. Like typo reviews the findings point at one revision of the file and the review is dropped once the file changes
+struct RedactionReview {
+    revision: u64,
+    findings: Vec<Finding>,
+    selected: Vec<bool>,
+}
+
+impl RedactionReview {
+    /// High confidence findings start out selected, low confidence ones have to be picked
+    fn new(revision: u64, findings: Vec<Finding>) -> Self {
+        Self {
+            revision,
+            selected: findings
+                .iter()
+                .map(|finding| finding.confidence == Confidence::High)
+                .collect(),
+            findings,
+        }
+    }
+}
+
+/// Recordings picked to be appended to the open file and how they are joined
+#[derive(Default)]
+struct Concatenation {
+    files: Vec<PathBuf>,
+    options: ConcatOptions,
+}
+
+/// Which kind of split point the split window uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum SplitMode {
+    #[default]
+    Markers,
+    Times,
+    IdleGaps,
+}
+
+/// Settings of the split window with the parts they made when last previewed
+struct SplitState {
+    mode: SplitMode,
+    /// Times to split at separated by commas
+    times: String,
+    /// Pauses longer than this many seconds split the recording
+    idle: f64,
+    /// Parts of the last preview with the revision they were planned at
+    parts: Option<(u64, Vec<SplitPart>)>,
+}
+
+impl Default for SplitState {
+    fn default() -> Self {
+        Self {
+            mode: SplitMode::default(),
+            times: String::new(),
+            idle: 5.0,
+            parts: None,
+        }
+    }
+}
+
+impl SplitState {
+    fn points(&self) -> Result<SplitPoints, String> {
+        match self.mode {
+            SplitMode::Markers => Ok(SplitPoints::Markers),
+            SplitMode::Times => parse_times(&self.times)
+                .map(SplitPoints::Times)
+                .ok_or_else(|| format!("Invalid split times: {}", self.times)),
+            SplitMode::IdleGaps => Ok(SplitPoints::IdleGaps(self.idle)),
+        }
+    }
+
+    /// Parts for the file as it is now, planned again if the last preview is from another revision
+    fn plan(&mut self, cast_file: &CastFile) -> Result<&[SplitPart], String> {
+        if self
+            .parts
+            .as_ref()
+            .is_none_or(|(revision, _)| *revision != cast_file.revision())
+        {
+            let parts = split::plan_split(cast_file, &self.points()?).map_err(|e| e.to_string())?;
+            self.parts = Some((cast_file.revision(), parts));
+        }
+        Ok(self
+            .parts
+            .as_ref()
+            .map_or(&[], |(_, parts)| parts.as_slice()))
+    }
+}
+
+/// Work running on another thread, shown with a progress bar and a cancel button until its result arrives. Dropping the task leaves the thread to finish on its own and throws its result away
+struct Task<T> {
+    label: String,
+    progress: Arc<Progress>,
+    result: mpsc::Receiver<Option<T>>,
+}
+
+impl<T: Send + 'static> Task<T> {
+    /// Runs `work` on a new thread. Work that scans the file is expected to stop and return `None` once `progress` is cancelled
+    fn spawn(label: String, work: impl FnOnce(&Progress) -> Option<T> + Send + 'static) -> Self {
+        let progress = Arc::new(Progress::default());
+        let shared = Arc::clone(&progress);
+        let (sender, result) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(work(&shared));
+        });
+        Self {
+            label,
+            progress,
+            result,
+        }
+    }
+
+    /// `None` while the work is running. Once it stopped this is the result, or `Some(None)` if it was cancelled or failed
+    fn poll(&self) -> Option<Option<T>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(None),
+        }
+    }
+
+    fn cancel(&self) {
+        self.progress.cancel();
+    }
+
+    /// Label, progress bar and cancel button of the task. Work that can't tell how far it got shows a spinner instead. Returns whether cancel was clicked
+    fn show(&self, ui: &mut Ui) -> bool {
+        ui.label(&self.label);
+        let fraction = self.progress.fraction();
+        if fraction > 0.0 {
+            ui.add(
+                egui::ProgressBar::new(fraction)
+                    .desired_width(TASK_PROGRESS_WIDTH)
+                    .show_percentage(),
+            );
+        } else {
+            ui.spinner();
+        }
+        ui.button("Cancel").clicked()
+    }
+}
+
+/// What a job started from a menu or dialog found or did
+enum JobOutput {
+    Typos(Vec<TypoFix>),
+    Secrets(Vec<Finding>),
+    /// What replacing every match of `pattern` changes, only counted with `dry_run`
+    Replace {
+        plan: ReplacePlan,
+        pattern: String,
+        dry_run: bool,
+    },
+    Saved(Result<(), CastError>),
+    /// High confidence secrets strict saving found and refused to save with
+    Unreviewed(Vec<Finding>),
+    Exported(Result<(), CastError>),
+}
+
+/// A job reading every event of a snapshot of the open file in the background
+struct Job {
+    task: Task<JobOutput>,
+    /// Revision of the file the job's findings point at, `None` once another file was opened
+    revision: Option<u64>,
+    /// Saves and exports go on when another file is opened while scans of the old file are cancelled
+    writes_file: bool,
+}
+
+/// What happens once a search running in the background finishes
+enum AfterSearch {
+    /// Only the list of matches is updated, as after replacing
+    Nothing,
+    SelectFirst,
+    /// Step from the match with this event number, or from the start without one, in the given direction
+    Step(Option<usize>, bool),
+}
+
+/// A search running in the background for the revision it was started at
+struct RunningSearch {
+    task: Task<Vec<SearchMatch>>,
+    search: Search,
+    revision: u64,
+    then: AfterSearch,
+}
+
+/// Search bar state. Matches are found for one revision of the file and the search runs again when stepping through them after the file changed
+#[derive(Default)]
+struct SearchState {
+    options: SearchOptions,
+    /// The search the matches were found with, `None` until the first search
+    search: Option<Search>,
+    revision: u64,
+    matches: Vec<SearchMatch>,
+    /// Search scanning the file in the background, whose matches replace `matches` once it finishes
+    running: Option<RunningSearch>,
+    /// Position in `matches` of the match last stepped to
+    current: Option<usize>,
+    /// Text matches are replaced with, which may use capture groups like `${1}` in regex searches
+    replacement: String,
+    /// Whether replacing also covers matches split between two events in a row
+    across_events: bool,
+    /// Matches and events a replace would change as last counted, cleared when the texts are edited
+    dry_run: Option<(usize, usize)>,
+}
+
+impl SearchState {
+    fn current_match(&self) -> Option<&SearchMatch> {
+        self.matches.get(self.current?)
+    }
+}
+
+/// Which part of an event is being edited
+#[derive(PartialEq)]
+enum EditField {
+    Time,
+    Data,
+}
+
+/// An event being edited together with the events around it, which bound its time. Like typo reviews the edit points at a specific revision of the file and is dropped once the file changes
+struct EventEdit {
+    revision: u64,
+    /// Previous, edited and next event as they were shown in the grid
+    window: [EventPositioned; 3],
+    field: EditField,
+    text: String,
+    /// Whether the time field has been given keyboard focus yet
+    focused: bool,
+}
+
+/// Typo fixes found by a scan with whether each is selected to be applied. The fixes point at events of a specific revision of the file so the review is dropped once the file changes
+struct TypoReview {
+    revision: u64,
+    fixes: Vec<TypoFix>,
+    selected: Vec<bool>,
+}
+
+struct MyEguiApp {
+    cast_file: Option<CastFile>,
+    /// File being opened on another thread, which replaces `cast_file` once it is read. Converting v1 and v3 files reads them whole
+    opening: Option<Task<Result<CastFile, CastError>>>,
+    file_dialog: Option<FileDialog>,
+    /// Save dialog for GIF export, kept apart from `file_dialog` as both save a file but write different formats
+    export_dialog: Option<FileDialog>,
+    /// Open dialog for a terminal emulator config to take the theme from
+    theme_dialog: Option<FileDialog>,
+    /// Open dialog for a recording to append or save dialog for the joined recording
+    concat_dialog: Option<FileDialog>,
+    /// Recordings to append, `Some` while the concatenation window is open
+    concatenation: Option<Concatenation>,
+    /// Save dialog for the first of the parts of a split
+    split_dialog: Option<FileDialog>,
+    /// Split settings, `Some` while the split window is open
+    split: Option<SplitState>,
+    gif_options: GifOptions,
+    /// Start and end in seconds of the range used by the time edits
+    edit_range: (f64, f64),
+    /// Seconds the range is moved by when shifting
+    edit_offset: f64,
+    /// Speed multiplier for the range
+    edit_speed: f64,
+    /// `old=new` pairs of times for retiming through a piecewise function
+    retime_points: String,
+    idle_compression: IdleCompression,
+    /// Proposed typo fixes waiting to be reviewed
+    typo_review: Option<TypoReview>,
+    redaction: RedactionSettings,
+    /// Secrets found by the last scan waiting to be reviewed
+    redaction_review: Option<RedactionReview>,
+    /// Time entered to jump the event grid and preview to
+    goto_time: f64,
+    header_draft: Option<HeaderDraft>,
+    /// Event time or data currently being edited in the grid
+    event_edit: Option<EventEdit>,
+    /// Code typed into the type dropdown for event types without an entry
+    custom_code: String,
+    search: SearchState,
+    /// Markers of the open file with the revision they were scanned at
+    marker_cache: Option<(u64, Vec<EventPositioned>)>,
+    /// Marker scan running in the background for the revision beside it. The markers of the last scan are shown until it is done
+    marker_scan: Option<(u64, Task<Vec<EventPositioned>>)>,
+    /// Check of the open file for problems, started whenever a file is opened
+    lint_scan: Option<Task<Report>>,
+    /// Problems found by the last check of the open file
+    lint_report: Option<Report>,
+    /// Scan or save started from a menu or dialog, applied once it finishes. Only one runs at a time
+    job: Option<Job>,
+    show_problems: bool,
+    scroll_position: f32,
+    toasts: Toasts,
+    preview: Preview,
+}
+
+impl MyEguiApp {
+    fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+        Self {
+            cast_file: None,
+            opening: None,
+            file_dialog: None,
+            export_dialog: None,
+            theme_dialog: None,
+            concat_dialog: None,
+            concatenation: None,
+            split_dialog: None,
+            split: None,
+            gif_options: GifOptions::default(),
+            edit_range: (0.0, 0.0),
+            edit_offset: 0.0,
+            edit_speed: 1.0,
+            retime_points: String::new(),
+            idle_compression: IdleCompression::default(),
+            typo_review: None,
+            redaction: RedactionSettings::default(),
+            redaction_review: None,
+            search: SearchState::default(),
+            goto_time: 0.0,
+            header_draft: None,
+            event_edit: None,
+            custom_code: String::new(),
+            marker_cache: None,
+            marker_scan: None,
+            lint_scan: None,
+            lint_report: None,
+            job: None,
+            show_problems: false,
+            scroll_position: 0.0,
+            // Initialize toasts with your preferred settings
+            toasts: Toasts::new()
+                .anchor(Align2::LEFT_TOP, (10.0, 30.0))
+                .direction(egui::Direction::TopDown),
+            preview: Preview::new(),
+        }
+    }
+
+    fn error_toast(&mut self, text: String) {
+        self.toasts.add(Toast {
+            text: text.into(),
+            kind: ToastKind::Error,
+            options: ToastOptions::default()
+                .duration_in_seconds(10.0)
+                .show_progress(true)
+                .show_icon(true),
+            ..Default::default()
+        });
+    }
+
+    /// Sets the theme of the open file as one undoable step. Other header edits in the draft are kept waiting to be applied
+    fn apply_theme(&mut self, description: String, theme: Theme) {
+        let Some(cast_file) = self.cast_file.as_mut() else {
+            return;
+        };
+        let mut header = cast_file.header.clone();
+        header.theme = Some(theme.clone());
+        if let Err(e) = cast_file.edit_header(description, header) {
+            self.error_toast(format!("Failed to apply theme: {}", e));
+        } else if let Some(draft) = self.header_draft.as_mut() {
+            draft.header.theme = Some(theme);
+            draft.revision = cast_file.revision();
+        }
+    }
+
+    /// Starts opening `path` on another thread. The open file stays usable until the new one is read
+    fn open_file(&mut self, path: PathBuf) {
+        if let Some(opening) = self.opening.take() {
+            opening.cancel();
+        }
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().to_string(),
+        );
+        self.opening = Some(Task::spawn(format!("Opening {}", name), move |_| {
+            Some(CastFile::new(path))
+        }));
+    }
+
+    /// Puts a file read by `open_file` in place of the open file, starting every view over
+    fn opened(&mut self, result: Result<CastFile, CastError>) {
+        match result {
+            Ok(cast_file) => {
+                self.typo_review = None;
+                self.redaction_review = None;
+                self.redaction.reviewed.clear();
+                if let Some(running) = self.search.running.take() {
+                    running.task.cancel();
+                }
+                self.search = SearchState {
+                    options: self.search.options.clone(),
+                    ..SearchState::default()
+                };
+                self.marker_cache = None;
+                if let Some((_, scan)) = self.marker_scan.take() {
+                    scan.cancel();
+                }
+                if let Some(job) = self.job.take_if(|job| !job.writes_file) {
+                    job.task.cancel();
+                }
+                if let Some(job) = &mut self.job {
+                    job.revision = None;
+                }
+                self.event_edit = None;
+                self.header_draft = None;
+                if cast_file.source_version != cast_file.save_version() {
+                    self.toasts.add(Toast {
+                        text: format!(
+                            "Converted asciicast v{} recording, it will be saved as v{}",
+                            cast_file.source_version,
+                            cast_file.save_version()
+                        )
+                        .into(),
+                        kind: ToastKind::Info,
+                        options: ToastOptions::default()
+                            .duration_in_seconds(5.0)
+                            .show_progress(true)
+                            .show_icon(true),
+                        ..Default::default()
+                    });
+                }
+                self.lint_report = None;
+                self.show_problems = false;
+                self.lint_scan = Some(lint_task(&cast_file));
+                self.cast_file = Some(cast_file);
+                self.preview.reset();
+            }
+            Err(e) => {
+                self.toasts.add(Toast {
+                    text: format!("Failed to Create Cast Editor: {}", e).into(),
+                    kind: ToastKind::Error,
+                    options: ToastOptions::default()
+                        .duration_in_seconds(10.0)
+                        .show_progress(true)
+                        .show_icon(true),
+                    ..Default::default()
+                });
+                // We need to set it to None as if it user opens another file while one's already open and there's an error we don't want to deal with a potentially unusual program state
+                self.cast_file = None;
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(cast_file) = self.cast_file.as_mut() {
+            cast_file.undo();
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(cast_file) = self.cast_file.as_mut() {
+            cast_file.redo();
+        }
+    }
+
+    /// Starts editing the middle event of `window`, replacing any edit in progress
+    fn start_edit(&mut self, window: &[EventPositioned], field: EditField) {
+        let Some(cast_file) = &self.cast_file else {
+            return;
+        };
+        let event = &window[1].event;
+        let text = match field {
+            EditField::Time => event.time.to_string(),
+            EditField::Data => event.data.get_editable_data(),
+        };
+        self.event_edit = Some(EventEdit {
+            revision: cast_file.revision(),
+            window: [window[0].clone(), window[1].clone(), window[2].clone()],
+            field,
+            text,
+            focused: false,
+        });
+    }
+
+    /// Replaces the type and data of an event, keeping its time and place in the file
+    fn modify_event_data(
+        &mut self,
+        positioned: &EventPositioned,
+        data: EventData,
+    ) -> Result<(), CastError> {
+        let Some(cast_file) = self.cast_file.as_mut() else {
+            return Ok(());
+        };
+        let order = cast_file.get_order(positioned);
+        cast_file.action(
+            ModificationAction::ModifyData(data),
+            order,
+            positioned,
+            None,
+        )
+    }
+
+    /// Moves the event being edited to the entered time, which has to stay between the events around it
+    fn commit_time_edit(&mut self) -> Result<(), CastError> {
+        let (Some(edit), Some(cast_file)) = (self.event_edit.take(), self.cast_file.as_mut())
+        else {
+            return Ok(());
+        };
+        let [previous, current, next] = &edit.window;
+        let time: f64 = edit.text.trim().parse().map_err(|_| {
+            CastError::InvalidEventFormat(format!("{} is not a time", edit.text.trim()))
+        })?;
+        if edit.revision != cast_file.revision() || time == current.event.time {
+            return Ok(());
+        }
+        let order = cast_file.get_order(current);
+        cast_file.advanced_action(
+            AdvancedModificationAction::Modify(Event {
+                time,
+                data: current.event.data.clone(),
+            }),
+            order,
+            current,
+            Some(previous),
+            Some(next),
+        )
+    }
+
+    /// Multiline editor for the data of the event being edited. Control characters show as escapes so terminal sequences can be read and changed
+    fn render_data_editor(&mut self, ctx: &Context) {
+        let (Some(edit), Some(cast_file)) = (self.event_edit.as_mut(), self.cast_file.as_mut())
+        else {
+            return;
+        };
+        if edit.revision != cast_file.revision() {
+            self.event_edit = None;
+            return;
+        }
+        if edit.field != EditField::Data {
+            return;
+        }
+
+        let mut open = true;
+        let mut apply = false;
+        let mut cancel = false;
+        let event = &edit.window[1].event;
+        egui::Window::new(format!("Edit {} at {}s", event.data.get_type(), event.time))
+            .id(egui::Id::new("event_data_editor"))
+            .open(&mut open)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new(
+                        "Control characters are written as escapes like \\u001b or \\e and a backslash as \\\\",
+                    )
+                    .weak(),
+                );
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    ui.add(
+                        egui::TextEdit::multiline(&mut edit.text)
+                            .code_editor()
+                            .desired_rows(8)
+                            .desired_width(f32::INFINITY),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    apply = ui.button("Apply").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if apply {
+            let current = edit.window[1].clone();
+            let result = EventData::from_edited(current.event.data.code(), &edit.text)
+                .map_err(CastError::from)
+                .and_then(|data| self.modify_event_data(&current, data));
+            match result {
+                Ok(()) => self.event_edit = None,
+                Err(e) => self.error_toast(format!("Failed to edit event data: {}", e)),
+            }
+        } else if cancel || !open {
+            self.event_edit = None;
+        }
+    }
+
+    /// Scans for markers again in the background if the file changed since the last scan. A scan for a revision that is already outdated is cancelled and started over
+    fn refresh_markers(&mut self) {
+        let Some(cast_file) = &self.cast_file else {
+            self.marker_cache = None;
+            return;
+        };
+        let revision = cast_file.revision();
+        if let Some((scanned, scan)) = &self.marker_scan {
+            if let Some(result) = scan.poll() {
+                if let Some(markers) = result {
+                    self.marker_cache = Some((*scanned, markers));
+                }
+                self.marker_scan = None;
+            } else if *scanned != revision {
+                scan.cancel();
+                self.marker_scan = None;
+            }
+        }
+        let current = self
+            .marker_cache
+            .as_ref()
+            .is_some_and(|(scanned, _)| *scanned == revision);
+        if !current && self.marker_scan.is_none() {
+            let snapshot = cast_file.snapshot();
+            let scan = Task::spawn("Scanning for markers".to_string(), move |progress| {
+                snapshot.markers(progress)
+            });
+            self.marker_scan = Some((revision, scan));
+        }
+    }
+
+    fn markers(&self) -> &[EventPositioned] {
+        self.marker_cache
+            .as_ref()
+            .map_or(&[], |(_, markers)| markers.as_slice())
+    }
+
+    /// Scrolls the event grid to the first event at or after `time` and moves the preview there. The grid only moves once the line index is ready
+    fn jump_to_time(&mut self, time: f64) {
+        if let Some(number) = self
+            .cast_file
+            .as_ref()
+            .and_then(|cast_file| cast_file.event_number_at_time(time))
+        {
+            self.scroll_to_event(number);
+        }
+        self.preview.seek(time);
+    }
+
+    /// Scrolls the event grid so event `number` is the first one shown
+    fn scroll_to_event(&mut self, number: usize) {
+        let Some(count) = self.cast_file.as_ref().and_then(CastFile::event_count) else {
+            return;
+        };
+        // The first fetched event of a page isn't shown so the page starts one event earlier
+        let last_start = count.saturating_sub(EVENTS_PER_PAGE);
+        self.scroll_position = if last_start == 0 {
+            0.0
+        } else {
+            (number.saturating_sub(1) as f64 / last_start as f64).min(1.0) as f32
+        };
+    }
+
+    /// Compiles the entered pattern and starts scanning the file for it in the background, replacing a search that is still running. `then` is done with the matches once they are found
+    fn run_search(&mut self, then: AfterSearch) {
+        let Some(cast_file) = &self.cast_file else {
+            return;
+        };
+        match self.search.options.compile() {
+            Ok(compiled) => {
+                if let Some(running) = self.search.running.take() {
+                    running.task.cancel();
+                }
+                let snapshot = cast_file.snapshot();
+                let search = compiled.clone();
+                let task = Task::spawn(
+                    format!("Searching for {}", self.search.options.pattern),
+                    move |progress| search::find_matches(&snapshot, &search, progress),
+                );
+                self.search.running = Some(RunningSearch {
+                    task,
+                    search: compiled,
+                    revision: cast_file.revision(),
+                    then,
+                });
+            }
+            Err(e) => self.error_toast(format!("Invalid search pattern: {}", e)),
+        }
+    }
+
+    /// Takes the matches of the background search once it finishes
+    fn poll_search(&mut self) {
+        let Some(result) = self
+            .search
+            .running
+            .as_ref()
+            .and_then(|running| running.task.poll())
+        else {
+            return;
+        };
+        let running = self.search.running.take().expect("a search is running");
+        let Some(matches) = result else {
+            return;
+        };
+        self.search.matches = matches;
+        self.search.search = Some(running.search);
+        self.search.revision = running.revision;
+        self.search.current = None;
+        match running.then {
+            AfterSearch::Nothing => {}
+            AfterSearch::SelectFirst => self.select_match(0),
+            AfterSearch::Step(anchor, forward) => self.step_from(anchor, forward),
+        }
+    }
+
+    /// Moves to the next or previous match after the one last stepped to, wrapping around at the ends. Matches are found again first if the file changed since they were
+    fn step_match(&mut self, forward: bool) {
+        let anchor = self.search.current_match().map(|found| found.number);
+        if self
+            .cast_file
+            .as_ref()
+            .is_some_and(|cast_file| cast_file.revision() != self.search.revision)
+        {
+            self.run_search(AfterSearch::Step(anchor, forward));
+            return;
+        }
+        self.step_from(anchor, forward);
+    }
+
+    /// Moves to the first match after or before the event numbered `anchor`, wrapping around at the ends
+    fn step_from(&mut self, anchor: Option<usize>, forward: bool) {
+        let matches = &self.search.matches;
+        let position = match (anchor, forward) {
+            (Some(anchor), true) => matches.iter().position(|found| found.number > anchor),
+            (Some(anchor), false) => matches.iter().rposition(|found| found.number < anchor),
+            (None, _) => None,
+        };
+        let position = match position {
+            Some(position) => position,
+            None if matches.is_empty() => return,
+            None if forward => 0,
+            None => matches.len() - 1,
+        };
+        self.select_match(position);
+    }
+
+    /// Scrolls the grid and moves the preview to a match
+    fn select_match(&mut self, position: usize) {
+        let Some(found) = self.search.matches.get(position) else {
+            return;
+        };
+        let (number, time) = (found.number, found.positioned.event.time);
+        self.search.current = Some(position);
+        self.scroll_to_event(number);
+        self.preview.seek(time);
+    }
+
+    /// Search bar above the event grid with the matches listed by time and byte location
+    fn render_search(&mut self, ui: &mut Ui) {
+        let mut run = false;
+        let mut step = None;
+        ui.horizontal(|ui| {
+            let options = &mut self.search.options;
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut options.pattern)
+                    .hint_text("Search event data")
+                    .desired_width(240.0),
+            );
+            if response.changed() {
+                self.search.dry_run = None;
+            }
+            if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                run = true;
+            }
+            ui.checkbox(&mut options.regex, "Regex");
+            ui.checkbox(&mut options.case_sensitive, "Match Case");
+            ui.checkbox(&mut options.ignore_escapes, "Ignore Escapes")
+                .on_hover_text("Match the text left on screen without terminal escape sequences");
+            ui.menu_button("Types", |ui| {
+                for (code, name) in EVENT_CODES {
+                    let mut included = options.codes.contains(&code);
+                    if ui.checkbox(&mut included, name).changed() {
+                        if included {
+                            options.codes.push(code);
+                        } else {
+                            options.codes.retain(|&other| other != code);
+                        }
+                    }
+                }
+                ui.label(RichText::new("With none checked every type is searched").weak());
+            });
+            if ui.button("Find").clicked() {
+                run = true;
+            }
+
+            let found = !self.search.matches.is_empty();
+            if ui
+                .add_enabled(found, egui::Button::new("Previous Match"))
+                .clicked()
+            {
+                step = Some(false);
+            }
+            if ui
+                .add_enabled(found, egui::Button::new("Next Match"))
+                .clicked()
+            {
+                step = Some(true);
+            }
+            if self.search.search.is_some() {
+                let count = self.search.matches.len();
+                match self.search.current {
+                    Some(current) => ui.label(format!("{} of {}", current + 1, count)),
+                    None => ui.label(format!("{} matching events", count)),
+                };
+            }
+        });
+
+        let mut replace = None;
+        ui.horizontal(|ui| {
+            let state = &mut self.search;
+            if ui
+                .add(
+                    egui::TextEdit::singleline(&mut state.replacement)
+                        .hint_text("Replace with")
+                        .desired_width(240.0),
+                )
+                .changed()
+            {
+                state.dry_run = None;
+            }
+            ui.checkbox(&mut state.across_events, "Across Events")
+                .on_hover_text(
+                    "Also replace matches split between two output or input events in a row",
+                );
+            if ui
+                .button("Count")
+                .on_hover_text("Count what Replace All would change without changing anything")
+                .clicked()
+            {
+                replace = Some(true);
+            }
+            if ui.button("Replace All").clicked() {
+                replace = Some(false);
+            }
+            if let Some((count, events)) = state.dry_run {
+                ui.label(format!("{} matches in {} events", count, events));
+            }
+        });
+
+        if self.search.search.is_some() && !self.search.matches.is_empty() {
+            let mut selected = None;
+            ui.collapsing("Search Results", |ui| {
+                if self.search.matches.len() >= MAX_MATCHES {
+                    ui.label(
+                        RichText::new(format!("Showing the first {} matches", MAX_MATCHES)).weak(),
+                    );
+                }
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::vertical()
+                    .id_salt("search_results")
+                    .max_height(SEARCH_RESULTS_HEIGHT)
+                    .show_rows(ui, row_height, self.search.matches.len(), |ui, rows| {
+                        for position in rows {
+                            let found = &self.search.matches[position];
+                            let text = format!(
+                                "{:.3}s  byte {}  {}  {}",
+                                found.positioned.event.time,
+                                found.positioned.byte_location,
+                                found.positioned.event.data.get_type(),
+                                found.first_match()
+                            );
+                            if ui
+                                .selectable_label(self.search.current == Some(position), text)
+                                .clicked()
+                            {
+                                selected = Some(position);
+                            }
+                        }
+                    });
+            });
+            if let Some(position) = selected {
+                self.select_match(position);
+            }
+        }
+
+        if run {
+            self.run_search(AfterSearch::SelectFirst);
+        } else if let Some(forward) = step {
+            self.step_match(forward);
+        } else if let Some(dry_run) = replace {
+            self.replace_all(dry_run);
+        }
+    }
+
+    /// Works out in the background what replacing every match of the search in output and input data changes. It is applied as one undoable step once found, or with `dry_run` only counted
+    fn replace_all(&mut self, dry_run: bool) {
+        let compiled = match self.search.options.compile() {
+            Ok(compiled) => compiled,
+            Err(e) => {
+                self.error_toast(format!("Invalid search pattern: {}", e));
+                return;
+            }
+        };
+        let pattern = self.search.options.pattern.clone();
+        let replacement = self.search.replacement.clone();
+        let across_events = self.search.across_events;
+        self.start_job(
+            format!("Replacing {}", pattern),
+            false,
+            move |snapshot, progress| {
+                let plan = search::plan_replace(
+                    snapshot,
+                    &compiled,
+                    &replacement,
+                    across_events,
+                    progress,
+                )?;
+                Some(JobOutput::Replace {
+                    plan,
+                    pattern,
+                    dry_run,
+                })
+            },
+        );
+    }
+
+    /// Applies a replace worked out by `replace_all`
+    fn apply_replace(&mut self, plan: ReplacePlan, pattern: String, dry_run: bool) {
+        let Some(cast_file) = self.cast_file.as_mut() else {
+            return;
+        };
+        if dry_run || plan.count == 0 {
+            self.search.dry_run = Some((plan.count, plan.replacements.len()));
+            return;
+        }
+        let description = format!("Replace {} matches of {}", plan.count, pattern);
+        match cast_file.apply_replacements(description, &plan.replacements) {
+            Ok(()) => {
+                self.toasts.add(Toast {
+                    text: format!(
+                        "Replaced {} matches in {} events",
+                        plan.count,
+                        plan.replacements.len()
+                    )
+                    .into(),
+                    kind: ToastKind::Info,
+                    options: ToastOptions::default()
+                        .duration_in_seconds(5.0)
+                        .show_progress(true)
+                        .show_icon(true),
+                    ..Default::default()
+                });
+                self.search.dry_run = None;
+                self.run_search(AfterSearch::Nothing);
+            }
+            Err(e) => self.error_toast(format!("Failed to replace: {}", e)),
+        }
+    }
+
+    /// Progress bars of the work running in the background with buttons to cancel it
+    fn render_tasks(&mut self, ui: &mut Ui) {
+        if let Some(opening) = &self.opening {
+            ui.separator();
+            if opening.show(ui) {
+                opening.cancel();
+                self.opening = None;
+            }
+        }
+        if let Some(running) = &self.search.running {
+            ui.separator();
+            if running.task.show(ui) {
+                running.task.cancel();
+                self.search.running = None;
+            }
+        }
+        if let Some(scan) = &self.lint_scan {
+            ui.separator();
+            if scan.show(ui) {
+                scan.cancel();
+                self.lint_scan = None;
+            }
+        }
+        if let Some(job) = &self.job {
+            ui.separator();
+            if job.task.show(ui) {
+                job.task.cancel();
+                self.job = None;
+            }
+        }
+        if self.opening.is_some()
+            || self.search.running.is_some()
+            || self.marker_scan.is_some()
+            || self.lint_scan.is_some()
+            || self.job.is_some()
+        {
+            ui.ctx().request_repaint_after(TASK_REPAINT);
+        }
+    }
+
+    /// Go-to-time and marker stepping above the event grid. Markers are stepped through relative to the preview playhead
+    fn render_navigation(&mut self, ui: &mut Ui) {
+        let Some(cast_file) = &self.cast_file else {
+            return;
+        };
+        let end_time = cast_file.end_time();
+        let playhead = self.preview.playhead();
+        let previous = self
+            .markers()
+            .iter()
+            .rev()
+            .map(|marker| marker.event.time)
+            .find(|&time| time < playhead);
+        let next = self
+            .markers()
+            .iter()
+            .map(|marker| marker.event.time)
+            .find(|&time| time > playhead);
+
+        let mut jump = None;
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.goto_time)
+                    .range(0.0..=end_time)
+                    .speed(0.1)
+                    .prefix("Time: ")
+                    .suffix("s"),
+            );
+            if ui.button("Go To Time").clicked() {
+                jump = Some(self.goto_time);
+            }
+            ui.separator();
+            if ui
+                .add_enabled(previous.is_some(), egui::Button::new("Previous Marker"))
+                .clicked()
+            {
+                jump = previous;
+            }
+            if ui
+                .add_enabled(next.is_some(), egui::Button::new("Next Marker"))
+                .clicked()
+            {
+                jump = next;
+            }
+        });
+        if let Some(time) = jump {
+            self.jump_to_time(time);
+        }
+    }
+
+    /// Lists every marker with its label and time. Clicking one jumps the event grid and preview to it
+    fn render_markers(&mut self, ui: &mut Ui) {
+        ui.heading(RichText::new("Markers").color(Color32::LIGHT_BLUE));
+        ui.separator();
+        if let Some((_, scan)) = &self.marker_scan {
+            ui.add(egui::ProgressBar::new(scan.progress.fraction()).show_percentage());
+            if self.marker_cache.is_none() {
+                return;
+            }
+        }
+        if self.markers().is_empty() {
+            ui.label(RichText::new("No markers").weak());
+            return;
+        }
+
+        let playhead = self.preview.playhead();
+        let mut jump = None;
+        egui::ScrollArea::vertical()
+            .id_salt("markers")
+            .show(ui, |ui| {
+                for marker in self.markers() {
+                    let time = marker.event.time;
+                    let label = marker.event.data.get_unescaped_data();
+                    let text = if label.is_empty() {
+                        format!("{:.3}s", time)
+                    } else {
+                        format!("{:.3}s  {}", time, label)
+                    };
+                    if ui.selectable_label(time == playhead, text).clicked() {
+                        jump = Some(time);
+                    }
+                }
+            });
+        if let Some(time) = jump {
+            self.jump_to_time(time);
+        }
+    }
+
+    /// Lists every recorded edit. Undone edits are greyed out and clicking any entry walks the history to just after that entry
+    fn render_history(&mut self, ui: &mut Ui) {
+        let Some(cast_file) = self.cast_file.as_mut() else {
+            return;
+        };
+
+        ui.heading(RichText::new("History").color(Color32::LIGHT_BLUE));
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(cast_file.history().can_undo(), egui::Button::new("Undo"))
+                .clicked()
+            {
+                cast_file.undo();
+            }
+            if ui
+                .add_enabled(cast_file.history().can_redo(), egui::Button::new("Redo"))
+                .clicked()
+            {
+                cast_file.redo();
+            }
+        });
+        ui.separator();
+
+        let cursor = cast_file.history().cursor();
+        let mut target = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            // The unedited file is always the first state that can be returned to
+            if ui.selectable_label(cursor == 0, "Opened File").clicked() {
+                target = Some(0);
+            }
+            for (index, entry) in cast_file.history().entries().iter().enumerate() {
+                let text = if index < cursor {
+                    RichText::new(&entry.description)
+                } else {
+                    RichText::new(&entry.description).weak()
+                };
+                if ui.selectable_label(index + 1 == cursor, text).clicked() {
+                    target = Some(index + 1);
+                }
+            }
+        });
+
+        if let Some(target) = target {
+            while cast_file.history().cursor() > target && cast_file.undo() {}
+            while cast_file.history().cursor() < target && cast_file.redo() {}
+        }
+    }
+
+    /// Time edits that touch every event in a range. They are applied lazily so they are cheap even on huge files
+    fn render_edit_menu(&mut self, ui: &mut Ui) {
+        let Some(cast_file) = self.cast_file.as_mut() else {
+            return;
+        };
+        let end_time = cast_file.end_time();
+        let mut transform = None;
+        let mut error = None;
+        let mut find_typos = false;
+        let mut scan_secrets = None;
+
+        let (start, end) = &mut self.edit_range;
+        ui.label(RichText::new("Time Range").strong());
+        ui.add(
+            egui::DragValue::new(start)
+                .range(0.0..=end_time)
+                .speed(0.1)
+                .prefix("Start: ")
+                .suffix("s"),
+        );
+        ui.add(
+            egui::DragValue::new(end)
+                .range(0.0..=end_time)
+                .speed(0.1)
+                .prefix("End: ")
+                .suffix("s"),
+        );
+        let (start, end) = self.edit_range;
+        if ui
+            .button("Cut Range")
+            .on_hover_text("Delete the range and close the gap")
+            .clicked()
+        {
+            error = cast_file.cut(start, end).err().map(|e| e.to_string());
+            ui.close_menu();
+        }
+        if ui
+            .button("Trim to Range")
+            .on_hover_text("Keep only the range, starting it at 0s")
+            .clicked()
+        {
+            error = cast_file.trim(start, end).err().map(|e| e.to_string());
+            ui.close_menu();
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.edit_offset)
+                    .speed(0.1)
+                    .prefix("Offset: ")
+                    .suffix("s"),
+            );
+            if ui.button("Shift Range").clicked() {
+                transform = Some(TimeTransform::Offset {
+                    start,
+                    end,
+                    offset: self.edit_offset,
+                });
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.edit_speed)
+                    .range(0.01..=100.0)
+                    .speed(0.05)
+                    .prefix("Speed: ")
+                    .suffix("x"),
+            );
+            if ui.button("Change Speed").clicked() {
+                transform = Some(TimeTransform::Scale {
+                    start,
+                    end,
+                    factor: self.edit_speed,
+                });
+            }
+        });
+
+        ui.separator();
+        ui.label(RichText::new("Retime").strong())
+            .on_hover_text("Maps old times to new times with straight lines between the points, for example `0=0, 10=5, 20=20`");
+        ui.text_edit_singleline(&mut self.retime_points);
+        if ui.button("Apply Retime").clicked() {
+            match parse_points(&self.retime_points) {
+                Some(points) => transform = Some(TimeTransform::Piecewise { points }),
+                None => error = Some(format!("Invalid retime points: {}", self.retime_points)),
+            }
+        }
+
+        ui.separator();
+        ui.label(RichText::new("Idle Time").strong());
+        let idle = &mut self.idle_compression;
+        ui.add(
+            egui::DragValue::new(&mut idle.max_gap)
+                .range(0.0..=f64::MAX)
+                .speed(0.1)
+                .prefix("Max Pause: ")
+                .suffix("s"),
+        );
+        let mut separate_input_limit = idle.max_gap_after_input.is_some();
+        ui.checkbox(&mut separate_input_limit, "Separate Limit After Input");
+        if separate_input_limit {
+            let mut limit = idle.max_gap_after_input.unwrap_or(idle.max_gap);
+            ui.add(
+                egui::DragValue::new(&mut limit)
+                    .range(0.0..=f64::MAX)
+                    .speed(0.1)
+                    .prefix("Max Pause After Input: ")
+                    .suffix("s"),
+            );
+            idle.max_gap_after_input = Some(limit);
+        } else {
+            idle.max_gap_after_input = None;
+        }
+        ui.horizontal(|ui| {
+            let mut compress = None;
+            if ui.button("Compress Idle Time").clicked() {
+                compress = Some((0.0, f64::INFINITY));
+            }
+            if ui.button("Compress in Range").clicked() {
+                compress = Some((start, end));
+            }
+            if let Some((start, end)) = compress {
+                idle.start = start;
+                idle.end = end;
+                if let Err(e) = cast_file.compress_idle(idle) {
+                    error = Some(e.to_string());
+                }
+                ui.close_menu();
+            }
+        });
+
+        ui.separator();
+        if ui
+            .button("Clean Up Typos...")
+            .on_hover_text("Find typing corrected with backspace")
+            .clicked()
+        {
+            find_typos = true;
+            ui.close_menu();
+        }
+
+        if ui
+            .button("Redact Secrets...")
+            .on_hover_text("Find tokens, keys and typed passwords to mask before publishing")
+            .clicked()
+        {
+            match self.redaction.redactor() {
+                Ok(redactor) => scan_secrets = Some(redactor),
+                Err(e) => error = Some(e),
+            }
+            ui.close_menu();
+        }
+
+        ui.separator();
+        if ui
+            .button("Append Recordings...")
+            .on_hover_text("Join other recordings after this one")
+            .clicked()
+        {
+            self.concatenation
+                .get_or_insert_with(Concatenation::default);
+            ui.close_menu();
+        }
+        if ui
+            .button("Split Recording...")
+            .on_hover_text("Save parts between markers, times or long pauses as separate files")
+            .clicked()
+        {
+            self.split.get_or_insert_with(SplitState::default);
+            ui.close_menu();
+        }
+
+        ui.separator();
+        let problems = self
+            .lint_report
+            .as_ref()
+            .map_or(0, |report| report.problems.len() + report.omitted);
+        if ui
+            .button(format!("Problems ({})...", problems))
+            .on_hover_text("Lines of the file that are broken or that players read differently")
+            .clicked()
+        {
+            self.show_problems = true;
+            ui.close_menu();
+        }
+
+        if let Some(transform) = transform {
+            if let Err(e) = cast_file.transform_time(transform) {
+                error = Some(e.to_string());
+            }
+            ui.close_menu();
+        }
+        if let Some(e) = error {
+            self.error_toast(format!("Failed to apply edit: {}", e));
+        }
+        if find_typos {
+            self.start_job(
+                "Looking for typos".to_string(),
+                false,
+                |snapshot, progress| cleanup::find_typos(snapshot, progress).map(JobOutput::Typos),
+            );
+        }
+        if let Some(redactor) = scan_secrets {
+            self.scan_secrets(redactor);
+        }
+    }
+
+    /// Lists the proposed typo fixes so they can be checked before being applied together as one undoable edit
+    fn render_typo_review(&mut self, ctx: &Context) {
+        let (Some(review), Some(cast_file)) = (self.typo_review.as_mut(), self.cast_file.as_mut())
+        else {
+            return;
+        };
+        if review.revision != cast_file.revision() {
+            self.typo_review = None;
+            return;
+        }
+
+        let mut open = true;
+        let mut apply = false;
+        egui::Window::new("Typo Cleanup")
+            .open(&mut open)
+            .default_height(400.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Select All").clicked() {
+                        review.selected.fill(true);
+                    }
+                    if ui.button("Select None").clicked() {
+                        review.selected.fill(false);
+                    }
+                    apply = ui.button("Apply Selected").clicked();
+                });
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("typo_fixes")
+                        .num_columns(4)
+                        .spacing([8.0, 4.0])
+                        .show(ui, |ui| {
+                            for (fix, selected) in review.fixes.iter().zip(&mut review.selected) {
+                                ui.checkbox(selected, "");
+                                ui.label(
+                                    RichText::new(format!("{:.3}s", fix.start_time())).monospace(),
+                                );
+                                ui.label(
+                                    RichText::new(format!("{:?}", fix.erased_text)).monospace(),
+                                )
+                                .on_hover_text(format!("Removes {} events", fix.events.len()));
+                                ui.label(format!("-{:.3}s", fix.saved_time()));
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+
+        if apply {
+            let fixes: Vec<TypoFix> = review
+                .fixes
+                .iter()
+                .zip(&review.selected)
+                .filter(|(_, selected)| **selected)
+                .map(|(fix, _)| fix.clone())
+                .collect();
+            let result = cast_file.apply_typo_fixes(&fixes);
+            self.typo_review = None;
+            if let Err(e) = result {
+                self.error_toast(format!("Failed to clean up typos: {}", e));
+            }
+        } else if !open {
+            self.typo_review = None;
+        }
+    }
+
+    /// Review window for the secrets found by a scan. Selected findings are masked as one undoable step and the rest are remembered as reviewed so strict saving lets them through
+    fn render_redaction_review(&mut self, ctx: &Context) {
+        let (Some(review), Some(cast_file)) =
+            (self.redaction_review.as_mut(), self.cast_file.as_mut())
+        else {
+            return;
+        };
+        if review.revision != cast_file.revision() {
+            self.redaction_review = None;
+            return;
+        }
+
+        let settings = &mut self.redaction;
+        let mut open = true;
+        let mut scan = false;
+        let mut apply = false;
+        egui::Window::new("Redact Secrets")
+            .open(&mut open)
+            .default_height(400.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Mask:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut settings.mask)
+                            .char_limit(1)
+                            .desired_width(20.0)
+                            .font(egui::TextStyle::Monospace),
+                    );
+                    ui.checkbox(&mut settings.strict, "Strict Saving").on_hover_text(
+                        "Refuse to save while high confidence secrets are neither redacted nor reviewed",
+                    );
+                });
+                ui.label("Custom patterns, one regular expression per line:");
+                ui.add(
+                    egui::TextEdit::multiline(&mut settings.patterns)
+                        .desired_rows(2)
+                        .font(egui::TextStyle::Monospace),
+                );
+                ui.horizontal(|ui| {
+                    scan = ui.button("Scan Again").clicked();
+                    if ui.button("Select All").clicked() {
+                        review.selected.fill(true);
+                    }
+                    if ui.button("Select None").clicked() {
+                        review.selected.fill(false);
+                    }
+                    apply = ui
+                        .button("Redact Selected")
+                        .on_hover_text("Findings left unselected are marked as reviewed")
+                        .clicked();
+                });
+                ui.separator();
+                if review.findings.is_empty() {
+                    ui.label(RichText::new("No secrets found").weak());
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("redaction_findings")
+                        .num_columns(5)
+                        .spacing([8.0, 4.0])
+                        .show(ui, |ui| {
+                            for (finding, selected) in review.findings.iter().zip(&mut review.selected) {
+                                ui.checkbox(selected, "");
+                                ui.label(
+                                    RichText::new(format!("{:.3}s", finding.positioned.event.time))
+                                        .monospace(),
+                                )
+                                .on_hover_text(format!("Byte {}", finding.positioned.byte_location));
+                                let confidence = match finding.confidence {
+                                    Confidence::High => RichText::new("High").color(Color32::LIGHT_RED),
+                                    Confidence::Low => RichText::new("Low").weak(),
+                                };
+                                ui.label(confidence);
+                                ui.label(&finding.detector);
+                                ui.label(RichText::new(finding.preview()).monospace());
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+
+        let mut error = None;
+        if scan {
+            match settings.redactor() {
+                Ok(redactor) => self.scan_secrets(redactor),
+                Err(e) => error = Some(e),
+            }
+        } else if apply {
+            match settings.redactor() {
+                Ok(redactor) => {
+                    let mut selected = Vec::new();
+                    for (finding, chosen) in review.findings.iter().zip(&review.selected) {
+                        if *chosen {
+                            selected.push(finding.clone());
+                        } else {
+                            settings.reviewed.insert(finding.key());
+                        }
+                    }
+                    if !selected.is_empty() {
+                        let description = format!("Redact {} secrets", selected.len());
+                        if let Err(e) =
+                            cast_file.apply_replacements(description, &redactor.plan(&selected))
+                        {
+                            error = Some(format!("Failed to redact secrets: {}", e));
+                        }
+                    }
+                    self.redaction_review = None;
+                }
+                Err(e) => error = Some(e),
+            }
+        } else if !open {
+            self.redaction_review = None;
+        }
+        if let Some(e) = error {
+            self.error_toast(e);
+        }
+    }
+
+    /// Takes the report of the check started on open once it is done. Errors open the problems window as they mean some of the file can't be shown
+    fn poll_lint(&mut self) {
+        let Some(result) = self.lint_scan.as_ref().and_then(Task::poll) else {
+            return;
+        };
+        self.lint_scan = None;
+        let Some(report) = result else {
+            return;
+        };
+        let errors = report.errors();
+        if errors > 0 {
+            self.show_problems = true;
+            self.toasts.add(Toast {
+                text: format!(
+                    "Found {} errors in the file, lines with errors are left out",
+                    errors
+                )
+                .into(),
+                kind: ToastKind::Warning,
+                options: ToastOptions::default()
+                    .duration_in_seconds(10.0)
+                    .show_progress(true)
+                    .show_icon(true),
+                ..Default::default()
+            });
+        }
+        self.lint_report = Some(report);
+    }
+
+    /// Runs `work` over a snapshot of the open file in the background unless another job is still running. `writes_file` is set for saves and exports, which finish even if another file is opened in the meantime
+    fn start_job(
+        &mut self,
+        label: String,
+        writes_file: bool,
+        work: impl FnOnce(&Snapshot, &Progress) -> Option<JobOutput> + Send + 'static,
+    ) {
+        if let Some(job) = &self.job {
+            let text = format!("{} is still running", job.task.label);
+            self.error_toast(text);
+            return;
+        }
+        let Some(cast_file) = &self.cast_file else {
+            return;
+        };
+        let snapshot = cast_file.snapshot();
+        let revision = snapshot.revision;
+        self.job = Some(Job {
+            task: Task::spawn(label, move |progress| work(&snapshot, progress)),
+            revision: Some(revision),
+            writes_file,
+        });
+    }
+
+    /// Applies what the job found once it finishes. Findings are dropped if the file changed in the meantime, as the events they point at may not be there any more
+    fn poll_job(&mut self) {
+        let Some(result) = self.job.as_ref().and_then(|job| job.task.poll()) else {
+            return;
+        };
+        let job = self.job.take().expect("a job is running");
+        let Some(output) = result else {
+            return;
+        };
+        let current = job.revision.filter(|&revision| {
+            self.cast_file
+                .as_ref()
+                .is_some_and(|cast_file| cast_file.revision() == revision)
+        });
+        match (output, current) {
+            (JobOutput::Saved(Ok(())) | JobOutput::Exported(Ok(())), _) => {}
+            (JobOutput::Saved(Err(e)), _) => {
+                self.error_toast(format!("Failed to Save File: {}", e))
+            }
+            (JobOutput::Exported(Err(e)), _) => {
+                self.error_toast(format!("Failed to Export GIF: {}", e))
+            }
+            (JobOutput::Unreviewed(findings), current) => {
+                self.error_toast(format!(
+                    "Failed to Save File: {}",
+                    CastError::UnredactedSecrets(findings.len())
+                ));
+                if let Some(revision) = current {
+                    self.redaction_review = Some(RedactionReview::new(revision, findings));
+                }
+            }
+            // Findings of a file that was closed since are of no use
+            (_, None) if job.revision.is_none() => {}
+            (_, None) => self
+                .error_toast("The file changed before the scan finished, run it again".to_string()),
+            (JobOutput::Typos(fixes), Some(_)) if fixes.is_empty() => {
+                self.error_toast("Failed to apply edit: No corrected typos found".to_string())
+            }
+            (JobOutput::Typos(fixes), Some(revision)) => {
+                self.typo_review = Some(TypoReview {
+                    revision,
+                    selected: vec![true; fixes.len()],
+                    fixes,
+                });
+            }
+            (JobOutput::Secrets(findings), Some(revision)) => {
+                self.redaction_review = Some(RedactionReview::new(revision, findings));
+            }
+            (
+                JobOutput::Replace {
+                    plan,
+                    pattern,
+                    dry_run,
+                },
+                Some(_),
+            ) => self.apply_replace(plan, pattern, dry_run),
+        }
+    }
+
+    /// Scans for secrets in the background and opens the review window with what it finds
+    fn scan_secrets(&mut self, redactor: Redactor) {
+        self.start_job(
+            "Scanning for secrets".to_string(),
+            false,
+            move |snapshot, progress| redactor.scan(snapshot, progress).map(JobOutput::Secrets),
+        );
+    }
+
+    /// Saves the edits to `path` in the background. Strict saving first scans for high confidence secrets that were neither redacted nor reviewed and refuses to save if there are any
+    fn save_file(&mut self, path: PathBuf) {
+        let Some(cast_file) = &self.cast_file else {
+            return;
+        };
+        let version = cast_file.save_version();
+        let strict = if self.redaction.strict {
+            match self.redaction.redactor() {
+                Ok(redactor) => Some((redactor, self.redaction.reviewed.clone())),
+                Err(e) => {
+                    self.error_toast(format!("Failed to Save File: {}", e));
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        self.start_job(
+            format!("Saving {}", name.to_string_lossy()),
+            true,
+            move |snapshot, progress| {
+                if let Some((redactor, reviewed)) = strict {
+                    let unreviewed = redactor.unreviewed(snapshot, &reviewed, progress)?;
+                    if !unreviewed.is_empty() {
+                        return Some(JobOutput::Unreviewed(unreviewed));
+                    }
+                }
+                match snapshot.save_to_file_as(&path, version, progress) {
+                    Err(CastError::Cancelled) => None,
+                    result => Some(JobOutput::Saved(result)),
+                }
+            },
+        );
+    }
+
+    /// Renders the edited recording to a GIF at `path` in the background
+    fn export_gif(&mut self, path: PathBuf) {
+        let options = self.gif_options.clone();
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        self.start_job(
+            format!("Exporting {}", name.to_string_lossy()),
+            true,
+            move |snapshot, progress| match snapshot.export_gif(&path, &options, progress) {
+                Err(CastError::Cancelled) => None,
+                result => Some(JobOutput::Exported(result)),
+            },
+        );
+    }
+
+    /// Scrolls the event grid to the events read from the line `problem` was found on, which is where it shows
+    fn jump_to_problem(&mut self, problem: &Problem) {
+        let Some(cast_file) = &self.cast_file else {
+            return;
+        };
+        let Some(byte) = cast_file.problem_location(problem) else {
+            self.error_toast("Lines of v1 recordings can't be shown among the events".to_string());
+            return;
+        };
+        match cast_file.event_number(byte) {
+            Some(number) => self.scroll_to_event(number),
+            None => self.error_toast("The events are still being indexed".to_string()),
+        }
+    }
+
+    /// Problems of the file as it is on disk with their line and byte. Clicking one scrolls the event grid to where the line is
+    fn render_problems(&mut self, ctx: &Context) {
+        if !self.show_problems {
+            return;
+        }
+        let Some(cast_file) = &self.cast_file else {
+            return;
+        };
+
+        let mut open = true;
+        let mut check = false;
+        let mut jump = None;
+        egui::Window::new("Problems")
+            .open(&mut open)
+            .default_height(400.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    match &self.lint_report {
+                        Some(report) => ui.label(format!(
+                            "{} events, {} errors, {} warnings",
+                            report.events,
+                            report.errors(),
+                            report.warnings()
+                        )),
+                        None => ui.label("Checking..."),
+                    };
+                    check = ui
+                        .add_enabled(self.lint_scan.is_none(), egui::Button::new("Check Again"))
+                        .on_hover_text("Check the file as it is on disk, edits left aside")
+                        .clicked();
+                });
+                let Some(report) = &self.lint_report else {
+                    return;
+                };
+                if report.source_version == 1 {
+                    ui.label(
+                        RichText::new("Lines of v1 recordings can't be shown among the events")
+                            .weak(),
+                    );
+                }
+                if report.omitted > 0 {
+                    ui.label(
+                        RichText::new(format!(
+                            "Showing the first {} of {} problems",
+                            MAX_PROBLEMS,
+                            MAX_PROBLEMS + report.omitted
+                        ))
+                        .weak(),
+                    );
+                }
+                ui.separator();
+                if report.problems.is_empty() {
+                    ui.label(RichText::new("No problems").weak());
+                    return;
+                }
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::vertical().id_salt("problems").show_rows(
+                    ui,
+                    row_height,
+                    report.problems.len(),
+                    |ui, rows| {
+                        for problem in &report.problems[rows] {
+                            ui.horizontal(|ui| {
+                                let severity = match problem.severity {
+                                    Severity::Error => {
+                                        RichText::new("Error").color(Color32::LIGHT_RED)
+                                    }
+                                    Severity::Warning => RichText::new("Warning").weak(),
+                                };
+                                ui.label(severity);
+                                let text = format!(
+                                    "line {}  byte {}  {}",
+                                    problem.line, problem.byte, problem.message
+                                );
+                                if ui.selectable_label(false, text).clicked() {
+                                    jump = Some(problem.clone());
+                                }
+                            });
+                        }
+                    },
+                );
+            });
+
+        if check {
+            self.lint_report = None;
+            self.lint_scan = Some(lint_task(cast_file));
+        }
+        if let Some(problem) = jump {
+            self.jump_to_problem(&problem);
+        }
+        if !open {
+            self.show_problems = false;
+        }
+    }
+
+    /// Window listing the recordings to append to the open file. The joined recording is written to a new file which is then opened in place of the open file
+    fn render_concatenation(&mut self, ctx: &Context) {
+        let (Some(concatenation), Some(cast_file)) =
+            (self.concatenation.as_mut(), self.cast_file.as_ref())
+        else {
+            return;
+        };
+
+        let name = |path: &Path| {
+            path.file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .into_owned()
+        };
+        let mut names = vec![name(&cast_file.file_path)];
+        names.extend(concatenation.files.iter().map(|file| name(file)));
+
+        let mut open = true;
+        let mut add = false;
+        let mut save = false;
+        egui::Window::new("Append Recordings")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let mut removed = None;
+                egui::Grid::new("concatenated_recordings")
+                    .num_columns(2)
+                    .spacing([8.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label(RichText::new(&names[0]).strong());
+                        ui.label(RichText::new("Open recording").weak());
+                        ui.end_row();
+                        for (index, file) in concatenation.files.iter().enumerate() {
+                            ui.label(&names[index + 1])
+                                .on_hover_text(file.display().to_string());
+                            if ui.button("Remove").clicked() {
+                                removed = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if let Some(index) = removed {
+                    concatenation.files.remove(index);
+                    let options = &mut concatenation.options;
+                    options.keep_header = options.keep_header.min(concatenation.files.len());
+                }
+                add = ui.button("Add Recording...").clicked();
+
+                ui.separator();
+                let options = &mut concatenation.options;
+                ui.add(
+                    egui::DragValue::new(&mut options.gap)
+                        .range(0.0..=f64::MAX)
+                        .speed(0.1)
+                        .prefix("Gap: ")
+                        .suffix("s"),
+                )
+                .on_hover_text("Pause between the end of one recording and the start of the next");
+                ui.horizontal(|ui| {
+                    ui.label("Keep Theme and Environment of:");
+                    egui::ComboBox::from_id_salt("concat_keep_header")
+                        .selected_text(&names[options.keep_header])
+                        .show_ui(ui, |ui| {
+                            for (index, name) in names.iter().enumerate() {
+                                ui.selectable_value(&mut options.keep_header, index, name);
+                            }
+                        });
+                });
+                save = ui
+                    .add_enabled(
+                        !concatenation.files.is_empty(),
+                        egui::Button::new("Join and Save As..."),
+                    )
+                    .on_hover_text("The joined recording is opened in place of this one, edits made so far included")
+                    .clicked();
+            });
+
+        if add {
+            let filter = Box::new({
+                |path: &Path| -> bool { path.extension() == Some(OsStr::new("cast")) }
+            });
+            let mut dialog =
+                FileDialog::open_file(cast_file.file_path.parent().map(Path::to_path_buf))
+                    .show_files_filter(filter);
+            dialog.open();
+            self.concat_dialog = Some(dialog);
+        } else if save {
+            let mut stem = cast_file
+                .file_path
+                .file_stem()
+                .unwrap_or_default()
+                .to_owned();
+            stem.push("_joined.cast");
+            let mut dialog = FileDialog::save_file(Some(cast_file.file_path.with_file_name(stem)));
+            dialog.open();
+            self.concat_dialog = Some(dialog);
+        } else if !open {
+            self.concatenation = None;
+        }
+    }
+
+    /// Joins the open file and the recordings picked in the concatenation window into `path` and opens the result
+    fn concatenate(&mut self, path: PathBuf) {
+        let (Some(concatenation), Some(cast_file)) =
+            (self.concatenation.as_ref(), self.cast_file.as_ref())
+        else {
+            return;
+        };
+        let result = concatenation
+            .files
+            .iter()
+            .map(|file| CastFile::new(file.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|others| cast_file.concatenate(&others, &path, &concatenation.options));
+        match result {
+            Ok(()) => {
+                let count = concatenation.files.len() + 1;
+                self.concatenation = None;
+                self.open_file(path);
+                self.toasts.add(Toast {
+                    text: format!("Joined {} recordings", count).into(),
+                    kind: ToastKind::Info,
+                    options: ToastOptions::default()
+                        .duration_in_seconds(5.0)
+                        .show_progress(true)
+                        .show_icon(true),
+                    ..Default::default()
+                });
+            }
+            Err(e) => self.error_toast(format!("Failed to Join Recordings: {}", e)),
+        }
+    }
+
+    /// Window choosing where to split the open file, with a preview of the parts before they are saved
+    fn render_split(&mut self, ctx: &Context) {
+        let (Some(split), Some(cast_file)) = (self.split.as_mut(), self.cast_file.as_ref()) else {
+            return;
+        };
+
+        let mut open = true;
+        let mut preview = false;
+        let mut save = false;
+        egui::Window::new("Split Recording")
+            .open(&mut open)
+            .default_height(400.0)
+            .show(ctx, |ui| {
+                let mode = split.mode;
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut split.mode, SplitMode::Markers, "At Markers");
+                    ui.selectable_value(&mut split.mode, SplitMode::Times, "At Times");
+                    ui.selectable_value(&mut split.mode, SplitMode::IdleGaps, "At Pauses");
+                });
+                let mut changed = split.mode != mode;
+                match split.mode {
+                    SplitMode::Markers => {
+                        ui.label(RichText::new("Every marker starts a new part").weak());
+                    }
+                    SplitMode::Times => {
+                        changed |= ui
+                            .add(
+                                egui::TextEdit::singleline(&mut split.times)
+                                    .hint_text("Seconds separated by commas, like 30, 95.5"),
+                            )
+                            .changed();
+                    }
+                    SplitMode::IdleGaps => {
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut split.idle)
+                                    .range(0.1..=f64::MAX)
+                                    .speed(0.1)
+                                    .prefix("Pauses Longer Than: ")
+                                    .suffix("s"),
+                            )
+                            .changed();
+                    }
+                }
+                if changed {
+                    split.parts = None;
+                }
+
+                ui.horizontal(|ui| {
+                    preview = ui.button("Preview Parts").clicked();
+                    save = ui
+                        .button("Save Parts As...")
+                        .on_hover_text("Parts are saved beside the chosen file with _1, _2 and so on added to its name")
+                        .clicked();
+                });
+                ui.separator();
+                let Some((revision, parts)) = &split.parts else {
+                    return;
+                };
+                if *revision != cast_file.revision() {
+                    ui.label(RichText::new("The recording changed since this preview").weak());
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("split_parts")
+                        .num_columns(4)
+                        .spacing([8.0, 4.0])
+                        .show(ui, |ui| {
+                            for (index, part) in parts.iter().enumerate() {
+                                ui.label(RichText::new(format!("Part {}", index + 1)).strong());
+                                ui.label(
+                                    RichText::new(format!("{:.3}s", part.start)).monospace(),
+                                );
+                                ui.label(format!("{:.3}s long", part.duration()));
+                                ui.label(format!("{} events", part.events));
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+
+        if preview || save {
+            match split.plan(cast_file) {
+                Ok(_) if save => {
+                    let mut dialog = FileDialog::save_file(Some(cast_file.file_path.clone()));
+                    dialog.open();
+                    self.split_dialog = Some(dialog);
+                }
+                Ok(_) => {}
+                Err(e) => self.error_toast(format!("Failed to split: {}", e)),
+            }
+        } else if !open {
+            self.split = None;
+        }
+    }
+
+    /// Saves the parts of the split window next to `base`
+    fn save_split(&mut self, base: PathBuf) {
+        let (Some(split), Some(cast_file)) = (self.split.as_mut(), self.cast_file.as_ref()) else {
+            return;
+        };
+        let result = split
+            .plan(cast_file)
+            .and_then(|parts| cast_file.split(parts, &base).map_err(|e| e.to_string()));
+        match result {
+            Ok(paths) => {
+                self.toasts.add(Toast {
+                    text: format!("Saved {} parts", paths.len()).into(),
+                    kind: ToastKind::Info,
+                    options: ToastOptions::default()
+                        .duration_in_seconds(5.0)
+                        .show_progress(true)
+                        .show_icon(true),
+                    ..Default::default()
+                });
+            }
+            Err(e) => self.error_toast(format!("Failed to Save Parts: {}", e)),
+        }
+    }
+
+    /// Editable header fields. Edits collect in a draft that is applied to the file as one undoable step
+    fn render_header(&mut self, ui: &mut Ui) {
+        let Some(cast_file) = self.cast_file.as_mut() else {
+            return;
+        };
+        if self
+            .header_draft
+            .as_ref()
+            .is_none_or(|draft| draft.revision != cast_file.revision())
+        {
+            self.header_draft = Some(HeaderDraft::new(cast_file));
+        }
+        let draft = self.header_draft.as_mut().expect("draft was just created");
+        let edited = draft.to_header();
+        let changed = edited != cast_file.header;
+        let mut apply = false;
+        let mut discard = false;
+        let mut preset = None;
+
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.heading(RichText::new("File Information:").color(Color32::LIGHT_BLUE));
+                apply = ui
+                    .add_enabled(changed, egui::Button::new("Apply Header Changes"))
+                    .clicked();
+                discard = ui
+                    .add_enabled(changed, egui::Button::new("Discard"))
+                    .clicked();
+            });
+
+            let header = &mut draft.header;
+            ui.group(|ui| {
+                ui.horizontal(|ui| {
+                    optional_text(ui, "Title:", &mut header.title);
+                    ui.add_space(20.0);
+                    optional_text(ui, "Command:", &mut header.command);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Version:").strong());
+                    ui.label(format!("{}", header.version));
+                    ui.add_space(20.0);
+                    ui.label(RichText::new("Dimensions:").strong());
+                    ui.add(egui::DragValue::new(&mut header.width).range(1..=u16::MAX));
+                    ui.label("x");
+                    ui.add(egui::DragValue::new(&mut header.height).range(1..=u16::MAX));
+                });
+
+                ui.horizontal(|ui| {
+                    let now = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map_or(0, |since| since.as_secs());
+                    optional_number(ui, "Timestamp:", &mut header.timestamp, now, "");
+                    ui.add_space(20.0);
+                    optional_number(
+                        ui,
+                        "Idle Time Limit:",
+                        &mut header.idle_time_limit,
+                        DEFAULT_IDLE_TIME_LIMIT,
+                        "s",
+                    );
+                    // Kept up to date by the time edits so it isn't edited directly
+                    if let Some(duration) = header.duration {
+                        ui.add_space(20.0);
+                        ui.label(RichText::new("Duration:").strong());
+                        ui.label(format!("{}s", duration));
+                    }
+                });
+            });
+
+            ui.add_space(10.0);
+            ui.collapsing("Environment Variables", |ui| {
+                let mut removed = None;
+                egui::Grid::new("environment")
+                    .num_columns(3)
+                    .spacing([5.0, 5.0])
+                    .show(ui, |ui| {
+                        for (index, (key, value)) in draft.env.iter_mut().enumerate() {
+                            ui.add(egui::TextEdit::singleline(key).desired_width(120.0));
+                            ui.add(egui::TextEdit::singleline(value).desired_width(240.0));
+                            if ui.button("Remove").clicked() {
+                                removed = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if let Some(index) = removed {
+                    draft.env.remove(index);
+                }
+                if ui.button("Add Variable").clicked() {
+                    draft.env.push((String::new(), String::new()));
+                }
+            });
+
+            ui.add_space(10.0);
+            ui.collapsing("Theme Settings", |ui| {
+                let mut custom = draft.header.theme.is_some();
+                if ui
+                    .checkbox(&mut custom, "Custom theme")
+                    .on_hover_text("Without a theme players use their own colors")
+                    .changed()
+                {
+                    draft.header.theme = custom.then(Theme::default);
+                }
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("theme_preset")
+                        .selected_text("Apply Preset")
+                        .show_ui(ui, |ui| {
+                            for name in themes::preset_names() {
+                                if ui.selectable_label(false, name).clicked() {
+                                    preset = Some(name);
+                                }
+                            }
+                        });
+                    if ui
+                        .button("Import Theme...")
+                        .on_hover_text(
+                            "Xresources, Alacritty, kitty, Windows Terminal or iTerm2 colors",
+                        )
+                        .clicked()
+                    {
+                        let mut theme_dialog = FileDialog::open_file(dirs::home_dir());
+                        theme_dialog.open();
+                        self.theme_dialog = Some(theme_dialog);
+                    }
+                });
+                let Some(theme) = draft.header.theme.as_mut() else {
+                    return;
+                };
+
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Foreground:").strong());
+                    color_edit(ui, &mut theme.fg);
+                    ui.add_space(20.0);
+                    ui.label(RichText::new("Background:").strong());
+                    color_edit(ui, &mut theme.bg);
+                });
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Color Palette:").strong());
+                    // The format only allows 8 or 16 colors. Growing to 16 starts the bright colors as copies of the normal ones
+                    if ui
+                        .selectable_label(theme.palette.len() == 8, "8 Colors")
+                        .clicked()
+                    {
+                        theme.palette.truncate(8);
+                    }
+                    if ui
+                        .selectable_label(theme.palette.len() == 16, "16 Colors")
+                        .clicked()
+                        && theme.palette.len() == 8
+                    {
+                        theme.palette.extend_from_within(..);
+                    }
+                });
+
+                egui::Grid::new("color_palette")
+                    .spacing([5.0, 5.0])
+                    .show(ui, |ui| {
+                        let mut col_count = 0;
+                        let cols_per_row = 8; // Making this 8 allows for the clear indication if one is using 1 or 2 rows for the palette and thus 8 or 16 values for the `.cast` format
+
+                        for color in theme.palette.iter_mut() {
+                            color_edit(ui, color);
+
+                            col_count += 1;
+                            if col_count % cols_per_row == 0 {
+                                ui.end_row(); // This serves both to end the row when 8 colors are displayed and end the grid object row so that future ui layouts are not horizontal to the grid. Since we know that the number of colors are either 8 or 16 this mod guarantees that there won't be any misplaced objects horizontally aligned to the grid and organizes the colors
+                            }
+                        }
+                    });
+            });
+        });
+
+        if apply {
+            if let Err(e) = cast_file.edit_header("Edit header".to_string(), edited) {
+                self.error_toast(format!("Failed to edit header: {}", e));
+            }
+        } else if discard {
+            self.header_draft = None;
+        } else if let Some(name) = preset {
+            let theme = themes::preset(name).expect("preset names come from the list");
+            self.apply_theme(format!("Apply {} theme", name), theme);
+        }
+    }
+
+    fn render_events(&mut self, ui: &mut Ui) {
+        if let Some(cast_file) = &self.cast_file {
+            // Get a specified number of events starting from the scroll position passed into the memory map so that we don't need to have all the file in memory to read and edit it. This makes the editor really fast. Once the line index is ready the position picks an event number, until then it picks a byte of the file
+            let page = cast_file.event_count().and_then(|count| {
+                let first = page_start(self.scroll_position, count);
+                cast_file
+                    .get_events(first, EVENTS_PER_PAGE)
+                    .map(|events| (first, count, events))
+            });
+            let events = match page {
+                Some((first, count, events)) => {
+                    // The first and last events of the page are only fetched as neighbours of the shown ones
+                    if events.len() >= 3 {
+                        ui.label(format!(
+                            "Events {} - {} of {}",
+                            first + 2,
+                            first + events.len() - 1,
+                            count
+                        ));
+                    } else {
+                        ui.label(format!("{} events", count));
+                    }
+                    Ok(events)
+                }
+                None => {
+                    ui.horizontal(|ui| {
+                        ui.label("Indexing events...");
+                        ui.add(
+                            egui::ProgressBar::new(cast_file.index_progress())
+                                .desired_width(TASK_PROGRESS_WIDTH)
+                                .show_percentage(),
+                        );
+                    });
+                    // Nothing else repaints when the index finishes in the background
+                    ui.ctx().request_repaint_after(TASK_REPAINT);
+                    cast_file.get_lines(self.scroll_position, EVENTS_PER_PAGE)
+                }
+            };
+            match events {
+                Ok(events) => {
+                    let mut action_error = None;
+                    egui::Grid::new("events_grid")
+                        .num_columns(4)
+                        .spacing([8.0, 4.0])
+                        .show(ui, |ui| {
+                            // Need enumerated line number for unique IDs for each rendered line
+                            for (
+                                line,
+                                event_position_window,
+                            ) in events.windows(3).enumerate()
+                            // todo handle first element (as lines are inserted before the checks are last to first)
+                            {
+                                let EventPositioned {
+                                    event,
+                                    byte_location,
+                                    chain_index,
+                                } = &event_position_window[1];
+                                egui::ComboBox::from_id_salt(format!("button_{}", line))
+                                    .selected_text("Choose...")
+                                    .show_ui(ui, |ui| {
+                                        // ! Double check if unwrap or 0 handles all expected conditions
+                                        let order = self.cast_file.as_ref().expect("Unable to get the cast handle as mut for modification").get_order(&event_position_window[1]);
+
+                                        if ui.button("Insert New Line Before This").clicked() {
+                                            if let Err(e) = self.cast_file.as_mut().expect("Unable to get the cast handle as mut for modification").action(
+                                                ModificationAction::Addition(Event { time: (event_position_window[0].event.time + event.time) / 2.0, data: EventData::Output("".to_string()) }),
+                                                order,
+                                                &event_position_window[1],
+                                                Some(&event_position_window[0]),
+                                            ) {
+                                                action_error = Some(e);
+                                            }
+                                        }
+
+                                        if ui.button("Delete").clicked() {
+                                            if let Err(e) = self.cast_file.as_mut().expect("Unable to get the cast handle as mut for modification").action(
+                                                ModificationAction::Deletion,
+                                                order,
+                                                &event_position_window[1],
+                                                None,
+                                            ) {
+                                                action_error = Some(e);
+                                            }
+                                        }
+                                    });
+                                let editing = |field: EditField| {
+                                    self.event_edit.as_ref().is_some_and(|edit| {
+                                        edit.field == field
+                                            && edit.window[1].byte_location == *byte_location
+                                            && edit.window[1].chain_index == *chain_index
+                                    })
+                                };
+
+                                // Times are edited in place and committed when the field loses focus, escape cancels
+                                if editing(EditField::Time) {
+                                    let edit = self.event_edit.as_mut().expect("edit was just checked");
+                                    let response = ui.add(
+                                        egui::TextEdit::singleline(&mut edit.text)
+                                            .desired_width(TIME_EDIT_WIDTH)
+                                            .font(egui::TextStyle::Monospace),
+                                    );
+                                    if !edit.focused {
+                                        response.request_focus();
+                                        edit.focused = true;
+                                    }
+                                    if response.lost_focus() {
+                                        if ui.input(|i| i.key_pressed(Key::Escape)) {
+                                            self.event_edit = None;
+                                        } else if let Err(e) = self.commit_time_edit() {
+                                            action_error = Some(e);
+                                        }
+                                    }
+                                } else if ui
+                                    .add(
+                                        egui::Label::new(RichText::new(event.time.to_string()).monospace())
+                                            .sense(egui::Sense::click()),
+                                    )
+                                    .on_hover_text("Double click to edit")
+                                    .double_clicked()
+                                {
+                                    self.start_edit(event_position_window, EditField::Time);
+                                }
+
+                                let mut new_code = None;
+                                egui::ComboBox::from_id_salt(format!("type_{}", line))
+                                    .selected_text(
+                                        RichText::new(event.data.get_type())
+                                            .color(event.data.get_color())
+                                            .monospace(),
+                                    )
+                                    .show_ui(ui, |ui| {
+                                        for (code, name) in EVENT_CODES {
+                                            if ui
+                                                .selectable_label(event.data.code() == code, format!("{} ({})", name, code))
+                                                .clicked()
+                                            {
+                                                new_code = Some(code);
+                                            }
+                                        }
+                                        ui.separator();
+                                        ui.horizontal(|ui| {
+                                            ui.add(
+                                                egui::TextEdit::singleline(&mut self.custom_code)
+                                                    .char_limit(1)
+                                                    .desired_width(20.0)
+                                                    .font(egui::TextStyle::Monospace),
+                                            );
+                                            if ui.button("Custom Code").clicked() {
+                                                new_code = self.custom_code.chars().next();
+                                            }
+                                        });
+                                    });
+                                // The data is kept as is, which fails for resize and exit events if it isn't in their format
+                                if let Some(code) = new_code.filter(|&code| code != event.data.code()) {
+                                    let result = EventData::from_code(code, event.data.get_data())
+                                        .map_err(CastError::from)
+                                        .and_then(|data| self.modify_event_data(&event_position_window[1], data));
+                                    if let Err(e) = result {
+                                        action_error = Some(e);
+                                    }
+                                }
+
+                                // Create a scrolling area with unique ID for each row
+                                egui::ScrollArea::horizontal()
+                                    .id_salt(format!("data_{}", line)) // Add unique ID for each scroll area
+                                    .scroll_bar_visibility(ScrollBarVisibility::AlwaysVisible)
+                                    .show(ui, |ui| {
+                                        ui.add_space(4.0);
+                                        let current = self.search.current_match().is_some_and(|found| {
+                                            found.positioned.byte_location == *byte_location
+                                                && found.positioned.chain_index == *chain_index
+                                        });
+                                        let text = data_text(ui, &event.data, self.search.search.as_ref(), current);
+                                        if ui
+                                            .add(egui::Label::new(text).sense(egui::Sense::click()))
+                                            .on_hover_text("Double click to edit")
+                                            .double_clicked()
+                                        {
+                                            self.start_edit(event_position_window, EditField::Data);
+                                        }
+                                        ui.add_space(4.0);
+                                    });
+
+                                ui.end_row();
+                            }
+                        });
+                    // This button will only show up if they have scrolled to the end of the file though it is always appended
+                    if ui.button("Insert New Line").clicked() {
+                        let cast_file = self
+                            .cast_file
+                            .as_mut()
+                            .expect("Unable to get the cast handle as mut for modification");
+                        let time = round_time(cast_file.end_time() + APPENDED_EVENT_DELAY);
+                        if let Err(e) = cast_file.append(Event {
+                            time,
+                            data: EventData::Output(String::new()),
+                        }) {
+                            action_error = Some(e);
+                        }
+                    }
+                    if let Some(e) = action_error {
+                        self.error_toast(format!("Failed to apply edit: {}", e));
+                    }
+                }
+                Err(e) => {
+                    self.error_toast(format!("Failed to get event list due to error: {}", e));
+                }
+            };
+        }
+    }
+}
+
+impl App for MyEguiApp {
+    fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+        // Crate provides a convenient interface for showing toast notifications or temporary timed popup notifications
+        self.toasts.show(ctx);
+
+        if let Some(result) = self.opening.as_ref().and_then(Task::poll) {
+            self.opening = None;
+            if let Some(result) = result {
+                self.opened(result);
+            }
+        }
+        self.poll_search();
+        self.poll_lint();
+        self.poll_job();
+
+        // Redo is checked first as the undo shortcut would otherwise also match while shift is held
+        if ctx.input_mut(|i| i.consume_shortcut(&REDO_SHORTCUT)) {
+            self.redo();
+        } else if ctx.input_mut(|i| i.consume_shortcut(&UNDO_SHORTCUT)) {
+            self.undo();
+        }
+
+        egui::TopBottomPanel::top("options").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                // Open button to open a file dialogue window that allows the users to select a `.cast` file
+                if (ui.button("Open")).clicked() {
+                    let filter = Box::new({
+                        |path: &Path| -> bool { path.extension() == Some(OsStr::new("cast")) }
+                    });
+                    // By default open to the home directory and apply the `.cast` filter
+                    let mut file_dialog =
+                        FileDialog::open_file(dirs::home_dir()).show_files_filter(filter);
+                    file_dialog.open();
+                    self.file_dialog = Some(file_dialog);
+                }
+
+                let file_path = self.cast_file.as_ref().map(|file| file.file_path.clone());
+                if let Some(file_path) = file_path {
+                    if (ui.button("Save")).clicked() {
+                        // By default open to the home directory and apply the `.cast` filter
+                        let mut file_dialog = FileDialog::save_file(Some(file_path.clone()));
+                        file_dialog.open();
+                        self.file_dialog = Some(file_dialog);
+                    }
+
+                    ui.menu_button("Edit", |ui| self.render_edit_menu(ui));
+
+                    ui.menu_button("Export", |ui| {
+                        ui.label(RichText::new("GIF Settings").strong());
+                        ui.add(
+                            egui::DragValue::new(&mut self.gif_options.font_size)
+                                .range(6.0..=72.0)
+                                .prefix("Font Size: ")
+                                .suffix("px"),
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut self.gif_options.max_fps)
+                                .range(1.0..=100.0)
+                                .prefix("Max FPS: "),
+                        );
+                        let mut loop_forever = self.gif_options.loop_count.is_none();
+                        ui.checkbox(&mut loop_forever, "Loop Forever");
+                        if loop_forever {
+                            self.gif_options.loop_count = None;
+                        } else {
+                            let mut loop_count = self.gif_options.loop_count.unwrap_or(1);
+                            ui.add(
+                                egui::DragValue::new(&mut loop_count)
+                                    .range(0..=u16::MAX)
+                                    .prefix("Repeats: "),
+                            );
+                            self.gif_options.loop_count = Some(loop_count);
+                        }
+
+                        if ui.button("Export GIF...").clicked() {
+                            let mut export_dialog =
+                                FileDialog::save_file(Some(file_path.with_extension("gif")));
+                            export_dialog.open();
+                            self.export_dialog = Some(export_dialog);
+                            ui.close_menu();
+                        }
+                    });
+                }
+
+                self.render_tasks(ui);
+            });
+            // This keeps open the file dialogue throughout egui updates when it has been opened by the open button and returns a opened file path buffer when a file has been selected
+            if let Some(dialog) = &mut self.file_dialog {
+                if dialog.show(ctx).selected() {
+                    if let Some(path) = dialog.path().map(Path::to_path_buf) {
+                        match dialog.dialog_type() {
+                            DialogType::SelectFolder => todo!(),
+                            DialogType::OpenFile => self.open_file(path),
+                            DialogType::SaveFile => self.save_file(path),
+                        }
+                    }
+                }
+            }
+        });
+
+        if let Some(dialog) = &mut self.export_dialog {
+            if dialog.show(ctx).selected() {
+                if let Some(path) = dialog.path().map(Path::to_path_buf) {
+                    self.export_gif(path);
+                }
+            }
+        }
+
+        if let Some(dialog) = &mut self.theme_dialog {
+            if dialog.show(ctx).selected() {
+                if let Some(path) = dialog.path().map(Path::to_path_buf) {
+                    match themes::import(&path) {
+                        Ok(theme) => {
+                            let name = path.file_name().unwrap_or(path.as_os_str());
+                            self.apply_theme(
+                                format!("Import theme from {}", name.to_string_lossy()),
+                                theme,
+                            );
+                        }
+                        Err(e) => self.error_toast(format!("Failed to Import Theme: {}", e)),
+                    }
+                }
+            }
+        }
+
+        if let Some(dialog) = &mut self.concat_dialog {
+            if dialog.show(ctx).selected() {
+                if let Some(path) = dialog.path().map(Path::to_path_buf) {
+                    match dialog.dialog_type() {
+                        DialogType::OpenFile => {
+                            if let Some(concatenation) = self.concatenation.as_mut() {
+                                concatenation.files.push(path);
+                            }
+                        }
+                        DialogType::SaveFile => self.concatenate(path),
+                        DialogType::SelectFolder => {}
+                    }
+                }
+            }
+        }
+
+        if let Some(dialog) = &mut self.split_dialog {
+            if dialog.show(ctx).selected() {
+                if let Some(path) = dialog.path().map(Path::to_path_buf) {
+                    self.save_split(path);
+                }
+            }
+        }
+
+        // todo: Check if file size even warrants a scroll bar and use it's size to inform the size of the scroll bar handle exponentially decreasing to a smaller point. Additionally allow a ron file for user settings to control settings such as minimum bar size
+        if self.cast_file.is_some() {
+            egui::TopBottomPanel::top("header").show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("header")
+                    .max_height(HEADER_MAX_HEIGHT)
+                    .show(ui, |ui| self.render_header(ui));
+            });
+
+            egui::SidePanel::left("history")
+                .resizable(true)
+                .default_width(200.0)
+                .show(ctx, |ui| {
+                    self.render_history(ui);
+                });
+
+            egui::TopBottomPanel::bottom("preview")
+                .resizable(true)
+                .default_height(300.0)
+                .show(ctx, |ui| {
+                    if let Some(cast_file) = &self.cast_file {
+                        self.preview.show(ui, cast_file);
+                    }
+                });
+
+            self.refresh_markers();
+            egui::SidePanel::right("markers")
+                .resizable(true)
+                .default_width(180.0)
+                .show(ctx, |ui| {
+                    self.render_markers(ui);
+                });
+
+            let event_count = self.cast_file.as_ref().and_then(CastFile::event_count);
+            let mut scrollbar = FixedScrollbar::new(&mut self.scroll_position);
+            if let Some(count) = event_count {
+                // One row per scroll step and a handle as tall as the share of the file on screen
+                let height = ctx.screen_rect().height();
+                let hidden = count.saturating_sub(EVENTS_PER_PAGE).max(1) as f32;
+                scrollbar = scrollbar
+                    .scroll_sensitivity(height / (EVENT_ROW_HEIGHT * hidden))
+                    .handle_height(
+                        (height * EVENTS_PER_PAGE as f32 / count.max(1) as f32)
+                            .max(MIN_HANDLE_HEIGHT),
+                    );
+            }
+            scrollbar.show_in_side_panel(ctx, "Memory Scroller");
+
+            egui::CentralPanel::default().show(ctx, |ui| {
+                self.render_navigation(ui);
+                self.render_search(ui);
+                self.render_events(ui);
+            });
+
+            self.render_typo_review(ctx);
+            self.render_redaction_review(ctx);
+            self.render_concatenation(ctx);
+            self.render_split(ctx);
+            self.render_problems(ctx);
+            self.render_data_editor(ctx);
+        }
+    }
+}
+
+/// Starts checking the file as it is on disk for problems in the background
+fn lint_task(cast_file: &CastFile) -> Task<Report> {
+    let snapshot = cast_file.snapshot();
+    Task::spawn("Checking for problems".to_string(), move |progress| {
+        snapshot.lint(progress)
+    })
+}
+
+/// Number of the first event fetched for a page at `scroll_position` between 0 and 1
+fn page_start(scroll_position: f32, event_count: usize) -> usize {
+    let last_start = event_count.saturating_sub(EVENTS_PER_PAGE);
+    ((scroll_position.clamp(0.0, 1.0) as f64 * last_start as f64).round() as usize).min(last_start)
+}
+
+/// Event data as shown in the grid with the matches of the search highlighted
+fn data_text(
+    ui: &Ui,
+    data: &EventData,
+    search: Option<&Search>,
+    current: bool,
+) -> egui::WidgetText {
+    let ranges = search
+        .map(|search| search.find_in(data))
+        .unwrap_or_default();
+    if ranges.is_empty() {
+        return RichText::new(data.get_data()).monospace().into();
+    }
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let mut job = egui::text::LayoutJob::default();
+    for (text, matched) in search::highlight_segments(&data.get_unescaped_data(), &ranges) {
+        let mut format = egui::TextFormat::simple(font.clone(), ui.visuals().text_color());
+        if matched {
+            format.background = if current {
+                CURRENT_MATCH_HIGHLIGHT
+            } else {
+                MATCH_HIGHLIGHT
+            };
+            format.color = Color32::WHITE;
+        }
+        job.append(&text, 0.0, format);
+    }
+    job.into()
+}
+
+/// Parses `old=new` time pairs separated by commas
+fn parse_points(text: &str) -> Option<Vec<(f64, f64)>> {
+    text.split(',')
+        .map(|pair| {
+            let (old, new) = pair.split_once('=')?;
+            Some((old.trim().parse().ok()?, new.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Parses times in seconds separated by commas
+fn parse_times(text: &str) -> Option<Vec<f64>> {
+    text.split(',')
+        .map(|time| time.trim().parse().ok())
+        .collect()
+}
+
+/// Text field for an optional header string where empty text removes the field
+fn optional_text(ui: &mut Ui, label: &str, value: &mut Option<String>) {
+    let mut text = value.clone().unwrap_or_default();
+    ui.label(RichText::new(label).strong());
+    if ui.text_edit_singleline(&mut text).changed() {
+        *value = (!text.is_empty()).then_some(text);
+    }
+}
+
+/// Checkbox turning an optional header number on, starting at `default`, with a field to edit it
+fn optional_number<T: egui::emath::Numeric>(
+    ui: &mut Ui,
+    label: &str,
+    value: &mut Option<T>,
+    default: T,
+    suffix: &str,
+) {
+    let mut enabled = value.is_some();
+    ui.checkbox(&mut enabled, RichText::new(label).strong());
+    match (enabled, value.as_mut()) {
+        (true, Some(number)) => {
+            ui.add(egui::DragValue::new(number).suffix(suffix));
+        }
+        (true, None) => *value = Some(default),
+        (false, _) => *value = None,
+    }
+}
+
+/// Color picker button with the CSS hex value of the color beside it
+fn color_edit(ui: &mut Ui, color: &mut Color32) {
+    egui::color_picker::color_edit_button_srgba(ui, color, egui::color_picker::Alpha::Opaque);
+    ui.label(color32_to_css_rgb(color));
+}
+
+fn color32_to_css_rgb(color: &Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
@@ -1,4 +1,4 @@
-use eframe::egui::Color32;
+use crate::color::Color32;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 use std::{
@@ -7,7 +7,7 @@ use std::{
 };
 use thiserror::Error;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct Header {
     pub version: u8,
     pub width: u16,
@@ -28,6 +28,15 @@ pub struct Header {
     pub env: Option<HashMap<String, String>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub theme: Option<Theme>,
+    /// v3 only `term.type`. v2 files carry this in `env.TERM` instead so it isn't written to them
+    #[serde(skip)]
+    pub term_type: Option<String>,
+    /// v3 only `term.version`, the version string reported by the recorded terminal
+    #[serde(skip)]
+    pub term_version: Option<String>,
+    /// v3 only list of tags
+    #[serde(skip)]
+    pub tags: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone)]
@@ -42,6 +51,8 @@ pub enum EventData {
     Input(String),
     Resize(u16, u16),
     Marker(String),
+    /// Exit status of the recorded process, only written by v3
+    Exit(i32),
     Other(char, String),
 }
 
@@ -53,10 +64,55 @@ impl EventData {
             EventData::Input(_) => "Input",
             EventData::Resize(_, _) => "Resize",
             EventData::Marker(_) => "Marker",
+            EventData::Exit(_) => "Exit",
             EventData::Other(_, _) => "Other",
         }
     }
 
+    /// Get the single character code the type is written as
+    pub fn code(&self) -> char {
+        match self {
+            EventData::Output(_) => 'o',
+            EventData::Input(_) => 'i',
+            EventData::Resize(_, _) => 'r',
+            EventData::Marker(_) => 'm',
+            EventData::Exit(_) => 'x',
+            EventData::Other(c, _) => *c,
+        }
+    }
+
+    /// Build event data from its code and data held in the escaped form it is written in. Resize and exit data have to be in their `WxH` and integer formats
+    pub fn from_code(code: char, data: String) -> Result<Self, EventError> {
+        Ok(match code {
+            'o' => EventData::Output(data),
+            'i' => EventData::Input(data),
+            'r' => {
+                let (cols, rows) = data
+                    .split_once('x')
+                    .ok_or_else(|| EventError::Resize(data.clone()))?;
+                let cols = cols.parse().map_err(|_| EventError::Resize(data.clone()))?;
+                let rows = rows.parse().map_err(|_| EventError::Resize(data.clone()))?;
+                EventData::Resize(cols, rows)
+            }
+            'm' => EventData::Marker(data),
+            'x' => EventData::Exit(data.parse().map_err(|_| EventError::Exit(data.clone()))?),
+            c => EventData::Other(c, data),
+        })
+    }
+
+    /// Build event data from an event line of a file. v2 has no exit events so an `x` line whose data isn't a status is an unknown event there and is kept as `Other` to be written back unchanged. Other codes have to be valid the way `from_code` checks them
+    pub fn from_line(code: char, data: String) -> Result<Self, EventError> {
+        match code {
+            'x' if data.parse::<i32>().is_err() => Ok(EventData::Other(code, data)),
+            code => Self::from_code(code, data),
+        }
+    }
+
+    /// Build event data from text edited in the form `get_editable_data` gives
+    pub fn from_edited(code: char, text: &str) -> Result<Self, EventError> {
+        Self::from_code(code, escape_data(&unescape_edited(text)?))
+    }
+
     /// Get the data contents as a String
     pub fn get_data(&self) -> String {
         match self {
@@ -64,10 +120,33 @@ impl EventData {
             EventData::Input(s) => s.clone(),
             EventData::Resize(w, h) => format!("{}x{}", w, h),
             EventData::Marker(s) => s.clone(),
+            EventData::Exit(status) => status.to_string(),
             EventData::Other(_, s) => s.clone(),
         }
     }
 
+    /// Get the data contents with JSON escapes such as `\u001b` turned back into the characters the recording actually wrote. Data is held in its escaped form so this is what should be fed to a terminal
+    pub fn get_unescaped_data(&self) -> String {
+        let data = self.get_data();
+        serde_json::from_str::<String>(&format!("\"{}\"", data)).unwrap_or(data)
+    }
+
+    /// Get the data contents as text for editing. Control characters are written as escapes such as `\u001b` so they can be seen and typed while line breaks stay real line breaks so multiline output reads as it would on screen
+    pub fn get_editable_data(&self) -> String {
+        let mut text = String::new();
+        for c in self.get_unescaped_data().chars() {
+            match c {
+                '\\' => text.push_str("\\\\"),
+                '\n' => text.push('\n'),
+                '\r' => text.push_str("\\r"),
+                '\t' => text.push_str("\\t"),
+                c if c.is_control() => text.push_str(&format!("\\u{:04x}", c as u32)),
+                c => text.push(c),
+            }
+        }
+        text
+    }
+
     /// Get the associated color for each type
     pub fn get_color(&self) -> Color32 {
         match self {
@@ -75,13 +154,14 @@ impl EventData {
             EventData::Input(_) => Color32::YELLOW,
             EventData::Resize(_, _) => Color32::RED,
             EventData::Marker(_) => Color32::BLUE,
+            EventData::Exit(_) => Color32::GRAY,
             EventData::Other(c, _) => {
                 let bits = *c as u8;
                 // Extract 2 bits for each channel
                 let r = ((bits & 0b11000000) >> 6) << 6; // Bits 7-6 for red
                 let g = ((bits & 0b00110000) >> 4) << 6; // Bits 5-4 for green
                 let b = ((bits & 0b00001100) >> 2) << 6; // Bits 3-2 for blue
-                let a = ((bits & 0b00000011) >> 0) << 6; // Bits 1-0 for alpha
+                let a = (bits & 0b00000011) << 6; // Bits 1-0 for alpha
 
                 Color32::from_rgba_unmultiplied(r, g, b, a)
             }
@@ -89,14 +169,56 @@ impl EventData {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Theme {
     pub fg: Color32,
     pub bg: Color32,
     pub palette: Vec<Color32>,
 }
 
+/// The palette asciinema uses when a recording doesn't specify a theme
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            fg: Color32::from_rgb(0xcc, 0xcc, 0xcc),
+            bg: Color32::from_rgb(0x12, 0x13, 0x14),
+            palette: vec![
+                Color32::from_rgb(0x00, 0x00, 0x00),
+                Color32::from_rgb(0xdd, 0x3c, 0x69),
+                Color32::from_rgb(0x4e, 0xbf, 0x22),
+                Color32::from_rgb(0xdd, 0xaf, 0x3c),
+                Color32::from_rgb(0x26, 0xb0, 0xd7),
+                Color32::from_rgb(0xb9, 0x54, 0xe1),
+                Color32::from_rgb(0x54, 0xe1, 0xb9),
+                Color32::from_rgb(0xd9, 0xd9, 0xd9),
+                Color32::from_rgb(0x4d, 0x4d, 0x4d),
+                Color32::from_rgb(0xdd, 0x3c, 0x69),
+                Color32::from_rgb(0x4e, 0xbf, 0x22),
+                Color32::from_rgb(0xdd, 0xaf, 0x3c),
+                Color32::from_rgb(0x26, 0xb0, 0xd7),
+                Color32::from_rgb(0xb9, 0x54, 0xe1),
+                Color32::from_rgb(0x54, 0xe1, 0xb9),
+                Color32::from_rgb(0xff, 0xff, 0xff),
+            ],
+        }
+    }
+}
+
 impl Theme {
+    /// Build a theme from colors written the way `.cast` headers write them, `#rrggbb` with the palette colors separated by colons
+    pub fn from_hex(fg: &str, bg: &str, palette: &str) -> Result<Self, ThemeError> {
+        let palette = palette
+            .split(':')
+            .map(Theme::color_from_hex)
+            .collect::<Result<Vec<Color32>, ThemeError>>()?;
+        Theme::validate_palette(&palette)?;
+        Ok(Theme {
+            fg: Theme::color_from_hex(fg)?,
+            bg: Theme::color_from_hex(bg)?,
+            palette,
+        })
+    }
+
     /// Helper to convert hex string to Color32
     fn color_from_hex(hex: &str) -> Result<Color32, ThemeError> {
         // Validate basic CSS color hex format
@@ -118,7 +240,7 @@ impl Theme {
     }
 
     // This validates the palette to ensure both that it contains colors and that it has either 8 or 16 colors
-    fn validate_palette(palette: &[Color32]) -> Result<(), ThemeError> {
+    pub fn validate_palette(palette: &[Color32]) -> Result<(), ThemeError> {
         match palette.len() {
             8 | 16 => Ok(()),
             len => Err(ThemeError::PaletteSize(len)),
@@ -201,24 +323,10 @@ impl Serialize for Event {
         seq.serialize_element(&self.time)?;
 
         // Serialize the event type as a single character string
-        let code = match &self.data {
-            EventData::Output(_) => "o",
-            EventData::Input(_) => "i",
-            EventData::Resize(_, _) => "r",
-            EventData::Marker(_) => "m",
-            EventData::Other(c, _) => &c.to_string(),
-        };
-        seq.serialize_element(&code)?;
-
-        // Serialize the data
-        let data = match &self.data {
-            EventData::Output(data)
-            | EventData::Input(data)
-            | EventData::Marker(data)
-            | EventData::Other(_, data) => data,
-            EventData::Resize(cols, rows) => &format!("{}x{}", cols, rows),
-        };
-        seq.serialize_element(data)?;
+        seq.serialize_element(&self.data.code().to_string())?;
+
+        // Data is held escaped so it is unescaped first, otherwise the serializer escapes it a second time
+        seq.serialize_element(&self.data.get_unescaped_data())?;
 
         seq.end()
     }
@@ -316,30 +424,7 @@ impl<'de> Deserialize<'de> for Event {
 
                 let data = parts[2].trim_matches('"').to_string();
 
-                let event_data = match code {
-                    'o' => EventData::Output(data),
-                    'i' => EventData::Input(data),
-                    'r' => {
-                        let (cols, rows) = data
-                            .split_once('x')
-                            .ok_or_else(|| EventError::Resize(data.clone()))
-                            .map_err(convert_err)?;
-
-                        let cols = cols
-                            .parse()
-                            .map_err(|_| EventError::Resize(data.clone()))
-                            .map_err(convert_err)?;
-
-                        let rows = rows
-                            .parse()
-                            .map_err(|_| EventError::Resize(data.clone()))
-                            .map_err(convert_err)?;
-
-                        EventData::Resize(cols, rows)
-                    }
-                    'm' => EventData::Marker(data),
-                    c => EventData::Other(c, data),
-                };
+                let event_data = EventData::from_line(code, data).map_err(convert_err)?;
 
                 Ok(Event {
                     time,
@@ -362,38 +447,12 @@ impl<'de> Deserialize<'de> for Event {
                     })?;
 
                 let data = match &arr[2] {
-                    Value::String(s) => {
-                        // Convert to a JSON value and back to get the escaped string representation
-                        serde_json::to_string(s)
-                            .map_err(serde::de::Error::custom)?
-                            // Remove the surrounding quotes that to_string adds
-                            .trim_matches('"')
-                            .to_string()
-                    }
+                    Value::String(s) => escape_data(s),
                     _ => return Err(serde::de::Error::custom("Third element must be a string")),
                 };
 
-                let event_data = match code {
-                    'o' => EventData::Output(data),
-                    'i' => EventData::Input(data),
-                    'r' => {
-                        let (cols, rows) = data.split_once('x').ok_or_else(|| {
-                            serde::de::Error::custom(format!("Invalid resize format: {}", data))
-                        })?;
-
-                        let cols = cols.parse().map_err(|_| {
-                            serde::de::Error::custom(format!("Invalid column value: {}", cols))
-                        })?;
-
-                        let rows = rows.parse().map_err(|_| {
-                            serde::de::Error::custom(format!("Invalid row value: {}", rows))
-                        })?;
-
-                        EventData::Resize(cols, rows)
-                    }
-                    'm' => EventData::Marker(data),
-                    c => EventData::Other(c, data),
-                };
+                let event_data = EventData::from_line(code, data)
+                    .map_err(|e| serde::de::Error::custom(e.to_string()))?;
 
                 Ok(Event {
                     time,
@@ -408,6 +467,48 @@ impl<'de> Deserialize<'de> for Event {
     }
 }
 
+/// Escapes text the way it is held in `EventData`, which is its JSON string form without the surrounding quotes
+pub(crate) fn escape_data(text: &str) -> String {
+    let escaped = serde_json::to_string(text).expect("strings always serialize");
+    // Remove exactly the surrounding quotes that to_string adds, trimming every quote would also eat an escaped quote at the end of the data
+    escaped[1..escaped.len() - 1].to_string()
+}
+
+/// Turns text edited in the form `EventData::get_editable_data` gives back into the characters it stands for. Besides the JSON escapes `\e` is accepted for the escape character as it starts nearly every terminal sequence
+fn unescape_edited(text: &str) -> Result<String, EventError> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        let escape = chars
+            .next()
+            .ok_or_else(|| EventError::Escape("\\".to_string()))?;
+        match escape {
+            '\\' | '"' | '/' => unescaped.push(escape),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            't' => unescaped.push('\t'),
+            'b' => unescaped.push('\u{8}'),
+            'f' => unescaped.push('\u{c}'),
+            'e' => unescaped.push('\u{1b}'),
+            'u' => {
+                let hex: String = chars.by_ref().take(4).collect();
+                let c = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 4)
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| EventError::Escape(format!("\\u{}", hex)))?;
+                unescaped.push(c);
+            }
+            other => return Err(EventError::Escape(format!("\\{}", other))),
+        }
+    }
+    Ok(unescaped)
+}
+
 #[derive(Error, Debug)]
 pub enum ThemeError {
     #[error("Invalid color hex format: {0}")]
@@ -418,6 +519,9 @@ pub enum ThemeError {
 
     #[error("Invalid palette size: expected 8 or 16 colors, got {0}")]
     PaletteSize(usize),
+
+    #[error("Unable to import theme: {0}")]
+    Import(String),
 }
 
 #[derive(Error, Debug)]
@@ -431,6 +535,12 @@ pub enum EventError {
     #[error("Invalid resize format: expected WxH, got {0}")]
     Resize(String),
 
+    #[error("Invalid exit status: {0}")]
+    Exit(String),
+
+    #[error("Invalid escape sequence: {0}")]
+    Escape(String),
+
     #[error("Missing event code")]
     MissingCode,
 
@@ -1,19 +1,32 @@
 use crate::asciicast_egui::*;
-use eframe::egui::Color32;
+use crate::cleanup::TypoFix;
+use crate::concat::{self, ConcatOptions};
+use crate::convert;
+use crate::export::{self, GifOptions};
+use crate::history::{Change, History, HistoryEntry};
+use crate::index::{self, LineIndex, INDEX_STRIDE};
+use crate::lint::{self, Problem, Report};
+use crate::scan::{self, Progress};
+use crate::search::Replacement;
+use crate::split::{self, SplitPart};
+use crate::terminal::Terminal;
+use crate::timing::{IdleCompression, TimeTransform, Timeline};
 use memmap2::Mmap;
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
-    collections::BTreeMap,
+    cell::{Cell, RefCell},
+    collections::{btree_map, BTreeMap, VecDeque},
     fmt,
     fs::File,
     io::{BufWriter, Write},
-    mem,
+    iter::{Peekable, Skip},
     path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    thread,
 };
 use thiserror::Error;
 
-// Size for block processing - can be tuned
-const BLOCK_SIZE: usize = 64 * 1024; // 64KB blocks
+/// Size of the blocks the memory map is split into to scan it on several threads at once. Blocks are extended to the next newline so no line is split between two of them
+pub(crate) const BLOCK_SIZE: usize = 64 * 1024; // 64KB blocks
 
 #[derive(Debug, Clone)]
 pub enum ModificationAction {
@@ -21,10 +34,22 @@ pub enum ModificationAction {
     Addition(Event),
     /// Delete either removes an addition action or changes the `original_deleted` state
     Deletion,
-    /// Only modify the data, not the time
+    /// Only modify the data, not the time. Modifying the original line hides it and puts an edited copy at the end of the chain in its place
     ModifyData(EventData),
 }
 
+impl fmt::Display for ModificationAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModificationAction::Addition(event) => {
+                write!(f, "Insert {} at {}s", event.data.get_type(), event.time)
+            }
+            ModificationAction::Deletion => write!(f, "Delete"),
+            ModificationAction::ModifyData(data) => write!(f, "Modify {} data", data.get_type()),
+        }
+    }
+}
+
 /// This represents advanced modification actions which can be thought of as collections of basic modification actions
 #[derive(Debug)]
 pub enum AdvancedModificationAction {
@@ -34,7 +59,21 @@ pub enum AdvancedModificationAction {
     Swap(EventPositioned, usize),
 }
 
+impl fmt::Display for AdvancedModificationAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdvancedModificationAction::Modify(event) => {
+                write!(f, "Modify {} at {}s", event.data.get_type(), event.time)
+            }
+            AdvancedModificationAction::Swap(target, _) => {
+                write!(f, "Swap with event at {}s", target.event.time)
+            }
+        }
+    }
+}
+
 /// `ModificationChain` is used to organize modifications at a given byte location. It works by holding a value to check whether or not to render the original and a vector of Events which are the modifications. These modifications are prepended to the memory mapped event they normally point to in implementation.
+#[derive(Clone)]
 pub struct ModificationChain {
     pub modifications: Vec<Event>,
     original_deleted: bool,
@@ -54,50 +93,630 @@ impl ModificationChain {
 pub struct EventPositioned {
     pub event: Event,
     pub byte_location: usize,
+    /// Position of the event in the modification chain at `byte_location`, `None` for the line of the file itself. Events of a chain can share a time so this is what tells them apart
+    pub chain_index: Option<usize>,
 }
 
 /// `CastFile` serves as both a reader and writer to the `.cast` file. The way it works is that it takes in a float between 0 and 1 and maps that to bytes between 0 and the file size. It then reads from that byte selected until it reaches the first newline and then it displays or reads the number of lines requested after that. This editor presumes you're using V2 of the `.cast` file type and thus it expects a JSON header followed by an arbitrary number of newline delimited lines in the format [time, code, data] as shown in the [documentation](https://docs.asciinema.org/manual/asciicast/v2/).
 pub struct CastFile {
     /// Owned path to `.cast` file
     pub file_path: PathBuf,
-    /// Memory map of the `.cast` file
-    mmap: Mmap,
+    /// Memory map of the `.cast` file, shared with the threads building the line index
+    mmap: Arc<Mmap>,
+    /// The file as it is on disk, which is `mmap` itself unless the file was converted. Problems are found in it so their lines and bytes are those of the file
+    source: Arc<Mmap>,
     pub header: Header,
+    /// Version of the file as it was opened. Anything other than 2 was converted to v2 on open. v1 files are saved as v2 while v3 files are saved as v3 again
+    pub source_version: u8,
     /// File size for fast computation of location for mmap
     file_size: u64,
     // Map of byte_location -> modification action
     modifications: BTreeMap<usize, ModificationChain>,
+    /// Time transforms applied to the memory mapped events as they are read
+    timeline: Timeline,
+    /// Applied and undone edits for undo and redo
+    history: History,
+    /// Edit state captured before the first change of the action currently being applied. This is `Some` only while an action is in progress
+    transaction: Option<Transaction>,
+    /// Incremented on every change to the edit state so views derived from the events know when to rebuild
+    revision: u64,
+    /// `end_time` together with the revision it was computed at. Cuts can remove long runs of lines from the end of the file which would otherwise be parsed again every frame
+    end_time_cache: Cell<Option<(u64, f64)>>,
+    /// Line index filled in by a background thread started on first use
+    index: Background<LineIndex>,
+    index_started: Cell<bool>,
+    /// How far building the line index got. It is cancelled when the file is closed so the threads don't go on reading a file nobody looks at
+    index_progress: Arc<Progress>,
+    /// Lines removed by the timeline before each index checkpoint, counted on a background thread for the timeline stored beside it. A count is cancelled as soon as the timeline changes again
+    removed_counts: RefCell<Option<RemovedCounts>>,
+}
+
+/// A value computed on another thread, empty until the thread is done
+type Background<T> = Arc<OnceLock<T>>;
+
+/// Timeline lines removed by it are counted for, the progress of the count and the counts once they are done
+type RemovedCounts = (Timeline, Arc<Progress>, Background<Vec<usize>>);
+
+/// Collects the original state of everything touched while applying a single user level action so that it can be committed to the history as one entry or rolled back on failure
+#[derive(Default)]
+struct Transaction {
+    chains: BTreeMap<usize, Option<ModificationChain>>,
+    timeline: Option<Timeline>,
+    header: Option<Header>,
 }
 
 impl CastFile {
     pub fn new(path: PathBuf) -> Result<Self, CastError> {
-        let file = File::open(&path).expect("Failed to Open File");
-        let file_size = file.metadata().expect("Failed to Get File Metadata").len();
+        let file = File::open(&path)?;
         // Create read-only memory map so that we can mitigate loading times
-        let mmap = unsafe { Mmap::map(&file).expect("Failed to Create Memory Map") };
+        let source =
+            Arc::new(unsafe { Mmap::map(&file).map_err(|e| CastError::MmapError(e.to_string()))? });
+
+        // Other versions are converted to a temporary v2 file so everything past this point only deals with v2
+        let source_version = convert::detect_version(&source)?;
+        let converted = |(header, mmap)| (header, Arc::new(mmap));
+        let (header, mmap) = match source_version {
+            1 => converted(convert::v1_to_v2(&source)?),
+            2 => {
+                // From the beginning of the file go to the first newline to parse header
+                let header_end = source
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .unwrap_or(source.len());
+                let header: Header = serde_json::from_slice(&source[..header_end])
+                    .map_err(|e| CastError::DeserializationError(e.to_string()))?;
+                (header, Arc::clone(&source))
+            }
+            3 => converted(convert::v3_to_v2(&source)?),
+            _ => return Err(CastError::InvalidVersion),
+        };
+        let file_size = mmap.len() as u64;
 
-        // From the beginning of the file go to the first newline to parse header
-        let header_end = mmap
-            .iter()
-            .position(|&b| b == b'\n')
-            .expect("Invalid file format");
-        let header: Header = serde_json::from_slice(&mmap[..header_end])
-            .map_err(|e| CastError::DeserializationError(e.to_string()))?;
-        if header.version != 2 {
-            return Err(CastError::InvalidVersion);
-        }
         Ok(Self {
+            source_version,
             file_path: path,
             mmap,
+            source,
             header,
             file_size,
             modifications: BTreeMap::new(),
+            timeline: Timeline::default(),
+            history: History::default(),
+            transaction: None,
+            revision: 0,
+            end_time_cache: Cell::new(None),
+            index: Arc::new(OnceLock::new()),
+            index_started: Cell::new(false),
+            index_progress: Arc::new(Progress::default()),
+            removed_counts: RefCell::new(None),
         })
     }
 
+    /// Runs `apply` as a single undoable step named `description`. Every modification made inside it, including nested actions, is recorded into one history entry. If `apply` fails all of its changes are rolled back so compound actions never leave the file half edited
+    pub fn transaction<F>(&mut self, description: String, apply: F) -> Result<(), CastError>
+    where
+        F: FnOnce(&mut Self) -> Result<(), CastError>,
+    {
+        // Nested calls simply join the outermost transaction
+        if self.transaction.is_some() {
+            return apply(self);
+        }
+
+        self.transaction = Some(Transaction::default());
+        let result = apply(self);
+        let transaction = self.transaction.take().unwrap_or_default();
+
+        // Pair each captured original state with the state it ended up in
+        let mut changes: Vec<Change> = transaction
+            .chains
+            .into_iter()
+            .map(|(byte_location, before)| Change::Chain {
+                byte_location,
+                after: self.modifications.get(&byte_location).cloned(),
+                before,
+            })
+            .collect();
+        if let Some(before) = transaction.timeline {
+            changes.push(Change::Timeline {
+                before,
+                after: self.timeline.clone(),
+            });
+        }
+        if let Some(before) = transaction.header {
+            changes.push(Change::Header {
+                before: Box::new(before),
+                after: Box::new(self.header.clone()),
+            });
+        }
+
+        match result {
+            Ok(()) => {
+                if !changes.is_empty() {
+                    self.history.push(HistoryEntry {
+                        description,
+                        changes,
+                    });
+                }
+                Ok(())
+            }
+            Err(e) => {
+                for change in changes.iter().rev() {
+                    self.revert_change(change);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Reverts the most recently applied history entry. Returns false if there was nothing to undo
+    pub fn undo(&mut self) -> bool {
+        let Some(entry) = self.history.step_back() else {
+            return false;
+        };
+        // The entry is cloned out as reverting needs mutable access to the rest of the file state
+        let changes = entry.changes.clone();
+        for change in changes.iter().rev() {
+            self.revert_change(change);
+        }
+        true
+    }
+
+    /// Reapplies the most recently undone history entry. Returns false if there was nothing to redo
+    pub fn redo(&mut self) -> bool {
+        let Some(entry) = self.history.step_forward() else {
+            return false;
+        };
+        let changes = entry.changes.clone();
+        for change in changes.iter() {
+            self.apply_change(change);
+        }
+        true
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Changes whenever the events of the file change through an action, undo or redo
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn revert_change(&mut self, change: &Change) {
+        match change {
+            Change::Chain {
+                byte_location,
+                before,
+                ..
+            } => self.set_chain(*byte_location, before.clone()),
+            Change::Timeline { before, .. } => self.set_timeline(before.clone()),
+            Change::Header { before, .. } => self.set_header(before.as_ref().clone()),
+        }
+    }
+
+    fn apply_change(&mut self, change: &Change) {
+        match change {
+            Change::Chain {
+                byte_location,
+                after,
+                ..
+            } => self.set_chain(*byte_location, after.clone()),
+            Change::Timeline { after, .. } => self.set_timeline(after.clone()),
+            Change::Header { after, .. } => self.set_header(after.as_ref().clone()),
+        }
+    }
+
+    fn set_chain(&mut self, byte_location: usize, chain: Option<ModificationChain>) {
+        self.revision += 1;
+        match chain {
+            Some(chain) => {
+                self.modifications.insert(byte_location, chain);
+            }
+            None => {
+                self.modifications.remove(&byte_location);
+            }
+        }
+    }
+
+    fn set_timeline(&mut self, timeline: Timeline) {
+        self.revision += 1;
+        self.timeline = timeline;
+    }
+
+    fn set_header(&mut self, header: Header) {
+        self.revision += 1;
+        self.header = header;
+    }
+
+    /// Gives mutable access to the header, capturing its original state into the running transaction the first time it's touched
+    fn header_mut(&mut self) -> &mut Header {
+        if let Some(transaction) = self.transaction.as_mut() {
+            transaction
+                .header
+                .get_or_insert_with(|| self.header.clone());
+        }
+        self.revision += 1;
+        &mut self.header
+    }
+
+    /// Replaces the header as a single undoable step named `description`. The terminal needs a size and a theme has to have 8 or 16 palette colors to be written
+    pub fn edit_header(&mut self, description: String, header: Header) -> Result<(), CastError> {
+        if header.width == 0 || header.height == 0 {
+            return Err(CastError::InvalidHeader(format!(
+                "terminal size {}x{} is empty",
+                header.width, header.height
+            )));
+        }
+        if let Some(theme) = &header.theme {
+            Theme::validate_palette(&theme.palette)
+                .map_err(|e| CastError::InvalidPaletteFormat(e.to_string()))?;
+        }
+        self.transaction(description, |cast| {
+            *cast.header_mut() = header;
+            Ok(())
+        })
+    }
+
+    /// Adds a transform to the timeline, capturing the timeline's original state into the running transaction the first time it's touched
+    fn push_transform(&mut self, transform: TimeTransform) {
+        if let Some(transaction) = self.transaction.as_mut() {
+            transaction
+                .timeline
+                .get_or_insert_with(|| self.timeline.clone());
+        }
+        self.revision += 1;
+        self.timeline.push(transform);
+    }
+
+    /// Applies a time transform to the whole file. Memory mapped events pick it up lazily through the timeline while the few events held in modification chains are rewritten right away as they already store final times
+    fn apply_transform(&mut self, transform: TimeTransform) {
+        let byte_locations: Vec<usize> = self
+            .modifications
+            .iter()
+            .filter(|(_, chain)| !chain.modifications.is_empty())
+            .map(|(&byte_location, _)| byte_location)
+            .collect();
+        for byte_location in byte_locations {
+            let chain = self.chain_mut(byte_location);
+            chain.modifications = chain
+                .modifications
+                .drain(..)
+                .filter_map(|mut event| {
+                    event.time = transform.apply(event.time)?;
+                    Some(event)
+                })
+                .collect();
+        }
+        self.push_transform(transform);
+
+        // A duration that was given is kept in sync with the new end of the recording
+        if self.header.duration.is_some() {
+            let end_time = self.end_time();
+            self.header_mut().duration = Some(end_time);
+        }
+    }
+
+    /// Applies a time transform to every event as a single undoable step. Fails with `CastError::TimingError` if the transform is malformed or would reorder events
+    pub fn transform_time(&mut self, transform: TimeTransform) -> Result<(), CastError> {
+        transform.validate()?;
+        self.check_order(&transform)?;
+        self.transaction(transform.to_string(), |cast| {
+            cast.apply_transform(transform);
+            Ok(())
+        })
+    }
+
+    /// Deletes every event in the `[start, end)` time range and moves all later events back by the removed duration. If the deleted events drew on the screen the output after the cut would be drawn over a screen that never appears, so the screen as they left it is redrawn in their place
+    pub fn cut(&mut self, start: f64, end: f64) -> Result<(), CastError> {
+        let transform = TimeTransform::Cut { start, end };
+        transform.validate()?;
+        self.check_order(&transform)?;
+        let redraw = self.redraw_events(start, end);
+        self.transaction(transform.to_string(), |cast| {
+            cast.apply_transform(transform);
+            cast.insert_redraw(start, redraw)
+        })
+    }
+
+    /// Events putting the screen in the state the events in `[start, end)` leave it in, for putting at `start` once they are removed. Every event before `end` is replayed through the terminal model to find it. Cuts from the very start play on a fresh terminal so only what is on screen is drawn while later cuts reset the terminal first, and either is resized if the removed events resized it. Empty when the removed events don't change the screen
+    fn redraw_events(&self, start: f64, end: f64) -> Vec<Event> {
+        let mut terminal = Terminal::for_header(&self.header);
+        let mut size_at_start = None;
+        let mut changed = false;
+        for positioned in self.events() {
+            let time = positioned.event.time;
+            if time >= end {
+                break;
+            }
+            if time >= start {
+                size_at_start.get_or_insert((terminal.width(), terminal.height()));
+                changed |= Terminal::changes_screen(&positioned.event);
+            }
+            terminal.feed_event(&positioned.event);
+        }
+        if !changed {
+            return Vec::new();
+        }
+
+        let size = (terminal.width(), terminal.height());
+        let mut events = Vec::new();
+        let mut text = terminal.redraw();
+        if size_at_start.is_some_and(|size_at_start| size_at_start != size) {
+            events.push(Event {
+                time: start,
+                data: EventData::Resize(size.0 as u16, size.1 as u16),
+            });
+        }
+        if start > 0.0 {
+            text.insert_str(0, "\x1bc");
+        }
+        if !text.is_empty() {
+            events.push(Event {
+                time: start,
+                data: EventData::Output(escape_data(&text)),
+            });
+        }
+        events
+    }
+
+    /// Puts the events from `redraw_events` in front of the first event at or after `time`. At the very start the size goes into the header instead of a resize event
+    fn insert_redraw(&mut self, time: f64, mut redraw: Vec<Event>) -> Result<(), CastError> {
+        if redraw.is_empty() {
+            return Ok(());
+        }
+        let Some(first) = self
+            .events_from(self.seek_time(time))
+            .find(|positioned| positioned.event.time >= time)
+        else {
+            // Nothing is left to draw over
+            return Ok(());
+        };
+        if time <= 0.0 {
+            if let Some(index) = redraw
+                .iter()
+                .position(|event| matches!(event.data, EventData::Resize(_, _)))
+            {
+                if let EventData::Resize(width, height) = redraw.remove(index).data {
+                    let header = self.header_mut();
+                    header.width = width;
+                    header.height = height;
+                }
+            }
+        }
+        let order = self.get_order(&first);
+        let chain = self.chain_mut(first.byte_location);
+        let order = order.min(chain.modifications.len());
+        chain.modifications.splice(order..order, redraw);
+        Ok(())
+    }
+
+    /// Shortens every pause longer than the configured limits by retiming the events after it. The file is read once to find the long gaps, which become the points of a single piecewise transform. The header duration is set to the new length even if the header had none, as players would otherwise report the length of the original recording from the last line
+    pub fn compress_idle(&mut self, options: &IdleCompression) -> Result<(), CastError> {
+        let valid_limit = |limit: f64| limit.is_finite() && limit >= 0.0;
+        if !valid_limit(options.max_gap)
+            || !options.max_gap_after_input.is_none_or(valid_limit)
+            || options.start >= options.end
+        {
+            return Err(CastError::TimingError);
+        }
+
+        let mut points: Vec<(f64, f64)> = Vec::new();
+        let mut removed = 0.0;
+        // Time of the previous event and whether it was an input
+        let mut previous: Option<(f64, bool)> = None;
+        for positioned in self.events_from(self.seek_time(options.start)) {
+            let time = positioned.event.time;
+            if time > options.end {
+                break;
+            }
+            if let Some((previous_time, after_input)) =
+                previous.filter(|&(previous_time, _)| previous_time >= options.start)
+            {
+                let limit = match options.max_gap_after_input {
+                    Some(limit) if after_input => limit,
+                    _ => options.max_gap,
+                };
+                let gap = time - previous_time;
+                if gap > limit {
+                    // Back to back gaps share the point between them
+                    if points.last().is_none_or(|&(old, _)| old < previous_time) {
+                        points.push((previous_time, previous_time - removed));
+                    }
+                    removed += gap - limit;
+                    points.push((time, time - removed));
+                }
+            }
+            previous = Some((time, matches!(positioned.event.data, EventData::Input(_))));
+        }
+
+        if points.is_empty() {
+            return Ok(());
+        }
+        let transform = TimeTransform::Piecewise { points };
+        transform.validate()?;
+        let description = match options.max_gap_after_input {
+            Some(after_input) => format!(
+                "Compress idle time to {}s ({}s after input)",
+                options.max_gap, after_input
+            ),
+            None => format!("Compress idle time to {}s", options.max_gap),
+        };
+        self.transaction(description, |cast| {
+            cast.apply_transform(transform);
+            let end_time = cast.end_time();
+            cast.header_mut().duration = Some(end_time);
+            Ok(())
+        })
+    }
+
+    /// Removes the events of every given typo fix and moves the rest of the recording back over the time they took, all as a single undoable step. Only the events of the fixes are removed, other events sharing their times are kept
+    pub fn apply_typo_fixes(&mut self, fixes: &[TypoFix]) -> Result<(), CastError> {
+        let description = match fixes.len() {
+            1 => format!("Clean up typo at {}s", fixes[0].start_time()),
+            count => format!("Clean up {} typos", count),
+        };
+        self.transaction(description, |cast| {
+            // Later fixes and events go first so neither closing gaps nor removing chain events moves the ones still to be removed
+            for fix in fixes.iter().rev() {
+                for positioned in fix.events.iter().rev() {
+                    let order = cast.get_order(positioned);
+                    cast.apply_action(ModificationAction::Deletion, order, positioned, None)?;
+                }
+                // Keystrokes sharing a timestamp leave no gap to close
+                if let Some(next_time) = fix.next_time.filter(|&time| time > fix.start_time()) {
+                    cast.apply_transform(TimeTransform::Offset {
+                        start: next_time,
+                        end: f64::INFINITY,
+                        offset: fix.start_time() - next_time,
+                    });
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Replaces the data of every given event as a single undoable step. Memory mapped events are hidden and replaced by edited copies the same way single data edits are
+    pub fn apply_replacements(
+        &mut self,
+        description: String,
+        replacements: &[Replacement],
+    ) -> Result<(), CastError> {
+        self.transaction(description, |cast| {
+            for replacement in replacements {
+                let positioned = &replacement.positioned;
+                let order = cast.get_order(positioned);
+                cast.apply_action(
+                    ModificationAction::ModifyData(replacement.data.clone()),
+                    order,
+                    positioned,
+                    None,
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Makes sure the first event doesn't end up before 0 and, for offsets, that the moved range stays between the events around it. Only the events around the range boundaries are read
+    fn check_order(&self, transform: &TimeTransform) -> Result<(), CastError> {
+        let first = self.events().next().map(|positioned| positioned.event.time);
+        if first
+            .and_then(|time| transform.apply(time))
+            .is_some_and(|time| time < 0.0)
+        {
+            return Err(CastError::TimingError);
+        }
+
+        if let TimeTransform::Offset { start, end, offset } = *transform {
+            // The last event before the range and the first inside it
+            let mut before = None;
+            let mut first_inside = None;
+            for positioned in self.events_from(self.seek_time(start)) {
+                let time = positioned.event.time;
+                if time < start {
+                    before = Some(time);
+                } else {
+                    first_inside = (time < end).then_some(time);
+                    break;
+                }
+            }
+            let Some(first_inside) = first_inside else {
+                // Nothing to move
+                return Ok(());
+            };
+            if before.is_some_and(|before| first_inside + offset < before) {
+                return Err(CastError::TimingError);
+            }
+
+            // The last event inside the range and the first after it
+            if end.is_finite() {
+                let mut last_inside = first_inside;
+                for positioned in self.events_from(self.seek_time(end)) {
+                    let time = positioned.event.time;
+                    if time >= end {
+                        if last_inside + offset > time {
+                            return Err(CastError::TimingError);
+                        }
+                        break;
+                    }
+                    if time >= start {
+                        last_inside = time;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Byte location of the last line whose time is before `time`, or the start of the events if there is none. Reading events from here reaches every event at or after `time`. Lines are compared by the time written at their start mapped through the timeline, with the lines it removes placed where they were cut out, which never decreases through the file so a binary search finds the line while only reading the times of a logarithmic number of lines
+    pub fn seek_time(&self, time: f64) -> usize {
+        let before = |line_time: f64| self.timeline.position(line_time) < time;
+        let mut low = self.data_start();
+        let mut high = self.mmap.len();
+        // The index narrows the search to the lines between two checkpoints without reading anything else
+        if let Some(index) = self.index.get() {
+            let checkpoints = index.checkpoints();
+            let after = checkpoints.partition_point(|&(_, line_time)| before(line_time));
+            if after > 0 {
+                low = checkpoints[after - 1].0;
+            }
+            if let Some(&(line_start, _)) = checkpoints.get(after) {
+                high = line_start;
+            }
+        }
+        while low < high {
+            let mid = low + (high - low) / 2;
+            // The first line after `mid` with a time
+            match index::line_times(&self.mmap, find_next_newline(&self.mmap, mid), high).next() {
+                Some((line_start, _, line_time)) if before(line_time) => low = line_start,
+                _ => high = mid,
+            }
+        }
+        low
+    }
+
+    /// Keeps only the events in the `[start, end)` time range and moves them so that `start` becomes time 0. The screen the removed start of the recording drew is redrawn at 0 as the kept output is drawn on top of it
+    pub fn trim(&mut self, start: f64, end: f64) -> Result<(), CastError> {
+        TimeTransform::Cut { start, end }.validate()?;
+        let redraw = if start > 0.0 {
+            self.redraw_events(0.0, start)
+        } else {
+            Vec::new()
+        };
+        let description = format!("Trim to {}s - {}s", start, end);
+        self.transaction(description, |cast| {
+            // The tail goes first so the window's times are still the ones that were asked for
+            if end.is_finite() {
+                cast.apply_transform(TimeTransform::Cut {
+                    start: end,
+                    end: f64::INFINITY,
+                });
+            }
+            if start > 0.0 {
+                cast.apply_transform(TimeTransform::Cut {
+                    start: 0.0,
+                    end: start,
+                });
+            }
+            cast.insert_redraw(0.0, redraw)
+        })
+    }
+
+    /// Gets or creates the modification chain at a byte location, capturing its original state into the running transaction the first time it's touched
+    fn chain_mut(&mut self, byte_location: usize) -> &mut ModificationChain {
+        if let Some(transaction) = self.transaction.as_mut() {
+            transaction
+                .chains
+                .entry(byte_location)
+                .or_insert_with(|| self.modifications.get(&byte_location).cloned());
+        }
+        self.revision += 1;
+        self.modifications
+            .entry(byte_location)
+            .or_insert_with(ModificationChain::new)
+    }
+
     // todo enable adding chains instead of just individual actions
-    // todo have the result of this be used to inform the completion of actions and thus be used to inform action history of users for undo and redo
-    /// Addition action inserts an action into the order specified. Delete action removes any action it points to based on order. If the delete is outside the order available it swaps the original line from on to off.
+    /// Addition action inserts an action into the order specified. Delete action removes any action it points to based on order. If the delete is outside the order available it swaps the original line from on to off. Each successful call is recorded as an entry in the undo history
     pub fn action(
         &mut self,
         action: ModificationAction,
@@ -105,12 +724,22 @@ impl CastFile {
         current_event: &EventPositioned,
         // This is only needed for timing boundaries in the Addition action
         previous_event: Option<&EventPositioned>,
+    ) -> Result<(), CastError> {
+        let description = format!("{} at {}s", action, current_event.event.time);
+        self.transaction(description, |cast| {
+            cast.apply_action(action, order, current_event, previous_event)
+        })
+    }
+
+    fn apply_action(
+        &mut self,
+        action: ModificationAction,
+        order: usize,
+        current_event: &EventPositioned,
+        previous_event: Option<&EventPositioned>,
     ) -> Result<(), CastError> {
         // Get or create the value at the current byte location
-        let entry = self
-            .modifications
-            .entry(current_event.byte_location)
-            .or_insert_with(ModificationChain::new);
+        let entry = self.chain_mut(current_event.byte_location);
 
         let order = order.clamp(0, entry.modifications.len());
         match action {
@@ -140,6 +769,13 @@ impl CastFile {
             ModificationAction::ModifyData(event_data) => {
                 match entry.modifications.get_mut(order) {
                     Some(event) => event.data = event_data,
+                    None if !entry.original_deleted => {
+                        entry.original_deleted = true;
+                        entry.modifications.push(Event {
+                            time: current_event.event.time,
+                            data: event_data,
+                        });
+                    }
                     None => return Err(CastError::ModificationError),
                 }
             }
@@ -147,9 +783,30 @@ impl CastFile {
         Ok(())
     }
 
+    /// Adds `event` after the last event as a single undoable step. Chains can only prepend to a line, so when the last event is a line of the file that line is hidden and put back at the end of its chain followed by `event`, the same way modifying a line does. A duration that was given is moved to the new end. Fails with `CastError::TimingError` unless `event` comes after the last event and with `CastError::UnverifiableTime` if there is no event to put it after
+    pub fn append(&mut self, event: Event) -> Result<(), CastError> {
+        let (last, chained) = self.last_event().ok_or(CastError::UnverifiableTime)?;
+        if event.time <= last.event.time {
+            return Err(CastError::TimingError);
+        }
+        let description = format!("Append {} at {}s", event.data.get_type(), event.time);
+        self.transaction(description, |cast| {
+            let time = event.time;
+            let chain = cast.chain_mut(last.byte_location);
+            if !chained {
+                chain.original_deleted = true;
+                chain.modifications.push(last.event);
+            }
+            chain.modifications.push(event);
+            if cast.header.duration.is_some() {
+                cast.header_mut().duration = Some(time);
+            }
+            Ok(())
+        })
+    }
+
     // todo enable adding chains instead of just individual actions
-    // todo have the result of this be used to inform the completion of actions and thus be used to inform action history of users for undo and redo
-    /// Addition action inserts an action into the order specified. Delete action removes any action it points to based on order. If the delete is outside the order available it swaps the original line from on to off.
+    /// Applies a compound action built from basic actions. The whole compound action is recorded as a single entry in the undo history and is rolled back entirely if any of its parts fail
     pub fn advanced_action(
         &mut self,
         action: AdvancedModificationAction,
@@ -159,11 +816,22 @@ impl CastFile {
         // todo change window to 3. Handle first by passing in 0 for first timing or f64 max for end timing. Change event position references to time values instead as that's all we're grabbing
         next_event: Option<&EventPositioned>,
     ) -> Result<(), CastError> {
-        // Get or create th value at the current byte location
-        let entry = self
-            .modifications
-            .entry(current_event.byte_location)
-            .or_insert_with(ModificationChain::new);
+        let description = format!("{} at {}s", action, current_event.event.time);
+        self.transaction(description, |cast| {
+            cast.apply_advanced_action(action, order, current_event, previous_event, next_event)
+        })
+    }
+
+    fn apply_advanced_action(
+        &mut self,
+        action: AdvancedModificationAction,
+        order: usize,
+        current_event: &EventPositioned,
+        previous_event: Option<&EventPositioned>,
+        next_event: Option<&EventPositioned>,
+    ) -> Result<(), CastError> {
+        // Get or create the value at the current byte location
+        let entry = self.chain_mut(current_event.byte_location);
 
         let order = order.clamp(0, entry.modifications.len());
         match action {
@@ -171,11 +839,22 @@ impl CastFile {
                 if let Some(next_event) = next_event {
                     if let Some(previous_event) = previous_event {
                         // First action's is deleting what you're pointing to
-                        self.action(ModificationAction::Deletion, order, current_event, None)?;
-                        // Then we add an action that is the edited event into the topmost region of the next event. We know the topmost region will be order 0 as it addition prepends events sequentially in vector order, thus 0 is first
-                        self.action(
+                        self.apply_action(
+                            ModificationAction::Deletion,
+                            order,
+                            current_event,
+                            None,
+                        )?;
+                        // Then we add the edited event directly before the next event. The next event may share a chain with the deleted one, in which case it moved forward by one
+                        let mut next_order = self.get_order(next_event);
+                        if next_event.byte_location == current_event.byte_location
+                            && next_event.chain_index.is_some_and(|index| index > order)
+                        {
+                            next_order -= 1;
+                        }
+                        self.apply_action(
                             ModificationAction::Addition(event),
-                            0,
+                            next_order,
                             next_event,
                             Some(previous_event),
                         )?;
@@ -189,38 +868,31 @@ impl CastFile {
             AdvancedModificationAction::Swap(target_event, target_order) => {
                 let current_data = current_event.event.data.clone();
                 let targeted_data = target_event.event.data.clone();
-                self.action(
+                self.apply_action(
                     ModificationAction::ModifyData(targeted_data),
                     order,
                     current_event,
                     None,
-                );
-                self.action(
+                )?;
+                self.apply_action(
                     ModificationAction::ModifyData(current_data),
                     target_order,
                     &target_event,
                     None,
-                );
+                )?;
             }
         };
         Ok(())
     }
 
-    // todo: We currently just assume that there will always be a requested time value due to the only modification of time being through the advanced modify action but we should likely have additional checks
-    /// This works on getting the order of the base events. It also operates under the presumption that *there are no duplicate time values for any event and that time events are ordered*. It returns either the order location or None if it is not present or there is no modification chain associated with that byte.
-
-    pub fn get_order(&self, byte_location: usize, base_event: &Event) -> usize {
-        self.modifications
-            .get(&byte_location)
-            // If no chain is found then return 0 as action can handle both if the chain exists or doesn't with the expected order of 0 for most behavior. When the first action order 0 works both for insertions and swapping the reading of the mmap event
-            .map_or(0, |chain| {
-                chain
-                    .modifications
-                    .iter()
-                    .position(|event| event.time == base_event.time)
-                    // ! If number isn't there then it should result in the maximum len of the chain. This allows delete on the original mmap event to work using similar syntax to other actions as the delete will target the len which necessarily flips if the original is being rendered.
-                    .unwrap_or(chain.modifications.len())
-            })
+    /// Gets the order actions on `positioned` have to be given. Chain events are found by their position in the chain so events sharing a time, like a redraw in front of the line after a cut, are never mistaken for each other. Lines of the file get the length of their chain, which is what makes a deletion flip the line itself
+    pub fn get_order(&self, positioned: &EventPositioned) -> usize {
+        positioned.chain_index.unwrap_or_else(|| {
+            self.modifications
+                .get(&positioned.byte_location)
+                // If no chain is found then return 0 as action can handle both if the chain exists or doesn't with the expected order of 0 for most behavior
+                .map_or(0, |chain| chain.modifications.len())
+        })
     }
 
     /// Gets `n` lines starting after the first encountered newline from `pos` (0.0 to 1.0) mapped to bytes of the file from 0 bytes to the end of the file. As it starts after the first newline the header is automatically excluded
@@ -233,7 +905,7 @@ impl CastFile {
         let byte_pos = (pos * self.file_size as f32) as usize;
 
         // Find the next instance of a newline starting from the mapped byte position
-        let mut current_pos = {
+        let current_pos = {
             // Branching result of a forward search for a newline. We add 1 to both branches as we want the character after the newline
             if let Some(next_newline) = self.mmap[byte_pos..].iter().position(|&b| b == b'\n') {
                 byte_pos + next_newline + 1
@@ -268,73 +940,508 @@ impl CastFile {
             end_pos = self.mmap.len();
         }
 
-        // Process the range and apply modifications
-        let mut events = Vec::new();
-        let mut mod_iter = self.modifications.range(current_pos..end_pos).peekable();
+        // Stream the range with modifications applied. Chains are keyed by the line they prepend to so every event belonging to a line before `end_pos` is included
+        Ok(self
+            .events_from(current_pos)
+            .take_while(|event| event.byte_location < end_pos)
+            .collect())
+    }
 
-        while current_pos < end_pos {
-            match mod_iter.peek() {
-                Some((&mod_pos, chain)) if mod_pos == current_pos => {
-                    // byte location for all actions before any potential delete action alters it
-                    let byte_location = current_pos;
-                    for event in chain.modifications.clone() {
-                        // Add the new event before current position so this is being pre-pended
-                        events.push(EventPositioned {
-                            event,
-                            byte_location,
-                        });
-                    }
-                    if chain.original_deleted {
-                        // Skip this original line in the mmap
-                        current_pos = find_next_newline(&self.mmap, current_pos);
+    /// Gets `n` events starting with event number `first`, counting from 0 with modifications applied. `None` until the line index is ready
+    pub fn get_events(&self, first: usize, n: usize) -> Option<Vec<EventPositioned>> {
+        Some(self.events_from_number(first)?.take(n).collect())
+    }
+
+    /// The line index if it's ready. The first call starts building it on another thread, or loading it from its cache in the user's cache directory, so this returns `None` until that finishes and the window never waits on a full read of the file
+    pub fn index(&self) -> Option<&LineIndex> {
+        if !self.index_started.replace(true) {
+            let mmap = Arc::clone(&self.mmap);
+            let slot = Arc::clone(&self.index);
+            let progress = Arc::clone(&self.index_progress);
+            let path = self.file_path.clone();
+            let data_start = self.data_start();
+            let source_version = self.source_version;
+            thread::spawn(move || {
+                let index = match LineIndex::load_cached(&path, source_version, mmap.len()) {
+                    Some(index) => index,
+                    None => {
+                        let Some(index) = LineIndex::build(&mmap, data_start, &progress) else {
+                            return;
+                        };
+                        index.save_cached(&path, source_version, mmap.len());
+                        index
                     }
-                    mod_iter.next(); // Move to next modification
+                };
+                let _ = slot.set(index);
+            });
+        }
+        self.index.get()
+    }
+
+    /// Share of the file the line index was built from so far, 1 once it is ready
+    pub fn index_progress(&self) -> f32 {
+        match self.index.get() {
+            Some(_) => 1.0,
+            None => self.index_progress.fraction(),
+        }
+    }
+
+    /// Lines the timeline removes before the checkpoint at `checkpoint`. Counting them needs every line so it runs on another thread whenever the timeline changes and `None` is returned until it's done
+    fn removed_before(&self, checkpoint: usize) -> Option<usize> {
+        if self.timeline.is_empty() {
+            return Some(0);
+        }
+        let mut removed_counts = self.removed_counts.borrow_mut();
+        if removed_counts
+            .as_ref()
+            .is_none_or(|(timeline, ..)| *timeline != self.timeline)
+        {
+            if let Some((_, progress, _)) = removed_counts.as_ref() {
+                progress.cancel();
+            }
+            let mmap = Arc::clone(&self.mmap);
+            let index = Arc::clone(&self.index);
+            let timeline = self.timeline.clone();
+            let progress = Arc::new(Progress::default());
+            let cancel = Arc::clone(&progress);
+            let slot = Arc::new(OnceLock::new());
+            let counts = Arc::clone(&slot);
+            thread::spawn(move || {
+                if let Some(removed) = index
+                    .get()
+                    .and_then(|index| index.count_removed(&mmap, &timeline, &progress))
+                {
+                    let _ = counts.set(removed);
                 }
-                Some((&mod_pos, _)) => {
-                    // Parse events until the next modification
-                    let parse_end = std::cmp::min(mod_pos, end_pos);
-                    if let Ok(mut parsed_events) =
-                        parse_events(&self.mmap[current_pos..parse_end], current_pos)
-                    {
-                        events.extend(parsed_events);
-                    }
-                    current_pos = parse_end;
+            });
+            *removed_counts = Some((self.timeline.clone(), cancel, slot));
+        }
+        let (.., slot) = removed_counts.as_ref()?;
+        slot.get().map(|counts| counts[checkpoint])
+    }
+
+    /// Number of events before the line at `byte_location` with modifications applied, which is the number of the first event read from there. `None` while the index or the effect of the timeline on it isn't known yet
+    pub fn event_number(&self, byte_location: usize) -> Option<usize> {
+        let index = self.index()?;
+        let checkpoint = index.checkpoint_before(byte_location);
+        let mut removed = self.removed_before(checkpoint)?;
+        let (mut lines, scan_start) = index
+            .checkpoints()
+            .get(checkpoint)
+            .map_or((0, byte_location), |&(line_start, _)| {
+                (checkpoint * INDEX_STRIDE, line_start)
+            });
+        for (_, _, time) in index::event_lines(&self.mmap, scan_start, byte_location) {
+            lines += 1;
+            if self.timeline.apply(time).is_none() {
+                removed += 1;
+            }
+        }
+
+        // Chains add their events and may hide the line they are attached to, which only counts if the timeline hasn't removed it already
+        let mut added = 0;
+        let mut deleted = 0;
+        for (&line_start, chain) in self.modifications.range(..byte_location) {
+            added += chain.modifications.len();
+            let line = &self.mmap[line_start..find_next_newline(&self.mmap, line_start)];
+            if chain.original_deleted
+                && parse_line(line, line_start)
+                    .is_some_and(|line| self.timeline.apply(line.event.time).is_some())
+            {
+                deleted += 1;
+            }
+        }
+        Some(lines - removed - deleted + added)
+    }
+
+    /// Total number of events with modifications applied, or `None` while it isn't known yet
+    pub fn event_count(&self) -> Option<usize> {
+        self.event_number(self.mmap.len())
+    }
+
+    /// Streams events starting with event number `number`. The closest index checkpoint before it is found by binary search so at most `INDEX_STRIDE` lines plus the modifications in between are read to get there
+    pub fn events_from_number(&self, number: usize) -> Option<Skip<EventIter<'_>>> {
+        let checkpoints = self.index()?.checkpoints();
+        let mut low = 0;
+        let mut high = checkpoints.len();
+        // Finds the first checkpoint past the event
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.event_number(checkpoints[mid].0)? <= number {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let start = match low {
+            0 => self.data_start(),
+            after => checkpoints[after - 1].0,
+        };
+        let skip = number.saturating_sub(self.event_number(start)?);
+        Some(self.events_from(start).skip(skip))
+    }
+
+    /// Number of the first event at or after `time`, which is where the event grid has to start to show it. `None` until the line index is ready
+    pub fn event_number_at_time(&self, time: f64) -> Option<usize> {
+        let line_start = self.seek_time(time);
+        let before = self
+            .events_from(line_start)
+            .take_while(|positioned| positioned.event.time < time)
+            .count();
+        Some(self.event_number(line_start)? + before)
+    }
+
+    /// Every `Marker` event in order with modifications applied. Lines are only parsed when their event code is a marker so this costs little more than finding the newlines of the file
+    pub fn markers(&self) -> Vec<EventPositioned> {
+        self.view()
+            .markers(&Progress::default())
+            .unwrap_or_default()
+    }
+
+    /// Reads every line of the file as it is on disk and fails on the first one that isn't a valid event or goes back in time. Returns the number of events. Blocks of the file are checked on several threads and their results joined in order, so the error reported is the first one in the file
+    pub fn check(&self) -> Result<usize, CastError> {
+        let mut previous_time = 0.0;
+        let mut count = 0;
+        let mut result = Ok(());
+        let mmap: &[u8] = &self.mmap;
+        let comments = self.source_version == 3;
+        scan::scan_blocks(
+            mmap,
+            self.data_start(),
+            mmap.len(),
+            &Progress::default(),
+            |block| check_block(&mmap[..block.end], block.start, comments),
+            |checked| {
+                if checked.first_time.is_some_and(|time| time < previous_time) {
+                    result = Err(CastError::TimingError);
+                    return false;
                 }
-                None => {
-                    // No more modifications, parse remaining events in range
-                    if let Ok(mut parsed_events) =
-                        parse_events(&self.mmap[current_pos..end_pos], current_pos)
-                    {
-                        events.extend(parsed_events);
+                count += checked.count;
+                previous_time = checked.last_time.unwrap_or(previous_time);
+                match checked.error {
+                    Some(error) => {
+                        result = Err(error);
+                        false
                     }
-                    break;
+                    None => true,
                 }
+            },
+        );
+        result.map(|()| count)
+    }
+
+    /// Byte location of the first event line, directly after the header
+    pub fn data_start(&self) -> usize {
+        find_next_newline(&self.mmap, 0)
+    }
+
+    /// Byte location in the memory map of the line `problem` was found on, for showing it among the events. v2 files are mapped as they are and converted v3 files keep every line where it was, while the lines of a v1 document don't match its events so `None` is returned for those
+    pub fn problem_location(&self, problem: &Problem) -> Option<usize> {
+        match self.source_version {
+            2 => Some(problem.byte),
+            3 => lint::line_start(&self.mmap, problem.line),
+            _ => None,
+        }
+    }
+
+    /// Time of the last event with modifications applied. Lines are read backwards from the end of the file so this is cheap regardless of file size
+    pub fn end_time(&self) -> f64 {
+        if let Some((revision, end_time)) = self.end_time_cache.get() {
+            if revision == self.revision {
+                return end_time;
             }
         }
+        let end_time = self.last_event().map_or(0.0, |(last, _)| last.event.time);
+        self.end_time_cache.set(Some((self.revision, end_time)));
+        end_time
+    }
+
+    /// Last event with modifications applied and whether it comes from the modification chain of its line rather than the line itself. Lines are read backwards from the end of the file
+    fn last_event(&self) -> Option<(EventPositioned, bool)> {
+        let data_start = self.data_start();
+        let mut line_end = self.mmap.len();
+        while line_end > data_start {
+            // Find the start of the line that ends at `line_end`, ignoring its trailing newline
+            let line_start = self.mmap[data_start..line_end - 1]
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(data_start, |p| data_start + p + 1);
+            if let Some(last) = self
+                .events_from(line_start)
+                .take_while(|event| event.byte_location < line_end)
+                .last()
+            {
+                let chained = last.chain_index.is_some();
+                return Some((last, chained));
+            }
+            line_end = line_start;
+        }
+        None
+    }
+
+    /// Streams every event of the file in order with all pending modifications applied
+    pub fn events(&self) -> EventIter<'_> {
+        self.events_from(self.data_start())
+    }
+
+    /// Streams events in order starting from the line at `byte_location` with all pending modifications applied. Only the line currently being read is parsed so this is usable on files of any size
+    pub fn events_from(&self, byte_location: usize) -> EventIter<'_> {
+        self.view().events_between(byte_location, self.mmap.len())
+    }
+
+    /// Runs `scan` over the events of each block of the file on several threads and hands the results to `combine` in file order. See `scan::scan_blocks`
+    pub fn scan_events<'a, T: Send>(
+        &'a self,
+        progress: &Progress,
+        scan: impl Fn(EventIter<'a>) -> T + Sync,
+        combine: impl FnMut(T) -> bool,
+    ) -> Option<()> {
+        self.view().scan(progress, scan, combine)
+    }
+
+    /// Copy of the events as they are now that can be scanned on other threads while the file goes on being edited
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            mmap: Arc::clone(&self.mmap),
+            modifications: self.modifications.clone(),
+            timeline: self.timeline.clone(),
+            header: self.header.clone(),
+            source_version: self.source_version,
+            revision: self.revision,
+            source: Arc::clone(&self.source),
+        }
+    }
 
-        Ok(events)
+    fn view(&self) -> EventView<'_> {
+        EventView {
+            mmap: &self.mmap,
+            modifications: &self.modifications,
+            timeline: &self.timeline,
+        }
     }
 
     // !todo make it to where when you save to a file you remove the current cast file in memory and reconstruct a Cast file handle pointing to the new file to free memory used for in-memory action history
     pub fn save_to_file(&self, path: &Path) -> Result<(), CastError> {
-        let file = File::create(path)?;
-        let writer = BufWriter::new(file);
-        self.write_modified_file(writer)
+        self.save_to_file_as(path, self.save_version())
+    }
+
+    /// Version `save_to_file` writes. v1 can't be written so those files are upgraded to v2
+    pub fn save_version(&self) -> u8 {
+        self.source_version.max(2)
+    }
+
+    /// Saves the modified recording as the given asciicast version, which must be 2 or 3
+    pub fn save_to_file_as(&self, path: &Path, version: u8) -> Result<(), CastError> {
+        self.snapshot()
+            .save_to_file_as(path, version, &Progress::default())
+    }
+
+    /// Saves this recording with `others` appended after it as a single v2 file at `path`, which may be any of the recordings being joined
+    pub fn concatenate(
+        &self,
+        others: &[CastFile],
+        path: &Path,
+        options: &ConcatOptions,
+    ) -> Result<(), CastError> {
+        let casts: Vec<&CastFile> = std::iter::once(self).chain(others).collect();
+        write_replacing(path, |writer| {
+            concat::write_concatenated(&casts, writer, options)
+        })
+    }
+
+    /// Saves each part planned by `split::plan_split` as its own recording next to `base` and returns where they were saved
+    pub fn split(&self, parts: &[SplitPart], base: &Path) -> Result<Vec<PathBuf>, CastError> {
+        let paths: Vec<PathBuf> = (0..parts.len())
+            .map(|index| split::part_path(base, index, parts.len()))
+            .collect();
+        split::write_parts(self, parts, &paths)?;
+        Ok(paths)
+    }
+
+    /// Renders the modified recording to an animated GIF at `path`
+    pub fn export_gif(&self, path: &Path, options: &GifOptions) -> Result<(), CastError> {
+        self.snapshot()
+            .export_gif(path, options, &Progress::default())
+    }
+}
+
+impl Drop for CastFile {
+    fn drop(&mut self) {
+        self.index_progress.cancel();
+        if let Some((_, progress, _)) = self.removed_counts.get_mut() {
+            progress.cancel();
+        }
+    }
+}
+
+/// The events of a `CastFile` as they were at one revision. It shares the memory map and holds its own copy of the modifications so it can be sent to other threads, which scan it while the file goes on being edited
+#[derive(Clone)]
+pub struct Snapshot {
+    mmap: Arc<Mmap>,
+    modifications: BTreeMap<usize, ModificationChain>,
+    timeline: Timeline,
+    pub header: Header,
+    /// See `CastFile::source_version`
+    pub source_version: u8,
+    /// Revision of the file the snapshot was taken at, which tells whether results found in it are still current
+    pub revision: u64,
+    /// The file as it is on disk, see `CastFile::source`
+    source: Arc<Mmap>,
+}
+
+impl Snapshot {
+    /// Streams every event in order with the modifications applied
+    pub fn events(&self) -> EventIter<'_> {
+        self.view()
+            .events_between(self.data_start(), self.mmap.len())
+    }
+
+    /// Byte location of the first event line, directly after the header
+    pub fn data_start(&self) -> usize {
+        find_next_newline(&self.mmap, 0)
+    }
+
+    /// Streams every event in order like `events`, counting how far into the file they got in `progress` and stopping early once it is cancelled. For scans that carry state from one event to the next and so can't be split into blocks, which tell a cancelled scan from a finished one with `Progress::is_cancelled`
+    pub fn events_tracked<'a>(
+        &'a self,
+        progress: &'a Progress,
+    ) -> impl Iterator<Item = EventPositioned> + 'a {
+        let end = self.mmap.len();
+        let mut read = self.data_start();
+        progress.expect(end - read);
+        self.events()
+            .take_while(move |_| !progress.is_cancelled())
+            .inspect(move |positioned| {
+                let position = positioned.byte_location.clamp(read, end);
+                progress.advance(position - read);
+                read = position;
+            })
+    }
+
+    /// Saves the recording as the given asciicast version, which must be 2 or 3. Nothing is written to `path` if `progress` is cancelled first
+    pub fn save_to_file_as(
+        &self,
+        path: &Path,
+        version: u8,
+        progress: &Progress,
+    ) -> Result<(), CastError> {
+        if !matches!(version, 2 | 3) {
+            return Err(CastError::InvalidVersion);
+        }
+        write_replacing(path, |writer| match version {
+            3 => convert::write_v3(self, writer, progress),
+            _ => self.write_modified_file(writer, progress),
+        })
+    }
+
+    /// Renders the recording to an animated GIF at `path`. Nothing is written to `path` if `progress` is cancelled first
+    pub fn export_gif(
+        &self,
+        path: &Path,
+        options: &GifOptions,
+        progress: &Progress,
+    ) -> Result<(), CastError> {
+        write_replacing(path, |writer| {
+            export::write_gif(self, writer, options, progress)
+        })
+    }
+
+    /// Runs `scan` over the events of each block of the file on several threads and hands the results to `combine` in file order. See `scan::scan_blocks`
+    pub fn scan_events<'a, T: Send>(
+        &'a self,
+        progress: &Progress,
+        scan: impl Fn(EventIter<'a>) -> T + Sync,
+        combine: impl FnMut(T) -> bool,
+    ) -> Option<()> {
+        self.view().scan(progress, scan, combine)
+    }
+
+    /// Every `Marker` event in order, or `None` if the scan was cancelled
+    pub fn markers(&self, progress: &Progress) -> Option<Vec<EventPositioned>> {
+        self.view().markers(progress)
+    }
+
+    /// Problems of the file as it is on disk, modifications left aside. See `lint::lint`
+    pub fn lint(&self, progress: &Progress) -> Option<Report> {
+        lint::lint(&self.source, progress)
     }
 
-    // ! This removes spaces but it can still be read so I'll deal with that later
-    fn serialize_event(event: &Event) -> Result<Vec<u8>, CastError> {
-        serde_json::to_string(event)
-            .map(|s| format!("{}\n", s).into_bytes())
-            .map_err(|e| CastError::SerializationError(e.to_string()))
+    /// Comment lines starting between the byte locations `start` and `end`, without their newline. Only converted v3 files have them as v3 is the only version with comments
+    pub(crate) fn comments_between(&self, start: usize, end: usize) -> impl Iterator<Item = &[u8]> {
+        let end = end.min(self.mmap.len());
+        let mut position = start.min(end);
+        std::iter::from_fn(move || {
+            while position < end {
+                let line_start = position;
+                position = find_next_newline(&self.mmap, line_start);
+                let line = self.mmap[line_start..position].trim_ascii();
+                if line.starts_with(b"#") {
+                    return Some(line);
+                }
+            }
+            None
+        })
+    }
+
+    /// Lines starting between the byte locations `start` and `end` that don't read as events, without their newline. Empty lines and comments aren't counted as damaged
+    fn damaged_between(&self, start: usize, end: usize) -> impl Iterator<Item = &[u8]> {
+        let end = end.min(self.mmap.len());
+        let mut position = start.min(end);
+        std::iter::from_fn(move || {
+            while position < end {
+                let line_start = position;
+                position = find_next_newline(&self.mmap, line_start);
+                let line = self.mmap[line_start..position].trim_ascii();
+                if !line.is_empty()
+                    && !line.starts_with(b"#")
+                    && parse_line(line, line_start).is_none()
+                {
+                    return Some(line);
+                }
+            }
+            None
+        })
     }
 
-    fn write_modified_file(&self, mut writer: impl Write) -> Result<(), CastError> {
+    /// Writes the recording as v2. Lines of files that were saved as they are and only edited are copied from the memory map, anything else is written event by event. Fails with `CastError::Cancelled` once `progress` is cancelled
+    fn write_modified_file(
+        &self,
+        mut writer: impl Write,
+        progress: &Progress,
+    ) -> Result<(), CastError> {
         // Write header first
         serde_json::to_writer(&mut writer, &self.header)
             .map_err(|e| CastError::SerializationError(e.to_string()))?;
         writeln!(&mut writer).map_err(|e| CastError::SerializationError(e.to_string()))?;
 
+        if !self.timeline.is_empty() || self.source_version == 3 {
+            // Original lines hold their untransformed times, and converted v3 files hold comments v2 doesn't allow, so every event has to be written out again. Exit events only exist in v3 and are left out like when joining recordings. Lines that don't read as events are copied as they are where they were so saving never loses them
+            let mut position = self.data_start();
+            for positioned in self.events_tracked(progress) {
+                for line in self.damaged_between(position, positioned.byte_location) {
+                    writer.write_all(line)?;
+                    writeln!(writer)?;
+                }
+                position = match positioned.chain_index {
+                    Some(_) => position.max(positioned.byte_location),
+                    None => find_next_newline(&self.mmap, positioned.byte_location),
+                };
+                if matches!(positioned.event.data, EventData::Exit(_)) {
+                    continue;
+                }
+                writer.write_all(&serialize_event(&positioned.event)?)?;
+            }
+            if progress.is_cancelled() {
+                return Err(CastError::Cancelled);
+            }
+            for line in self.damaged_between(position, self.mmap.len()) {
+                writer.write_all(line)?;
+                writeln!(writer)?;
+            }
+            writer.flush()?;
+            return Ok(());
+        }
+
         let mut current_pos = 0;
         // Find first newline to skip header in mmap
         while current_pos < self.mmap.len() && self.mmap[current_pos] != b'\n' {
@@ -342,6 +1449,7 @@ impl CastFile {
         }
         current_pos += 1; // Skip the newline itself
 
+        progress.expect(self.mmap.len() - current_pos.min(self.mmap.len()));
         let mut mod_iter = self.modifications.iter().peekable();
 
         while current_pos < self.mmap.len() {
@@ -350,24 +1458,26 @@ impl CastFile {
                 Some((&mod_pos, chain)) if mod_pos == current_pos => {
                     for event in chain.modifications.clone() {
                         // Write new event before current line
-                        let serialized = Self::serialize_event(&event)?;
+                        let serialized = serialize_event(&event)?;
                         writer.write_all(&serialized)?;
                     }
                     if chain.original_deleted {
                         // Skip this original line in the mmap
-                        current_pos = find_next_newline(&self.mmap, current_pos);
+                        let line_end = find_next_newline(&self.mmap, current_pos);
+                        progress.advance(line_end - current_pos);
+                        current_pos = line_end;
                     }
                     mod_iter.next(); // Move to next modification
                 }
                 Some((&mod_pos, _)) => {
                     // Write until next modification
                     let write_end = std::cmp::min(mod_pos, self.mmap.len());
-                    writer.write_all(&self.mmap[current_pos..write_end])?;
+                    write_tracked(&mut writer, &self.mmap[current_pos..write_end], progress)?;
                     current_pos = write_end;
                 }
                 None => {
                     // No more modifications, write rest of file
-                    writer.write_all(&self.mmap[current_pos..])?;
+                    write_tracked(&mut writer, &self.mmap[current_pos..], progress)?;
                     break;
                 }
             }
@@ -376,42 +1486,255 @@ impl CastFile {
         writer.flush()?;
         Ok(())
     }
+
+    fn view(&self) -> EventView<'_> {
+        EventView {
+            mmap: &self.mmap,
+            modifications: &self.modifications,
+            timeline: &self.timeline,
+        }
+    }
 }
 
-/// Parse multiple events at once from a byte slice with it's relative start position from the beginning of the file
-fn parse_events(slice: &[u8], base_position: usize) -> Result<Vec<EventPositioned>, CastError> {
-    let input = std::str::from_utf8(slice)?;
+/// Everything reading events needs, borrowed from a `CastFile` or `Snapshot`. Unlike the file itself this can be shared between the threads of a scan
+#[derive(Clone, Copy)]
+struct EventView<'a> {
+    mmap: &'a [u8],
+    modifications: &'a BTreeMap<usize, ModificationChain>,
+    timeline: &'a Timeline,
+}
 
-    let mut current_position = base_position;
-    let mut events = Vec::new();
+impl<'a> EventView<'a> {
+    /// Streams the events of the lines starting in `start..end` together with the chains attached to them
+    fn events_between(self, start: usize, end: usize) -> EventIter<'a> {
+        EventIter {
+            mmap: self.mmap,
+            position: start,
+            end,
+            modifications: self.modifications.range(start..).peekable(),
+            timeline: self.timeline,
+            pending: VecDeque::new(),
+        }
+    }
 
-    for line in input.lines() {
-        let line_start = current_position;
-        current_position += line.len() + 1; // +1 for newline
+    fn scan<T: Send>(
+        self,
+        progress: &Progress,
+        scan: impl Fn(EventIter<'a>) -> T + Sync,
+        combine: impl FnMut(T) -> bool,
+    ) -> Option<()> {
+        scan::scan_blocks(
+            self.mmap,
+            find_next_newline(self.mmap, 0),
+            self.mmap.len(),
+            progress,
+            |block| scan(self.events_between(block.start, block.end)),
+            combine,
+        )
+    }
 
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
+    fn markers(self, progress: &Progress) -> Option<Vec<EventPositioned>> {
+        let mut markers = Vec::new();
+        scan::scan_blocks(
+            self.mmap,
+            find_next_newline(self.mmap, 0),
+            self.mmap.len(),
+            progress,
+            |block| self.markers_between(block.start, block.end),
+            |found| {
+                markers.extend(found);
+                true
+            },
+        )?;
+        // Chains past the last line
+        for (&chain_start, chain) in self.modifications.range(self.mmap.len()..) {
+            markers.extend(chain_markers(chain_start, chain));
         }
+        Some(markers)
+    }
 
-        // Use the existing Serde deserialization
-        match serde_json::from_str::<Event>(line) {
-            Ok(event) => {
-                events.push(EventPositioned {
-                    event,
-                    byte_location: line_start,
-                });
+    /// Markers of the lines starting in `start..end` and the chains attached to them
+    fn markers_between(self, start: usize, end: usize) -> Vec<EventPositioned> {
+        let mut markers = Vec::new();
+        let mut chains = self.modifications.range(start..end).peekable();
+        for (line_start, line_end, _) in index::line_times(self.mmap, start, end) {
+            // Chain events come before the line they are attached to
+            let mut deleted = false;
+            while let Some((&chain_start, chain)) =
+                chains.next_if(|(&chain_start, _)| chain_start <= line_start)
+            {
+                markers.extend(chain_markers(chain_start, chain));
+                deleted = chain_start == line_start && chain.original_deleted;
             }
-            Err(e) => {
-                eprintln!("Failed to parse event at position {}: {}", line_start, e);
-                // Optionally: return Err(SerializationError::Json(e))
-                // But skipping bad lines might be more robust
+            let line = &self.mmap[line_start..line_end];
+            if deleted || index::line_code(line) != Some(b"m") {
                 continue;
             }
+            if let Some(mut positioned) = parse_line(line, line_start) {
+                if let Some(time) = self.timeline.apply(positioned.event.time) {
+                    positioned.event.time = time;
+                    markers.push(positioned);
+                }
+            }
+        }
+        // Chains on lines without a time, which have no events of their own
+        for (&chain_start, chain) in chains {
+            markers.extend(chain_markers(chain_start, chain));
+        }
+        markers
+    }
+}
+
+fn chain_markers(
+    chain_start: usize,
+    chain: &ModificationChain,
+) -> impl Iterator<Item = EventPositioned> + '_ {
+    chain
+        .modifications
+        .iter()
+        .enumerate()
+        .filter(|(_, event)| matches!(event.data, EventData::Marker(_)))
+        .map(move |(index, event)| EventPositioned {
+            event: event.clone(),
+            byte_location: chain_start,
+            chain_index: Some(index),
+        })
+}
+
+/// What checking the lines of one block found
+struct CheckedBlock {
+    count: usize,
+    first_time: Option<f64>,
+    last_time: Option<f64>,
+    /// The first problem in the block, lines after it aren't checked
+    error: Option<CastError>,
+}
+
+/// Checks the lines from `start` to the end of `bytes` the way `CastFile::check` does. Lines starting with `#` are skipped if `comments` are allowed, which they are in converted v3 files
+fn check_block(bytes: &[u8], start: usize, comments: bool) -> CheckedBlock {
+    let mut checked = CheckedBlock {
+        count: 0,
+        first_time: None,
+        last_time: None,
+        error: None,
+    };
+    let mut position = start;
+    while position < bytes.len() {
+        let line_start = position;
+        position = find_next_newline(bytes, line_start);
+        let line = match std::str::from_utf8(&bytes[line_start..position]) {
+            Ok(line) => line.trim(),
+            Err(e) => {
+                checked.error = Some(e.into());
+                break;
+            }
+        };
+        if line.is_empty() || (comments && line.starts_with('#')) {
+            continue;
+        }
+        let event: Event = match serde_json::from_str(line) {
+            Ok(event) => event,
+            Err(e) => {
+                checked.error = Some(CastError::InvalidEventFormat(format!(
+                    "{} at byte {}",
+                    e, line_start
+                )));
+                break;
+            }
+        };
+        if checked
+            .last_time
+            .is_some_and(|previous| event.time < previous)
+        {
+            checked.error = Some(CastError::TimingError);
+            break;
+        }
+        checked.first_time.get_or_insert(event.time);
+        checked.last_time = Some(event.time);
+        checked.count += 1;
+    }
+    checked
+}
+
+/// Iterator over the events of a `CastFile` that merges modification chains with the memory mapped lines they are prepended to
+pub struct EventIter<'a> {
+    mmap: &'a [u8],
+    /// Byte location of the next line to read from the mmap
+    position: usize,
+    /// Lines starting at or after this aren't read, which is the end of the mmap unless only one block is being read
+    end: usize,
+    modifications: Peekable<btree_map::Range<'a, usize, ModificationChain>>,
+    /// Transforms for the times of memory mapped events
+    timeline: &'a Timeline,
+    /// Events of a modification chain that have been reached but not yet returned
+    pending: VecDeque<EventPositioned>,
+}
+
+impl Iterator for EventIter<'_> {
+    type Item = EventPositioned;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            if self.position >= self.end {
+                return None;
+            }
+
+            match self.modifications.peek() {
+                Some((&mod_pos, chain)) if mod_pos == self.position => {
+                    // Chain events are all positioned at the line they are prepended to
+                    self.pending
+                        .extend(
+                            chain
+                                .modifications
+                                .iter()
+                                .enumerate()
+                                .map(|(index, event)| EventPositioned {
+                                    event: event.clone(),
+                                    byte_location: mod_pos,
+                                    chain_index: Some(index),
+                                }),
+                        );
+                    if chain.original_deleted {
+                        // Skip this original line in the mmap
+                        self.position = find_next_newline(self.mmap, self.position);
+                    }
+                    self.modifications.next();
+                }
+                _ => {
+                    let line_start = self.position;
+                    self.position = find_next_newline(self.mmap, line_start);
+                    if let Some(mut positioned) =
+                        parse_line(&self.mmap[line_start..self.position], line_start)
+                    {
+                        // Events removed by a cut are skipped like deleted lines
+                        if let Some(time) = self.timeline.apply(positioned.event.time) {
+                            positioned.event.time = time;
+                            return Some(positioned);
+                        }
+                    }
+                }
+            }
         }
     }
+}
+
+/// Parse a single event line with it's start position from the beginning of the file. Empty and malformed lines are skipped by returning `None`, `lint` is what reports them
+pub(crate) fn parse_line(line: &[u8], line_start: usize) -> Option<EventPositioned> {
+    let line = std::str::from_utf8(line).ok()?.trim();
+    if line.is_empty() {
+        return None;
+    }
 
-    Ok(events)
+    // Use the existing Serde deserialization. Skipping bad lines is more robust than failing the whole read
+    let event = serde_json::from_str::<Event>(line).ok()?;
+    Some(EventPositioned {
+        event,
+        byte_location: line_start,
+        chain_index: None,
+    })
 }
 
 #[derive(Error, Debug)]
@@ -431,7 +1754,18 @@ pub enum CastError {
     #[error("Invalid event format: {0}")]
     InvalidEventFormat(String),
 
-    #[error("Invalid version. This only supports the v2 format version for `.cast` files")]
+    #[error("Invalid event: {0}")]
+    InvalidEvent(#[from] EventError),
+
+    #[error("Invalid header: {0}")]
+    InvalidHeader(String),
+
+    #[error("Invalid theme: {0}")]
+    InvalidTheme(#[from] ThemeError),
+
+    #[error(
+        "Invalid version. This only supports the v1, v2 and v3 format versions for `.cast` files"
+    )]
     InvalidVersion,
 
     #[error("Serialization error: {0}")]
@@ -463,12 +1797,610 @@ pub enum CastError {
 
     #[error("No contextualizing event was passed in thus timing boundaries are unverifiable")]
     UnverifiableTime,
+
+    #[error("Export error: {0}")]
+    ExportError(String),
+
+    #[error("GIF encoding error: {0}")]
+    GifError(#[from] gif::EncodingError),
+
+    #[error("Refusing to save with {0} unreviewed high confidence secrets")]
+    UnredactedSecrets(usize),
+
+    #[error("Found {0} errors in the recording")]
+    LintErrors(usize),
+
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+impl CastError {
+    /// Process exit code for the headless mode. 1 is left for unexpected failures and 2 for usage errors
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CastError::IoError(_) | CastError::FileSystemError(_) | CastError::MmapError(_) => 3,
+            CastError::InvalidHexFormat(_)
+            | CastError::InvalidColorComponent { .. }
+            | CastError::InvalidPaletteFormat(_)
+            | CastError::InvalidEventFormat(_)
+            | CastError::InvalidEvent(_)
+            | CastError::InvalidHeader(_)
+            | CastError::InvalidTheme(_)
+            | CastError::InvalidVersion
+            | CastError::DeserializationError(_)
+            | CastError::JsonError(_)
+            | CastError::Utf8Error(_)
+            | CastError::LintErrors(_) => 4,
+            CastError::TimingError | CastError::ModificationError | CastError::UnverifiableTime => {
+                5
+            }
+            CastError::SerializationError(_)
+            | CastError::ExportError(_)
+            | CastError::GifError(_) => 6,
+            CastError::UnredactedSecrets(_) => 7,
+            CastError::Cancelled => 1,
+        }
+    }
+}
+
+// ! This removes spaces but it can still be read so I'll deal with that later
+fn serialize_event(event: &Event) -> Result<Vec<u8>, CastError> {
+    serde_json::to_string(event)
+        .map(|s| format!("{}\n", s).into_bytes())
+        .map_err(|e| CastError::SerializationError(e.to_string()))
+}
+
+/// Writes `bytes` in blocks, counting each as scanned in `progress` and failing with `CastError::Cancelled` once it is cancelled
+fn write_tracked(
+    writer: &mut impl Write,
+    bytes: &[u8],
+    progress: &Progress,
+) -> Result<(), CastError> {
+    for block in bytes.chunks(BLOCK_SIZE) {
+        if progress.is_cancelled() {
+            return Err(CastError::Cancelled);
+        }
+        writer.write_all(block)?;
+        progress.advance(block.len());
+    }
+    Ok(())
+}
+
+/// Writes a file beside `path` and moves it over `path` once `write` succeeds. The target is often a file that is memory mapped, which must not be truncated while it's still being read from
+pub(crate) fn write_replacing(
+    path: &Path,
+    write: impl FnOnce(BufWriter<File>) -> Result<(), CastError>,
+) -> Result<(), CastError> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    let file = File::create(&temporary)?;
+    match write(BufWriter::new(file)) {
+        Ok(()) => Ok(std::fs::rename(&temporary, path)?),
+        Err(e) => {
+            // The partial file is useless and the original error is the one worth reporting
+            let _ = std::fs::remove_file(&temporary);
+            Err(e)
+        }
+    }
 }
 
 // Helper function to find next newline position without overwhelming memory usage
-fn find_next_newline(buffer: &[u8], start: usize) -> usize {
+pub(crate) fn find_next_newline(buffer: &[u8], start: usize) -> usize {
     buffer[start..]
         .iter()
         .position(|&b| b == b'\n')
         .map_or(buffer.len(), |pos| start + pos + 1)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{open, HEADER};
+    use std::time::Duration;
+
+    /// Waits for the line index and the lines the timeline removes to be known
+    fn indexed(cast: &CastFile) {
+        while cast.event_count().is_none() {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn times(cast: &CastFile) -> Vec<f64> {
+        cast.events()
+            .map(|positioned| positioned.event.time)
+            .collect()
+    }
+
+    #[test]
+    fn damaged_lines_are_not_numbered() {
+        let cast = open(
+            "damaged",
+            HEADER,
+            &[
+                "[1.0,\"o\",\"a\"]",
+                "[2.0,\"o\" damaged",
+                "[3.0,\"o\",\"b\"]",
+            ],
+        );
+        indexed(&cast);
+        assert_eq!(cast.event_count(), Some(2));
+        let last = cast.events().last().unwrap();
+        assert_eq!(cast.event_number(last.byte_location), Some(1));
+        let second = cast.events_from_number(1).unwrap().next().unwrap();
+        assert_eq!(second.event.time, 3.0);
+        assert_eq!(times(&cast), [1.0, 3.0]);
+    }
+
+    #[test]
+    fn cancelled_saves_leave_the_file_alone() {
+        let mut cast = open(
+            "cancel-save",
+            HEADER,
+            &["[1.0,\"o\",\"a\"]", "[2.0,\"o\",\"b\"]"],
+        );
+        let progress = Progress::default();
+        progress.cancel();
+        let path = crate::test_util::temp_path("cancel-save-target");
+        std::fs::write(&path, "before").unwrap();
+        // Both the copy of unedited files and the rewrite after a cut stop
+        let copied = cast.snapshot().save_to_file_as(&path, 2, &progress);
+        cast.cut(0.5, 1.5).unwrap();
+        let rewritten = cast.snapshot().save_to_file_as(&path, 2, &progress);
+        let saved = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(path);
+        assert!(matches!(copied, Err(CastError::Cancelled)));
+        assert!(matches!(rewritten, Err(CastError::Cancelled)));
+        assert_eq!(saved, "before");
+        assert!(crate::cleanup::find_typos(&cast.snapshot(), &progress).is_none());
+    }
+
+    #[test]
+    fn damaged_lines_survive_saving_a_cut() {
+        let mut cast = open(
+            "damaged-cut",
+            HEADER,
+            &[
+                "[1.0,\"o\",\"a\"]",
+                "[2.0,\"o\" damaged",
+                "[3.0,\"i\",\"b\"]",
+                "[4.0,\"i\",\"c\"]",
+                "not an event",
+            ],
+        );
+        // Input doesn't change the screen so nothing is redrawn
+        cast.cut(2.5, 3.5).unwrap();
+        let path = crate::test_util::temp_path("damaged-cut-saved");
+        cast.save_to_file(&path).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(path);
+        let lines: Vec<&str> = saved.lines().skip(1).collect();
+        assert_eq!(
+            lines,
+            [
+                "[1.0,\"o\",\"a\"]",
+                "[2.0,\"o\" damaged",
+                "[3.0,\"i\",\"c\"]",
+                "not an event",
+            ]
+        );
+    }
+
+    /// A recording with an output event every tenth of a second from 0 to `seconds`
+    fn steady(name: &str, seconds: usize) -> CastFile {
+        let events: Vec<String> = (0..seconds * 10)
+            .map(|tenth| format!("[{}.{},\"o\",\"x\"]", tenth / 10, tenth % 10))
+            .collect();
+        let events: Vec<&str> = events.iter().map(String::as_str).collect();
+        open(name, HEADER, &events)
+    }
+
+    #[test]
+    fn seek_time_reaches_the_first_event_at_the_time() {
+        let mut cast = steady("seek", 300);
+        cast.cut(20.0, 50.0).unwrap();
+        cast.cut(100.0, 100.5).unwrap();
+        indexed(&cast);
+        for tenth in (0..2800).step_by(7) {
+            let time = tenth as f64 / 10.0 + 0.05;
+            let start = cast.seek_time(time);
+            let mut events = cast.events_from(start);
+            let first = events.next().unwrap();
+            assert!(
+                start == cast.data_start() || first.event.time < time,
+                "{}",
+                time
+            );
+            let expected = cast
+                .events()
+                .find(|positioned| positioned.event.time >= time);
+            let found = events.find(|positioned| positioned.event.time >= time);
+            assert_eq!(
+                found.map(|positioned| positioned.byte_location),
+                expected.map(|positioned| positioned.byte_location),
+                "{}",
+                time
+            );
+        }
+    }
+
+    /// Time and data of every event
+    fn contents(cast: &CastFile) -> Vec<(f64, String)> {
+        cast.events()
+            .map(|positioned| (positioned.event.time, positioned.event.data.get_data()))
+            .collect()
+    }
+
+    const ABC: [&str; 3] = [
+        "[1.0,\"o\",\"a\"]",
+        "[2.0,\"o\",\"b\"]",
+        "[3.0,\"o\",\"c\"]",
+    ];
+
+    fn abc() -> Vec<(f64, String)> {
+        vec![
+            (1.0, "a".to_string()),
+            (2.0, "b".to_string()),
+            (3.0, "c".to_string()),
+        ]
+    }
+
+    /// The three events around and including event `number`
+    fn window(cast: &CastFile, number: usize) -> [EventPositioned; 3] {
+        let events: Vec<EventPositioned> = cast.events().collect();
+        [
+            events[number - 1].clone(),
+            events[number].clone(),
+            events[number + 1].clone(),
+        ]
+    }
+
+    #[test]
+    fn undo_and_redo_a_single_action() {
+        let mut cast = open("undo", HEADER, &ABC);
+        assert!(!cast.undo());
+        let first = cast.events().next().unwrap();
+        cast.action(
+            ModificationAction::Deletion,
+            cast.get_order(&first),
+            &first,
+            None,
+        )
+        .unwrap();
+        let deleted = abc()[1..].to_vec();
+        assert_eq!(contents(&cast), deleted);
+        assert_eq!(cast.history().entries()[0].description, "Delete at 1s");
+
+        assert!(cast.undo());
+        assert_eq!(contents(&cast), abc());
+        assert!(!cast.history().can_undo());
+        assert!(cast.history().can_redo());
+
+        assert!(cast.redo());
+        assert_eq!(contents(&cast), deleted);
+        assert!(!cast.redo());
+    }
+
+    #[test]
+    fn a_new_edit_drops_what_could_be_redone() {
+        let mut cast = open("redo-dropped", HEADER, &ABC);
+        for number in [0, 1] {
+            let positioned = cast.events().nth(number).unwrap();
+            cast.action(
+                ModificationAction::ModifyData(EventData::Output(format!("{}", number))),
+                cast.get_order(&positioned),
+                &positioned,
+                None,
+            )
+            .unwrap();
+        }
+        assert!(cast.undo());
+        assert!(cast.undo());
+        let last = cast.events().nth(2).unwrap();
+        cast.action(
+            ModificationAction::Deletion,
+            cast.get_order(&last),
+            &last,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(cast.history().entries().len(), 1);
+        assert!(!cast.history().can_redo());
+        assert!(!cast.redo());
+        assert_eq!(contents(&cast), abc()[..2]);
+    }
+
+    #[test]
+    fn modify_and_swap_undo_as_one_step() {
+        let mut cast = open("undo-advanced", HEADER, &ABC);
+        let [previous, current, next] = window(&cast, 1);
+        cast.advanced_action(
+            AdvancedModificationAction::Modify(Event {
+                time: 2.5,
+                data: current.event.data.clone(),
+            }),
+            cast.get_order(&current),
+            &current,
+            Some(&previous),
+            Some(&next),
+        )
+        .unwrap();
+        assert_eq!(
+            contents(&cast),
+            [
+                (1.0, "a".to_string()),
+                (2.5, "b".to_string()),
+                (3.0, "c".to_string())
+            ]
+        );
+        assert_eq!(cast.history().entries().len(), 1);
+        assert!(cast.undo());
+        assert_eq!(contents(&cast), abc());
+
+        let [first, second, _] = window(&cast, 1);
+        let target_order = cast.get_order(&second);
+        cast.advanced_action(
+            AdvancedModificationAction::Swap(second, target_order),
+            cast.get_order(&first),
+            &first,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            contents(&cast),
+            [
+                (1.0, "b".to_string()),
+                (2.0, "a".to_string()),
+                (3.0, "c".to_string())
+            ]
+        );
+        assert!(cast.undo());
+        assert_eq!(contents(&cast), abc());
+        assert!(!cast.history().can_undo());
+    }
+
+    #[test]
+    fn failing_compound_actions_roll_back() {
+        let mut cast = open("rollback", HEADER, &ABC);
+        let [previous, current, next] = window(&cast, 1);
+        // The deletion goes through before moving past the next event fails
+        let result = cast.advanced_action(
+            AdvancedModificationAction::Modify(Event {
+                time: 5.0,
+                data: current.event.data.clone(),
+            }),
+            cast.get_order(&current),
+            &current,
+            Some(&previous),
+            Some(&next),
+        );
+        assert!(matches!(result, Err(CastError::TimingError)));
+        assert_eq!(contents(&cast), abc());
+        assert!(cast.history().entries().is_empty());
+        assert!(!cast.undo());
+    }
+
+    #[test]
+    fn header_and_timeline_edits_undo() {
+        let mut cast = open("undo-header", HEADER, &ABC);
+        let mut header = cast.header.clone();
+        header.title = Some("demo".to_string());
+        header.width = 100;
+        cast.edit_header("Edit header".to_string(), header).unwrap();
+        cast.transform_time(TimeTransform::Scale {
+            start: 0.0,
+            end: f64::INFINITY,
+            factor: 2.0,
+        })
+        .unwrap();
+        assert_eq!(times(&cast), [0.5, 1.0, 1.5]);
+
+        assert!(cast.undo());
+        assert_eq!(times(&cast), [1.0, 2.0, 3.0]);
+        assert_eq!(cast.header.title.as_deref(), Some("demo"));
+        assert!(cast.undo());
+        assert_eq!(
+            (cast.header.title.as_deref(), cast.header.width),
+            (None, 80)
+        );
+
+        assert!(cast.redo());
+        assert!(cast.redo());
+        assert_eq!(cast.header.title.as_deref(), Some("demo"));
+        assert_eq!(times(&cast), [0.5, 1.0, 1.5]);
+    }
+
+    #[test]
+    fn batch_actions_undo_as_one_step() {
+        let mut cast = open(
+            "undo-batch",
+            HEADER,
+            &[
+                "[1.0,\"o\",\"a\"]",
+                "[2.0,\"o\",\"x\"]",
+                "[2.5,\"o\",\"\\b \\b\"]",
+                "[3.0,\"o\",\"a\"]",
+                "[4.0,\"o\",\"c\"]",
+            ],
+        );
+        let original = contents(&cast);
+
+        let fixes = crate::cleanup::find_typos(&cast.snapshot(), &Progress::default()).unwrap();
+        cast.apply_typo_fixes(&fixes).unwrap();
+        assert_eq!(times(&cast), [1.0, 2.0, 3.0]);
+        let fixed = contents(&cast);
+
+        let search = crate::search::SearchOptions {
+            pattern: "a".to_string(),
+            case_sensitive: true,
+            ..Default::default()
+        }
+        .compile()
+        .unwrap();
+        let plan = crate::search::plan_replace(
+            &cast.snapshot(),
+            &search,
+            "b",
+            false,
+            &Progress::default(),
+        )
+        .unwrap();
+        cast.apply_replacements("Replace a".to_string(), &plan.replacements)
+            .unwrap();
+        assert_eq!(
+            contents(&cast),
+            [
+                (1.0, "b".to_string()),
+                (2.0, "b".to_string()),
+                (3.0, "c".to_string())
+            ]
+        );
+        let replaced = contents(&cast);
+
+        // The removed output is redrawn where it was cut
+        cast.cut(1.5, 2.5).unwrap();
+        assert_eq!(times(&cast), [1.0, 1.5, 2.0]);
+        assert_eq!(cast.history().entries().len(), 3);
+
+        for expected in [&replaced, &fixed, &original] {
+            assert!(cast.undo());
+            assert_eq!(&contents(&cast), expected);
+        }
+        assert!(!cast.undo());
+    }
+
+    #[test]
+    fn edits_after_a_cut_leave_the_redraw_alone() {
+        let mut cast = open(
+            "cut-edit",
+            HEADER,
+            &[
+                "[1.0,\"o\",\"hello\"]",
+                "[2.0,\"o\",\"world\"]",
+                "[3.0,\"o\",\"!\"]",
+            ],
+        );
+        cast.cut(1.0, 2.0).unwrap();
+        let redraw = contents(&cast)[0].clone();
+        assert_eq!(redraw.0, 1.0);
+        assert!(redraw.1.contains("hello"));
+        assert_eq!(
+            contents(&cast)[1..],
+            [(1.0, "world".to_string()), (2.0, "!".to_string())]
+        );
+
+        // The redraw shares its time and line with the event it is put in front of
+        let world = cast.events().nth(1).unwrap();
+        let order = cast.get_order(&world);
+        cast.action(
+            ModificationAction::ModifyData(EventData::Output("earth".to_string())),
+            order,
+            &world,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            contents(&cast),
+            [
+                redraw.clone(),
+                (1.0, "earth".to_string()),
+                (2.0, "!".to_string())
+            ]
+        );
+
+        let earth = cast.events().nth(1).unwrap();
+        let order = cast.get_order(&earth);
+        cast.action(ModificationAction::Deletion, order, &earth, None)
+            .unwrap();
+        assert_eq!(contents(&cast), [redraw, (2.0, "!".to_string())]);
+    }
+
+    #[test]
+    fn compressing_idle_time_shortens_the_recording() {
+        let mut cast = open(
+            "idle",
+            HEADER,
+            &[
+                "[1.0,\"o\",\"a\"]",
+                "[1.5,\"i\",\"b\"]",
+                "[11.5,\"o\",\"c\"]",
+                "[12.0,\"o\",\"d\"]",
+            ],
+        );
+        assert_eq!(cast.header.duration, None);
+        cast.compress_idle(&IdleCompression {
+            max_gap: 2.0,
+            ..IdleCompression::default()
+        })
+        .unwrap();
+        assert_eq!(times(&cast), [1.0, 1.5, 3.5, 4.0]);
+        assert_eq!(cast.end_time(), 4.0);
+        assert_eq!(cast.header.duration, Some(4.0));
+
+        cast.undo();
+        assert_eq!(cast.end_time(), 12.0);
+        assert_eq!(cast.header.duration, None);
+    }
+
+    #[test]
+    fn partially_erased_keystrokes_are_not_fixed() {
+        let cast = open(
+            "partial-typo",
+            HEADER,
+            &[
+                "[1.0,\"o\",\"ab\"]",
+                "[1.2,\"o\",\"c\"]",
+                "[1.4,\"o\",\"\\b \\b\\b \\b\"]",
+            ],
+        );
+        assert!(
+            crate::cleanup::find_typos(&cast.snapshot(), &Progress::default())
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn typo_fixes_only_remove_their_own_events() {
+        let mut cast = open(
+            "typo",
+            HEADER,
+            &[
+                "[1.0,\"o\",\"\\u001b[1m$ \\u001b[0m\"]",
+                "[1.2,\"o\",\"\\r\\n\"]",
+                "[1.2,\"o\",\"x\"]",
+                "[1.4,\"o\",\"\\b \\b\"]",
+                "[1.6,\"o\",\"l\"]",
+                "[1.8,\"o\",\"s\"]",
+            ],
+        );
+        let fixes = crate::cleanup::find_typos(&cast.snapshot(), &Progress::default()).unwrap();
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].erased_text, "x");
+        cast.apply_typo_fixes(&fixes).unwrap();
+
+        let events: Vec<(f64, String)> = cast
+            .events()
+            .map(|positioned| (positioned.event.time, positioned.event.data.get_data()))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (1.0, "\\u001b[1m$ \\u001b[0m".to_string()),
+                (1.2, "\\r\\n".to_string()),
+                (1.2, "l".to_string()),
+                (1.4, "s".to_string()),
+            ]
+        );
+        assert_eq!(cast.history().entries().len(), 1);
+        let mut timeline = Timeline::default();
+        timeline.push(TimeTransform::Offset {
+            start: 1.6,
+            end: f64::INFINITY,
+            offset: 1.2 - 1.6,
+        });
+        assert_eq!(cast.timeline, timeline);
+    }
+}
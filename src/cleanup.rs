@@ -0,0 +1,198 @@
+use crate::asciicast_egui::EventData;
+use crate::cast::{EventPositioned, Snapshot};
+use crate::scan::Progress;
+
+/// Events with more printable characters than this are treated as output or pastes rather than keystrokes
+const MAX_TYPED_CHARS: usize = 3;
+
+/// Sequences terminals and shells use to erase the character before the cursor, longest first so `\b \b` isn't read as a lone `\b`
+const ERASE_SEQUENCES: [&str; 4] = ["\u{8} \u{8}", "\u{8}\u{1b}[K", "\u{8}", "\u{7f}"];
+
+/// A run of mistyped keystrokes together with the events that erased them. Removing all of them and closing the gap they leave makes the recording look like the text was typed correctly the first time
+#[derive(Debug, Clone)]
+pub struct TypoFix {
+    /// Every event removed by the fix in file order. These are always all of the events between the first and the last one
+    pub events: Vec<EventPositioned>,
+    /// The characters that were typed and then erased
+    pub erased_text: String,
+    /// Time of the first event after the fix, which is moved back to where the mistake started. `None` when the fix is at the end of the file
+    pub next_time: Option<f64>,
+}
+
+impl TypoFix {
+    /// Time the first mistaken keystroke was typed
+    pub fn start_time(&self) -> f64 {
+        self.events
+            .first()
+            .map_or(0.0, |positioned| positioned.event.time)
+    }
+
+    /// Seconds removed from the recording when the fix is applied
+    pub fn saved_time(&self) -> f64 {
+        self.next_time
+            .map_or(0.0, |next_time| next_time - self.start_time())
+    }
+}
+
+/// A fix while the scan is still running. Events are kept with their position in the event stream so overlapping corrections can be merged and the result checked for gaps
+struct PendingFix {
+    events: Vec<(usize, EventPositioned)>,
+    next_time: Option<f64>,
+}
+
+impl PendingFix {
+    fn last_index(&self) -> usize {
+        self.events.last().map_or(0, |(index, _)| *index)
+    }
+
+    /// Whether the removed events leave nothing in between them
+    fn is_contiguous(&self) -> bool {
+        self.events
+            .windows(2)
+            .all(|pair| pair[0].0 + 1 == pair[1].0)
+    }
+
+    fn into_fix(self) -> TypoFix {
+        let events: Vec<EventPositioned> = self
+            .events
+            .into_iter()
+            .map(|(_, positioned)| positioned)
+            .collect();
+        // Echoed output shows what was typed, input is only used for recordings without echo
+        let typed_text = |input: bool| -> String {
+            events
+                .iter()
+                .filter(|positioned| matches!(positioned.event.data, EventData::Input(_)) == input)
+                .filter_map(|positioned| typed_chars(&positioned.event.data))
+                .collect()
+        };
+        let mut erased_text = typed_text(false);
+        if erased_text.is_empty() {
+            erased_text = typed_text(true);
+        }
+        TypoFix {
+            events,
+            erased_text,
+            next_time: self.next_time,
+        }
+    }
+}
+
+/// Characters an event typed, if it looks like keystrokes
+fn typed_chars(data: &EventData) -> Option<String> {
+    let (EventData::Output(_) | EventData::Input(_)) = data else {
+        return None;
+    };
+    let text = data.get_unescaped_data();
+    let count = text.chars().count();
+    let printable = text.chars().all(|c| !c.is_control());
+    (printable && (1..=MAX_TYPED_CHARS).contains(&count)).then_some(text)
+}
+
+/// Number of characters an event erases, if it consists of nothing but erase sequences
+fn erased_chars(data: &EventData) -> Option<usize> {
+    let (EventData::Output(_) | EventData::Input(_)) = data else {
+        return None;
+    };
+    let text = data.get_unescaped_data();
+    let mut rest = text.as_str();
+    let mut count = 0;
+    'outer: while !rest.is_empty() {
+        for sequence in ERASE_SEQUENCES {
+            if let Some(stripped) = rest.strip_prefix(sequence) {
+                rest = stripped;
+                count += 1;
+                continue 'outer;
+            }
+        }
+        return None;
+    }
+    (count > 0).then_some(count)
+}
+
+/// A keystroke event that hasn't been erased yet, with how many of its characters are still on screen
+struct Typed {
+    index: usize,
+    positioned: EventPositioned,
+    remaining: usize,
+}
+
+/// Scans the whole recording for typing that was corrected with backspace. Input and output are followed separately as recordings with stdin hold every keystroke twice, once as typed and once as echoed. Any event that is neither typing nor erasing ends the run of typing, so erasing text that was already on screen is never proposed. `None` if cancelled through `progress`
+pub fn find_typos(cast: &Snapshot, progress: &Progress) -> Option<Vec<TypoFix>> {
+    let mut fixes: Vec<PendingFix> = Vec::new();
+    // Keystrokes since the last unrelated event, split into output and input
+    let mut typed: [Vec<Typed>; 2] = [Vec::new(), Vec::new()];
+
+    for (index, positioned) in cast.events_tracked(progress).enumerate() {
+        // Fill in the event following the latest fix
+        if let Some(fix) = fixes.last_mut() {
+            if fix.last_index() + 1 == index {
+                fix.next_time = Some(positioned.event.time);
+            }
+        }
+
+        let stack = &mut typed[matches!(positioned.event.data, EventData::Input(_)) as usize];
+        if let Some(text) = typed_chars(&positioned.event.data) {
+            stack.push(Typed {
+                index,
+                positioned,
+                remaining: text.chars().count(),
+            });
+            continue;
+        }
+
+        let Some(mut count) = erased_chars(&positioned.event.data) else {
+            typed.iter_mut().for_each(Vec::clear);
+            continue;
+        };
+        // Only whole keystrokes can be removed, partially erased ones stop the fix
+        let mut erased = Vec::new();
+        while count > 0 {
+            match stack.last() {
+                Some(top) if top.remaining <= count => {
+                    count -= top.remaining;
+                    erased.push(stack.pop().expect("stack top was just checked"));
+                }
+                _ => break,
+            }
+        }
+        if count > 0 || erased.is_empty() {
+            // Erasing text from before the typing run or part of a keystroke, neither of which can be undone by removing events
+            typed.iter_mut().for_each(Vec::clear);
+            continue;
+        }
+
+        erased.reverse();
+        let first_index = erased[0].index;
+        let mut events: Vec<(usize, EventPositioned)> = erased
+            .into_iter()
+            .map(|typed| (typed.index, typed.positioned))
+            .collect();
+        events.push((index, positioned));
+
+        match fixes.last_mut() {
+            // Corrections reaching back into or directly following an earlier correction become one fix
+            Some(fix) if first_index <= fix.last_index() + 1 => {
+                fix.events.extend(events);
+                fix.events.sort_by_key(|(index, _)| *index);
+                fix.next_time = None;
+            }
+            _ => fixes.push(PendingFix {
+                events,
+                next_time: None,
+            }),
+        }
+    }
+
+    if progress.is_cancelled() {
+        return None;
+    }
+    // Echoed keystrokes interleave with input, so a fix is only safe if nothing it doesn't remove sits between its events
+    Some(
+        fixes
+            .into_iter()
+            .filter(PendingFix::is_contiguous)
+            .map(PendingFix::into_fix)
+            .collect(),
+    )
+}
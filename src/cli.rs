@@ -0,0 +1,768 @@
+use asciinema_editor::asciicast_egui::EventData;
+use asciinema_editor::cast::{CastError, CastFile};
+use asciinema_editor::concat::ConcatOptions;
+use asciinema_editor::lint;
+use asciinema_editor::redact::{Confidence, Finding, Redactor};
+use asciinema_editor::scan::Progress;
+use asciinema_editor::search::{self, SearchOptions};
+use asciinema_editor::split::{self, SplitPoints};
+use asciinema_editor::themes;
+use asciinema_editor::timing::{IdleCompression, TimeTransform};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use unicode_width::UnicodeWidthChar;
+
+const USAGE: &str = "\
+Usage: asciinema-editor [<command> <file> [options]]
+
+Without a command the editor window is opened.
+
+Commands:
+  info <file>             Print the header and event statistics
+  cat <file>              Print the recorded output to stdout
+  cut <file>              Delete --start..--end and close the gap, or keep only it with --trim
+  speed <file>            Play --start..--end (default whole file) --factor times faster
+  compress-idle <file>    Cap pauses to --max seconds, --max-after-input for pauses after input
+  convert <file>          Save as asciicast version --to 2 or 3
+  validate <file>         Check every event parses and times never go backwards
+  lint <file>             Report every problem of the file with its line and byte, as JSON with
+                          --json
+  theme <file>            Set the color theme to --theme
+  replace <file>          Replace --find with --replace in output and input data
+  redact <file>           Mask tokens, keys and typed passwords, listing what was masked
+  concat <file>           Append every --append recording after the file
+  split <file>            Save the parts between --at times, markers or --idle pauses as files
+                          named after the output with _1, _2 and so on added
+
+Options:
+  -o, --output <file>     Where to save edits, defaults to overwriting the input
+      --start <seconds>   Start of the time range
+      --end <seconds>     End of the time range
+      --trim              For cut, keep the range instead of deleting it
+      --factor <x>        Speed multiplier
+      --max <seconds>     Longest pause kept by compress-idle
+      --max-after-input <seconds>
+      --to <version>      Target version for convert
+      --find <text>       Text to replace, a regular expression with --regex
+      --replace <text>    Replacement, which can use capture groups like ${1} with --regex
+      --regex             Read --find as a regular expression
+      --ignore-case       Match --find regardless of case
+      --ignore-escapes    Match --find against the output without terminal escape sequences
+      --across-events     Also replace matches split between two events in a row
+      --dry-run           For replace and redact, report what would change without saving
+      --pattern <regex>   For redact, another secret to look for, can be given more than once
+      --mask <char>       For redact, the character secrets are masked with, * by default
+      --high-only         For redact, leave low confidence findings like password=... alone
+      --strict            Refuse to save while high confidence secrets remain
+      --append <file>     For concat, a recording to append, can be given more than once
+      --gap <seconds>     For concat, pause between recordings, 1 by default
+      --keep-header <n>   For concat, recording to keep the theme and environment of, 0 for the
+                          file and 1 onwards for the --append recordings in order
+      --at <seconds>      For split, a time to split at, can be given more than once
+      --at-markers        For split, split at every marker
+      --idle <seconds>    For split, split at every pause longer than this
+      --json              For lint, print the report as JSON
+      --theme <theme>     Built in theme name or terminal config file to take colors from, also
+                          applied by the other commands that save
+
+Exit codes: 0 success, 2 usage, 3 file access, 4 invalid recording, 5 invalid timing, 6 writing,
+7 secrets left with --strict";
+
+const COMMANDS: [&str; 13] = [
+    "info",
+    "cat",
+    "cut",
+    "speed",
+    "compress-idle",
+    "convert",
+    "validate",
+    "lint",
+    "theme",
+    "replace",
+    "redact",
+    "concat",
+    "split",
+];
+
+/// Options that take a value
+const VALUE_OPTIONS: [&str; 18] = [
+    "-o",
+    "--output",
+    "--start",
+    "--end",
+    "--factor",
+    "--max",
+    "--max-after-input",
+    "--to",
+    "--theme",
+    "--find",
+    "--replace",
+    "--pattern",
+    "--mask",
+    "--append",
+    "--gap",
+    "--keep-header",
+    "--at",
+    "--idle",
+];
+
+/// Options without a value. Anything else starting with `-` is a usage error, so a mistyped `--dry-run` never saves
+const FLAGS: [&str; 10] = [
+    "--trim",
+    "--regex",
+    "--ignore-case",
+    "--ignore-escapes",
+    "--across-events",
+    "--dry-run",
+    "--high-only",
+    "--strict",
+    "--at-markers",
+    "--json",
+];
+
+/// A failed command, either from bad arguments or from the edit itself
+enum CliError {
+    Usage(String),
+    Cast(CastError),
+}
+
+impl From<CastError> for CliError {
+    fn from(e: CastError) -> Self {
+        CliError::Cast(e)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        CliError::Cast(CastError::IoError(e))
+    }
+}
+
+/// Parsed command line of the form `<command> <file> [options]`
+struct Args {
+    command: String,
+    file: PathBuf,
+    /// Every value given for each option in order. Options given more than once use the last value unless they collect all of them
+    values: HashMap<String, Vec<String>>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Self, CliError> {
+        let mut args = args.iter();
+        let command = args
+            .next()
+            .ok_or_else(|| CliError::Usage("Missing command".to_string()))?
+            .clone();
+        if !COMMANDS.contains(&command.as_str()) {
+            return Err(CliError::Usage(format!("Unknown command {}", command)));
+        }
+        let mut file = None;
+        let mut values = HashMap::new();
+        let mut flags = Vec::new();
+        while let Some(arg) = args.next() {
+            if VALUE_OPTIONS.contains(&arg.as_str()) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| CliError::Usage(format!("Missing value for {}", arg)))?;
+                // The short and long output options are the same option
+                let name = if arg == "-o" { "--output" } else { arg };
+                values
+                    .entry(name.to_string())
+                    .or_insert_with(Vec::new)
+                    .push(value.clone());
+            } else if FLAGS.contains(&arg.as_str()) {
+                flags.push(arg.clone());
+            } else if arg.starts_with('-') {
+                return Err(CliError::Usage(format!("Unknown option {}", arg)));
+            } else if file.is_none() {
+                file = Some(PathBuf::from(arg));
+            } else {
+                return Err(CliError::Usage(format!("Unexpected argument {}", arg)));
+            }
+        }
+        let file = file.ok_or_else(|| CliError::Usage(format!("Missing file for {}", command)))?;
+        Ok(Self {
+            command,
+            file,
+            values,
+            flags,
+        })
+    }
+
+    fn value(&self, name: &str) -> Option<&String> {
+        self.values.get(name).and_then(|values| values.last())
+    }
+
+    fn all_values(&self, name: &str) -> &[String] {
+        self.values.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    fn number(&self, name: &str) -> Result<Option<f64>, CliError> {
+        self.value(name)
+            .map(|value| {
+                value.parse().map_err(|_| {
+                    CliError::Usage(format!("{} must be a number, got {}", name, value))
+                })
+            })
+            .transpose()
+    }
+
+    fn required_number(&self, name: &str) -> Result<f64, CliError> {
+        self.number(name)?
+            .ok_or_else(|| CliError::Usage(format!("{} is required for {}", name, self.command)))
+    }
+
+    fn has_flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+
+    /// Where edits are saved
+    fn output(&self) -> PathBuf {
+        self.value("--output")
+            .map_or_else(|| self.file.clone(), PathBuf::from)
+    }
+
+    /// Detectors with the user defined `--pattern`s added
+    fn redactor(&self) -> Result<Redactor, CliError> {
+        let mut redactor = Redactor::with_patterns(self.all_values("--pattern"))
+            .map_err(|e| CliError::Usage(format!("Invalid --pattern: {}", e)))?;
+        if let Some(mask) = self.value("--mask") {
+            let mut chars = mask.chars();
+            redactor.mask = match (chars.next(), chars.next()) {
+                (Some(c), None) if c.width() == Some(1) => c,
+                _ => {
+                    return Err(CliError::Usage(format!(
+                        "--mask must be a single one column character, got {}",
+                        mask
+                    )))
+                }
+            };
+        }
+        Ok(redactor)
+    }
+
+    /// With `--strict` fails while high confidence secrets remain, as there is no one to review them headless
+    fn check_secrets(&self, cast: &CastFile) -> Result<(), CliError> {
+        if self.has_flag("--strict") {
+            let remaining = self
+                .redactor()?
+                .unreviewed(&cast.snapshot(), &HashSet::new(), &Progress::default())
+                .expect("scans without a way to cancel them finish");
+            if !remaining.is_empty() {
+                print_findings(&remaining)?;
+                return Err(CastError::UnredactedSecrets(remaining.len()).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the edits as asciicast `version`, unless `--strict` finds secrets
+    fn save(&self, cast: &CastFile, version: u8) -> Result<(), CliError> {
+        self.check_secrets(cast)?;
+        cast.save_to_file_as(&self.output(), version)?;
+        Ok(())
+    }
+
+    /// Sets the theme given with `--theme` if there is one. A built in name wins over a file of the same name
+    fn apply_theme(&self, cast: &mut CastFile) -> Result<(), CliError> {
+        let Some(value) = self.value("--theme") else {
+            return Ok(());
+        };
+        let theme = match themes::preset(value) {
+            Some(theme) => theme,
+            None if Path::new(value).is_file() => {
+                themes::import(Path::new(value)).map_err(CastError::from)?
+            }
+            None => {
+                let names: Vec<&str> = themes::preset_names().collect();
+                return Err(CliError::Usage(format!(
+                    "--theme must be a file or one of {}, got {}",
+                    names.join(", "),
+                    value
+                )));
+            }
+        };
+        let mut header = cast.header.clone();
+        header.theme = Some(theme);
+        cast.edit_header(format!("Set theme to {}", value), header)?;
+        Ok(())
+    }
+}
+
+/// Runs a headless command and returns the process exit code
+pub fn run(args: &[String]) -> i32 {
+    if args
+        .first()
+        .is_some_and(|arg| arg == "-h" || arg == "--help" || arg == "help")
+    {
+        println!("{}", USAGE);
+        return 0;
+    }
+    match Args::parse(args).and_then(|args| execute(&args)) {
+        Ok(()) => 0,
+        Err(CliError::Usage(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            2
+        }
+        Err(CliError::Cast(e)) => {
+            eprintln!("Error: {}", e);
+            e.exit_code()
+        }
+    }
+}
+
+fn execute(args: &Args) -> Result<(), CliError> {
+    // Opening the file for editing would fail on the very problems lint reports
+    if args.command == "lint" {
+        return lint(args);
+    }
+    let mut cast = CastFile::new(args.file.clone())?;
+    match args.command.as_str() {
+        "info" | "cat" | "validate" if args.values.contains_key("--theme") => {
+            return Err(CliError::Usage(format!(
+                "--theme can't be used with {} as it doesn't save",
+                args.command
+            )));
+        }
+        "theme" if !args.values.contains_key("--theme") => {
+            return Err(CliError::Usage("--theme is required for theme".to_string()));
+        }
+        _ => args.apply_theme(&mut cast)?,
+    }
+    match args.command.as_str() {
+        "info" => info(&cast)?,
+        "cat" => {
+            let stdout = io::stdout();
+            let mut writer = BufWriter::new(stdout.lock());
+            for positioned in cast.events() {
+                if let EventData::Output(_) = positioned.event.data {
+                    writer.write_all(positioned.event.data.get_unescaped_data().as_bytes())?;
+                }
+            }
+            writer.flush()?;
+        }
+        "cut" => {
+            let start = args.required_number("--start")?;
+            let end = args.number("--end")?.unwrap_or(f64::INFINITY);
+            if args.has_flag("--trim") {
+                cast.trim(start, end)?;
+            } else {
+                cast.cut(start, end)?;
+            }
+            args.save(&cast, cast.save_version())?;
+        }
+        "speed" => {
+            cast.transform_time(TimeTransform::Scale {
+                start: args.number("--start")?.unwrap_or(0.0),
+                end: args.number("--end")?.unwrap_or(f64::INFINITY),
+                factor: args.required_number("--factor")?,
+            })?;
+            args.save(&cast, cast.save_version())?;
+        }
+        "compress-idle" => {
+            cast.compress_idle(&IdleCompression {
+                max_gap: args.required_number("--max")?,
+                max_gap_after_input: args.number("--max-after-input")?,
+                start: args.number("--start")?.unwrap_or(0.0),
+                end: args.number("--end")?.unwrap_or(f64::INFINITY),
+            })?;
+            args.save(&cast, cast.save_version())?;
+        }
+        "convert" => {
+            let version = match args.value("--to").map(String::as_str) {
+                Some("2") => 2,
+                Some("3") => 3,
+                Some(other) => {
+                    return Err(CliError::Usage(format!(
+                        "--to must be 2 or 3, got {}",
+                        other
+                    )))
+                }
+                None => return Err(CliError::Usage("--to is required for convert".to_string())),
+            };
+            args.save(&cast, version)?;
+        }
+        "validate" => {
+            let count = cast.check()?;
+            println!("{}: {} valid events", args.file.display(), count);
+        }
+        "theme" => args.save(&cast, cast.save_version())?,
+        "redact" => {
+            let redactor = args.redactor()?;
+            let mut findings = redactor
+                .scan(&cast.snapshot(), &Progress::default())
+                .expect("scans without a way to cancel them finish");
+            if args.has_flag("--high-only") {
+                findings.retain(|finding| finding.confidence == Confidence::High);
+            }
+            print_findings(&findings)?;
+            if !args.has_flag("--dry-run") {
+                cast.apply_replacements(
+                    format!("Redact {} secrets", findings.len()),
+                    &redactor.plan(&findings),
+                )?;
+                args.save(&cast, cast.save_version())?;
+            }
+        }
+        "concat" => {
+            let appended = args.all_values("--append");
+            if appended.is_empty() {
+                return Err(CliError::Usage(
+                    "--append is required for concat".to_string(),
+                ));
+            }
+            let keep_header = match args.value("--keep-header") {
+                Some(value) => match value.parse() {
+                    Ok(keep_header) if keep_header <= appended.len() => keep_header,
+                    _ => {
+                        return Err(CliError::Usage(format!(
+                            "--keep-header must be between 0 and {}, got {}",
+                            appended.len(),
+                            value
+                        )))
+                    }
+                },
+                None => 0,
+            };
+            let options = ConcatOptions {
+                gap: args
+                    .number("--gap")?
+                    .unwrap_or(ConcatOptions::default().gap),
+                keep_header,
+            };
+            let mut others = appended
+                .iter()
+                .map(|path| CastFile::new(PathBuf::from(path)))
+                .collect::<Result<Vec<_>, _>>()?;
+            // The theme has to end up on the recording whose header is kept
+            if keep_header > 0 {
+                args.apply_theme(&mut others[keep_header - 1])?;
+            }
+            args.check_secrets(&cast)?;
+            for other in &others {
+                args.check_secrets(other)?;
+            }
+            cast.concatenate(&others, &args.output(), &options)?;
+        }
+        "split" => {
+            let times = args
+                .all_values("--at")
+                .iter()
+                .map(|value| {
+                    value.parse().map_err(|_| {
+                        CliError::Usage(format!("--at must be a number, got {}", value))
+                    })
+                })
+                .collect::<Result<Vec<f64>, _>>()?;
+            let points = match (
+                times.is_empty(),
+                args.has_flag("--at-markers"),
+                args.number("--idle")?,
+            ) {
+                (false, false, None) => SplitPoints::Times(times),
+                (true, true, None) => SplitPoints::Markers,
+                (true, false, Some(limit)) => SplitPoints::IdleGaps(limit),
+                _ => {
+                    return Err(CliError::Usage(
+                        "split needs exactly one of --at, --at-markers or --idle".to_string(),
+                    ))
+                }
+            };
+            let parts = split::plan_split(&cast, &points)?;
+            args.check_secrets(&cast)?;
+            for path in cast.split(&parts, &args.output())? {
+                println!("{}", path.display());
+            }
+        }
+        "replace" => {
+            let find = args
+                .value("--find")
+                .ok_or_else(|| CliError::Usage("--find is required for replace".to_string()))?;
+            let replacement = args
+                .value("--replace")
+                .ok_or_else(|| CliError::Usage("--replace is required for replace".to_string()))?;
+            let options = SearchOptions {
+                pattern: find.clone(),
+                regex: args.has_flag("--regex"),
+                case_sensitive: !args.has_flag("--ignore-case"),
+                ignore_escapes: args.has_flag("--ignore-escapes"),
+                codes: Vec::new(),
+            };
+            let compiled = options
+                .compile()
+                .map_err(|e| CliError::Usage(format!("Invalid --find pattern: {}", e)))?;
+            let plan = search::plan_replace(
+                &cast.snapshot(),
+                &compiled,
+                replacement,
+                args.has_flag("--across-events"),
+                &Progress::default(),
+            )
+            .expect("scans without a way to cancel them finish");
+            println!(
+                "{} matches in {} events",
+                plan.count,
+                plan.replacements.len()
+            );
+            if !args.has_flag("--dry-run") {
+                cast.apply_replacements(
+                    format!("Replace {} matches of {}", plan.count, find),
+                    &plan.replacements,
+                )?;
+                args.save(&cast, cast.save_version())?;
+            }
+        }
+        other => unreachable!("command {} was checked while parsing", other),
+    }
+    Ok(())
+}
+
+/// Lists what was found and where, without the secrets themselves
+fn print_findings(findings: &[Finding]) -> Result<(), CliError> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for finding in findings {
+        writeln!(
+            out,
+            "{:.3}s  byte {}  {:?}  {}  {}",
+            finding.positioned.event.time,
+            finding.positioned.byte_location,
+            finding.confidence,
+            finding.detector,
+            finding.preview()
+        )?;
+    }
+    writeln!(out, "{} secrets found", findings.len())?;
+    Ok(())
+}
+
+/// Prints every problem of the file, or the whole report as JSON with `--json`. Errors fail the command so scripts can tell a broken file by the exit code while warnings alone don't
+fn lint(args: &Args) -> Result<(), CliError> {
+    if args.values.contains_key("--theme") {
+        return Err(CliError::Usage(
+            "--theme can't be used with lint as it doesn't save".to_string(),
+        ));
+    }
+    let report = lint::lint_file(&args.file, &Progress::default())?
+        .expect("scans without a way to cancel them finish");
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if args.has_flag("--json") {
+        serde_json::to_writer_pretty(&mut out, &report)
+            .map_err(|e| CastError::SerializationError(e.to_string()))?;
+        writeln!(out)?;
+    } else {
+        for problem in &report.problems {
+            writeln!(
+                out,
+                "{}:{}: {}: {} (byte {})",
+                args.file.display(),
+                problem.line,
+                problem.severity,
+                problem.message,
+                problem.byte
+            )?;
+        }
+        if report.omitted > 0 {
+            writeln!(out, "and {} more problems", report.omitted)?;
+        }
+        writeln!(
+            out,
+            "{}: {} events, {} errors, {} warnings",
+            args.file.display(),
+            report.events,
+            report.errors(),
+            report.warnings()
+        )?;
+    }
+    match report.errors() {
+        0 => Ok(()),
+        errors => Err(CastError::LintErrors(errors).into()),
+    }
+}
+
+fn info(cast: &CastFile) -> Result<(), CliError> {
+    let header = &cast.header;
+    // Blocks are counted on several threads and merged in file order so types are still listed in the order they first appear
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+    cast.scan_events(
+        &Progress::default(),
+        |events| {
+            let mut counts = Vec::new();
+            for positioned in events {
+                add_count(&mut counts, positioned.event.data.get_type(), 1);
+            }
+            counts
+        },
+        |block| {
+            for (kind, count) in block {
+                add_count(&mut counts, kind, count);
+            }
+            true
+        },
+    );
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if cast.source_version == header.version {
+        writeln!(out, "Version: {}", header.version)?;
+    } else {
+        writeln!(
+            out,
+            "Version: {} (read as {})",
+            cast.source_version, header.version
+        )?;
+    }
+    writeln!(out, "Dimensions: {}x{}", header.width, header.height)?;
+    if let Some(title) = &header.title {
+        writeln!(out, "Title: {}", title)?;
+    }
+    if let Some(command) = &header.command {
+        writeln!(out, "Command: {}", command)?;
+    }
+    if let Some(timestamp) = header.timestamp {
+        writeln!(out, "Timestamp: {}", timestamp)?;
+    }
+    if let Some(duration) = header.duration {
+        writeln!(out, "Header Duration: {}s", duration)?;
+    }
+    if let Some(idle_time_limit) = header.idle_time_limit {
+        writeln!(out, "Idle Time Limit: {}s", idle_time_limit)?;
+    }
+    writeln!(out, "End Time: {}s", cast.end_time())?;
+    let total: usize = counts.iter().map(|(_, count)| count).sum();
+    writeln!(out, "Events: {}", total)?;
+    for (kind, count) in counts {
+        writeln!(out, "  {}: {}", kind, count)?;
+    }
+    Ok(())
+}
+
+fn add_count(counts: &mut Vec<(&'static str, usize)>, kind: &'static str, added: usize) {
+    match counts.iter_mut().find(|(name, _)| *name == kind) {
+        Some((_, count)) => *count += added,
+        None => counts.push((kind, added)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, process};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn usage_error(args: &[String]) -> String {
+        match Args::parse(args) {
+            Err(CliError::Usage(message)) => message,
+            _ => panic!("{:?} parsed", args),
+        }
+    }
+
+    /// Writes `contents` to a file in the temporary directory that no other test uses
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "asciinema-editor-cli-test-{}-{}.cast",
+            process::id(),
+            name
+        ));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    const RECORDING: &str = "{\"version\":2,\"width\":80,\"height\":24}\n[0.5,\"o\",\"hello\"]\n";
+
+    #[test]
+    fn parses_values_flags_and_the_file() {
+        let parsed = Args::parse(&args(&[
+            "split",
+            "-o",
+            "out.cast",
+            "in.cast",
+            "--at",
+            "1",
+            "--at-markers",
+            "--at",
+            "-2",
+        ]))
+        .unwrap_or_else(|_| panic!("arguments didn't parse"));
+        assert_eq!(parsed.command, "split");
+        assert_eq!(parsed.file, PathBuf::from("in.cast"));
+        assert_eq!(parsed.output(), PathBuf::from("out.cast"));
+        assert_eq!(parsed.all_values("--at"), ["1", "-2"]);
+        assert_eq!(parsed.value("--at").map(String::as_str), Some("-2"));
+        assert!(parsed.has_flag("--at-markers"));
+        assert!(!parsed.has_flag("--trim"));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        let cases = [
+            (&[][..], "Missing command"),
+            (&["play", "a.cast"][..], "Unknown command play"),
+            (&["cut", "--trim"][..], "Missing file for cut"),
+            (
+                &["cut", "a.cast", "--start"][..],
+                "Missing value for --start",
+            ),
+            (
+                &["cut", "a.cast", "b.cast"][..],
+                "Unexpected argument b.cast",
+            ),
+            (
+                &["replace", "a.cast", "--dryrun"][..],
+                "Unknown option --dryrun",
+            ),
+            (
+                &["redact", "a.cast", "--stirct"][..],
+                "Unknown option --stirct",
+            ),
+            (&["cat", "a.cast", "-x"][..], "Unknown option -x"),
+        ];
+        for (arguments, message) in cases {
+            assert_eq!(usage_error(&args(arguments)), message);
+        }
+    }
+
+    #[test]
+    fn exit_codes_tell_failures_apart() {
+        let path = temp_file("exit-codes", RECORDING);
+        let file = path.to_str().unwrap();
+        let broken = temp_file("exit-codes-broken", "not a header\n");
+        let cases = [
+            (vec!["--help"], 0),
+            (vec!["info", file], 0),
+            (
+                vec![
+                    "replace",
+                    file,
+                    "--find",
+                    "hello",
+                    "--replace",
+                    "bye",
+                    "--dryrun",
+                ],
+                2,
+            ),
+            (vec!["redact", file, "--stirct"], 2),
+            (vec!["cut", file, "--start", "soon", "--end", "1"], 2),
+            (vec!["info", "/nonexistent/asciinema-editor.cast"], 3),
+            (vec!["info", broken.to_str().unwrap()], 4),
+            (vec!["cut", file, "--start", "2", "--end", "1"], 5),
+        ];
+        for (arguments, code) in cases {
+            assert_eq!(run(&args(&arguments)), code, "{:?}", arguments);
+        }
+        // Nothing above saved, least of all the mistyped dry run
+        assert_eq!(fs::read_to_string(&path).unwrap(), RECORDING);
+        let _ = fs::remove_file(path);
+        let _ = fs::remove_file(broken);
+    }
+}
@@ -0,0 +1,57 @@
+/// With the `gui` feature themes and the terminal use egui's color directly so they can be handed to the painter without conversion
+#[cfg(feature = "gui")]
+pub use eframe::egui::Color32;
+
+/// An sRGB color with alpha used without the `gui` feature. It mirrors the parts of egui's `Color32` the engine uses so the rest of the crate doesn't care which one it gets
+#[cfg(not(feature = "gui"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Color32([u8; 4]);
+
+#[cfg(not(feature = "gui"))]
+impl Color32 {
+    pub const TRANSPARENT: Color32 = Color32([0, 0, 0, 0]);
+    pub const BLACK: Color32 = Color32([0, 0, 0, 255]);
+    pub const WHITE: Color32 = Color32([255, 255, 255, 255]);
+    pub const GRAY: Color32 = Color32([160, 160, 160, 255]);
+    pub const RED: Color32 = Color32([255, 0, 0, 255]);
+    pub const GREEN: Color32 = Color32([0, 255, 0, 255]);
+    pub const BLUE: Color32 = Color32([0, 0, 255, 255]);
+    pub const YELLOW: Color32 = Color32([255, 255, 0, 255]);
+
+    pub const fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        Self([r, g, b, 255])
+    }
+
+    /// egui stores premultiplied alpha so the same is done here to keep `r`, `g` and `b` returning the same values with either type
+    pub fn from_rgba_unmultiplied(r: u8, g: u8, b: u8, a: u8) -> Self {
+        let premultiply = |channel: u8| (channel as u16 * a as u16 / 255) as u8;
+        Self([premultiply(r), premultiply(g), premultiply(b), a])
+    }
+
+    pub const fn r(&self) -> u8 {
+        self.0[0]
+    }
+
+    pub const fn g(&self) -> u8 {
+        self.0[1]
+    }
+
+    pub const fn b(&self) -> u8 {
+        self.0[2]
+    }
+
+    pub const fn a(&self) -> u8 {
+        self.0[3]
+    }
+
+    /// Blends towards `other` by `t` between 0 and 1, interpolating the stored values directly like egui does
+    pub fn lerp_to_gamma(&self, other: Self, t: f32) -> Self {
+        let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+        Self([
+            mix(self.0[0], other.0[0]),
+            mix(self.0[1], other.0[1]),
+            mix(self.0[2], other.0[2]),
+            mix(self.0[3], other.0[3]),
+        ])
+    }
+}
@@ -0,0 +1,237 @@
+use crate::asciicast_egui::{Event, EventData, Header};
+use crate::cast::{CastError, CastFile};
+use crate::timing::round_time;
+use std::io::Write;
+
+/// How recordings are joined into one
+#[derive(Debug, Clone)]
+pub struct ConcatOptions {
+    /// Seconds of pause between the last event of one recording and the first event of the next
+    pub gap: f64,
+    /// Position of the recording whose theme and environment the joined recording keeps, 0 being the recording the others are appended to
+    pub keep_header: usize,
+}
+
+impl Default for ConcatOptions {
+    fn default() -> Self {
+        Self {
+            gap: 1.0,
+            keep_header: 0,
+        }
+    }
+}
+
+/// Header of the joined recording. Everything but the theme, environment and terminal type comes from the first recording as that is the terminal playback starts in. A duration is only written if the first recording had one, and ends at the last event written rather than at an exit event left out after it
+fn joined_header(casts: &[&CastFile], options: &ConcatOptions) -> Result<Header, CastError> {
+    let first = casts
+        .first()
+        .ok_or_else(|| CastError::InvalidHeader("no recordings to join".to_string()))?;
+    let kept = casts.get(options.keep_header).ok_or_else(|| {
+        CastError::InvalidHeader(format!(
+            "there is no recording {} to keep the theme of, only {}",
+            options.keep_header,
+            casts.len()
+        ))
+    })?;
+    let mut header = first.header.clone();
+    header.version = 2;
+    header.theme = kept.header.theme.clone();
+    header.env = kept.header.env.clone();
+    header.term_type = kept.header.term_type.clone();
+    header.term_version = kept.header.term_version.clone();
+    if header.duration.is_some() {
+        let (last, others) = casts.split_last().expect("there is a first recording");
+        let gaps = options.gap * others.len() as f64;
+        let start: f64 = others.iter().map(|cast| cast.end_time()).sum();
+        header.duration = Some(round_time(start + gaps + last_written_time(last)));
+    }
+    Ok(header)
+}
+
+/// Time of the last event of `cast` that isn't an exit event. Exit events come last so only the end of the file is read unless it holds nothing else
+fn last_written_time(cast: &CastFile) -> f64 {
+    let written = |events: crate::cast::EventIter<'_>| {
+        events
+            .filter(|positioned| !matches!(positioned.event.data, EventData::Exit(_)))
+            .last()
+            .map(|positioned| positioned.event.time)
+    };
+    written(cast.events_from(cast.seek_time(cast.end_time())))
+        .or_else(|| written(cast.events()))
+        .unwrap_or(0.0)
+}
+
+/// Writes `casts` one after another as a single v2 recording, each with its modifications applied. Every recording starts `gap` seconds after the last event of the one before it and a `Resize` event is put at its start whenever the terminal it was recorded in differs from the size the one before it ended at. Exit events only exist in v3, so those of v3 recordings are left out. Events are streamed from each file as they are written so the recordings are never read into memory
+pub fn write_concatenated(
+    casts: &[&CastFile],
+    mut writer: impl Write,
+    options: &ConcatOptions,
+) -> Result<(), CastError> {
+    if !options.gap.is_finite() || options.gap < 0.0 {
+        return Err(CastError::TimingError);
+    }
+    let header = joined_header(casts, options)?;
+    serde_json::to_writer(&mut writer, &header)
+        .map_err(|e| CastError::SerializationError(e.to_string()))?;
+    writeln!(writer)?;
+
+    let mut size = (header.width, header.height);
+    let mut offset = 0.0;
+    for (position, cast) in casts.iter().enumerate() {
+        let recorded = (cast.header.width, cast.header.height);
+        if position > 0 && recorded != size {
+            let resize = Event {
+                time: round_time(offset),
+                data: EventData::Resize(recorded.0, recorded.1),
+            };
+            serde_json::to_writer(&mut writer, &resize)
+                .map_err(|e| CastError::SerializationError(e.to_string()))?;
+            writeln!(writer)?;
+            size = recorded;
+        }
+        for positioned in cast.events() {
+            let mut event = positioned.event;
+            match event.data {
+                EventData::Resize(width, height) => size = (width, height),
+                EventData::Exit(_) => continue,
+                _ => {}
+            }
+            event.time = round_time(event.time + offset);
+            serde_json::to_writer(&mut writer, &event)
+                .map_err(|e| CastError::SerializationError(e.to_string()))?;
+            writeln!(writer)?;
+        }
+        offset += cast.end_time() + options.gap;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::open;
+
+    /// Joins `casts` and reads back the header and every event as time, code and data
+    fn joined(casts: &[&CastFile], options: &ConcatOptions) -> (Header, Vec<(f64, char, String)>) {
+        let mut bytes = Vec::new();
+        write_concatenated(casts, &mut bytes, options).unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+        let mut lines = text.lines();
+        let header = serde_json::from_str(lines.next().unwrap()).unwrap();
+        let events = lines
+            .map(|line| {
+                let event: Event = serde_json::from_str(line).unwrap();
+                (event.time, event.data.code(), event.data.get_data())
+            })
+            .collect();
+        (header, events)
+    }
+
+    fn event(time: f64, code: char, data: &str) -> (f64, char, String) {
+        (time, code, data.to_string())
+    }
+
+    const SMALL: &str = r#"{"version":2,"width":80,"height":24,"duration":2.5}"#;
+
+    #[test]
+    fn recordings_follow_each_other_after_the_gap() {
+        let first = open(
+            "concat-first",
+            SMALL,
+            &[r#"[0.5,"o","one"]"#, r#"[2.0,"x","0"]"#],
+        );
+        let second = open(
+            "concat-second",
+            SMALL,
+            &[r#"[0.25,"o","two"]"#, r#"[1.0,"x","1"]"#],
+        );
+        let third = open("concat-third", SMALL, &[r#"[0.0,"o","three"]"#]);
+        let options = ConcatOptions {
+            gap: 0.5,
+            ..ConcatOptions::default()
+        };
+        let (header, events) = joined(&[&first, &second, &third], &options);
+        // Exit events are left out but still count towards where their recording ends
+        assert_eq!(
+            events,
+            [
+                event(0.5, 'o', "one"),
+                event(2.75, 'o', "two"),
+                event(4.0, 'o', "three"),
+            ]
+        );
+        assert_eq!(header.duration, Some(4.0));
+
+        let (header, events) = joined(&[&third, &second], &ConcatOptions::default());
+        assert_eq!(events, [event(0.0, 'o', "three"), event(1.25, 'o', "two"),]);
+        // The duration ends with the last event written, not the exit after it
+        assert_eq!(header.duration, Some(1.25));
+
+        // Which leaves nothing for the linter to report
+        let mut bytes = Vec::new();
+        write_concatenated(&[&third, &second], &mut bytes, &ConcatOptions::default()).unwrap();
+        let report = crate::lint::lint(&bytes, &Default::default()).unwrap();
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+    }
+
+    #[test]
+    fn differing_sizes_get_a_resize() {
+        let small = open("concat-small", SMALL, &[r#"[1.0,"o","small"]"#]);
+        let large = open(
+            "concat-large",
+            r#"{"version":2,"width":120,"height":40}"#,
+            &[r#"[1.0,"o","large"]"#],
+        );
+        let grown = open(
+            "concat-grown",
+            SMALL,
+            &[r#"[1.0,"r","120x40"]"#, r#"[2.0,"o","grown"]"#],
+        );
+        let (header, events) = joined(&[&small, &large, &small], &ConcatOptions::default());
+        assert_eq!((header.width, header.height), (80, 24));
+        assert_eq!(
+            events,
+            [
+                event(1.0, 'o', "small"),
+                event(2.0, 'r', "120x40"),
+                event(3.0, 'o', "large"),
+                event(4.0, 'r', "80x24"),
+                event(5.0, 'o', "small"),
+            ]
+        );
+
+        // The size a recording ends at counts, not the one it started in
+        let (_, events) = joined(&[&grown, &large], &ConcatOptions::default());
+        assert_eq!(
+            events,
+            [
+                event(1.0, 'r', "120x40"),
+                event(2.0, 'o', "grown"),
+                event(4.0, 'o', "large"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        let cast = open("concat-options", SMALL, &[r#"[1.0,"o","a"]"#]);
+        let gap = ConcatOptions {
+            gap: -1.0,
+            ..ConcatOptions::default()
+        };
+        assert!(matches!(
+            write_concatenated(&[&cast, &cast], Vec::new(), &gap),
+            Err(CastError::TimingError)
+        ));
+        let keep_header = ConcatOptions {
+            keep_header: 2,
+            ..ConcatOptions::default()
+        };
+        assert!(matches!(
+            write_concatenated(&[&cast, &cast], Vec::new(), &keep_header),
+            Err(CastError::InvalidHeader(_))
+        ));
+    }
+}
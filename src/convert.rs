@@ -0,0 +1,536 @@
+use crate::asciicast_egui::{Event, Header, Theme};
+use crate::cast::{CastError, Snapshot};
+use crate::scan::Progress;
+use crate::timing::round_time;
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Numbers the converted files of this process so each gets its own name
+static CONVERTED_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Only the version field of a header, used to pick a parser before committing to one
+#[derive(Deserialize)]
+struct VersionProbe {
+    version: u8,
+}
+
+/// Finds the asciicast version of a file. v2 and v3 files start with a single line JSON header while v1 files are one JSON document that is usually pretty printed over many lines, so the first line is tried before the whole file
+pub fn detect_version(bytes: &[u8]) -> Result<u8, CastError> {
+    let header_end = bytes
+        .iter()
+        .position(|&b| b == b'\n')
+        .unwrap_or(bytes.len());
+    if let Ok(probe) = serde_json::from_slice::<VersionProbe>(&bytes[..header_end]) {
+        return Ok(probe.version);
+    }
+    serde_json::from_slice::<VersionProbe>(bytes)
+        .map(|probe| probe.version)
+        .map_err(|e| CastError::DeserializationError(e.to_string()))
+}
+
+/// Fields of an asciicast v1 document besides its output
+#[derive(Deserialize)]
+pub(crate) struct V1Header {
+    pub(crate) width: u16,
+    pub(crate) height: u16,
+    #[serde(default)]
+    pub(crate) duration: Option<f64>,
+    #[serde(default)]
+    command: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    env: Option<HashMap<String, String>>,
+}
+
+/// A complete asciicast v1 document as described in the [documentation](https://docs.asciinema.org/manual/asciicast/v1/)
+#[derive(Deserialize)]
+struct V1Document {
+    #[serde(flatten)]
+    header: V1Header,
+    /// Output as pairs of delay since the previous output and the written data
+    stdout: Vec<(f64, String)>,
+}
+
+/// Converts an asciicast v1 document into the bytes of the equivalent v2 file. Relative delays become absolute timestamps and every frame becomes an Output event. The result is a memory mapped `ConvertedFile` so `CastFile` can treat it exactly like a v2 file on disk. v1 documents are read whole so their frames are held in memory while converting
+pub fn v1_to_v2(bytes: &[u8]) -> Result<(Header, Mmap), CastError> {
+    let document: V1Document = serde_json::from_slice(bytes)
+        .map_err(|e| CastError::DeserializationError(e.to_string()))?;
+
+    // v1 has no empty title or command semantics that v2 needs to keep
+    let non_empty = |value: Option<String>| value.filter(|value| !value.is_empty());
+    let v1 = document.header;
+    let header = Header {
+        version: 2,
+        width: v1.width,
+        height: v1.height,
+        timestamp: None,
+        duration: v1.duration,
+        idle_time_limit: None,
+        command: non_empty(v1.command),
+        title: non_empty(v1.title),
+        env: v1.env,
+        theme: None,
+        term_type: None,
+        term_version: None,
+        tags: None,
+    };
+
+    let mut converted = ConvertedFile::create()?;
+    serde_json::to_writer(&mut converted.writer, &header)?;
+    writeln!(converted.writer)?;
+    let mut time = 0.0;
+    for (delay, data) in &document.stdout {
+        time += delay;
+        serde_json::to_writer(&mut converted.writer, &(round_time(time), "o", data))?;
+        writeln!(converted.writer)?;
+    }
+
+    Ok((header, converted.into_mmap()?))
+}
+
+/// Terminal description of a v3 header
+#[derive(Deserialize, Serialize)]
+struct V3Term {
+    cols: u16,
+    rows: u16,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    term_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    theme: Option<Theme>,
+}
+
+/// Header of an asciicast v3 file as described in the [documentation](https://docs.asciinema.org/manual/asciicast/v3/)
+#[derive(Deserialize, Serialize)]
+struct V3Header {
+    version: u8,
+    term: V3Term,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    idle_time_limit: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    env: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tags: Option<Vec<String>>,
+}
+
+/// Converts an asciicast v3 file into the bytes of the equivalent v2 file. Event intervals are summed into absolute timestamps and only the time of each line is rewritten so event data is kept byte for byte. Every other line, such as comments and lines without a readable interval, is copied as it is, which keeps the lines of the two files the same and lets saving as v3 put the comments back. Bad lines are skipped when reading events like they are in v2 files and reported by `check` and `lint`. The file is streamed line by line into a memory mapped `ConvertedFile` so only the pages being read are held in memory, at the cost of a copy of the file in the temporary directory for as long as it is open
+pub fn v3_to_v2(bytes: &[u8]) -> Result<(Header, Mmap), CastError> {
+    let header_end = bytes
+        .iter()
+        .position(|&b| b == b'\n')
+        .unwrap_or(bytes.len());
+    let json = serde_json::from_slice(&bytes[..header_end])
+        .map_err(|e| CastError::DeserializationError(e.to_string()))?;
+    let header = v3_header(json).map_err(|e| CastError::DeserializationError(e.to_string()))?;
+
+    let mut converted = ConvertedFile::create()?;
+    serde_json::to_writer(&mut converted.writer, &header)?;
+    writeln!(converted.writer)?;
+    let mut time = 0.0;
+    let mut line_start = (header_end + 1).min(bytes.len());
+    while line_start < bytes.len() {
+        let line_end = bytes[line_start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(bytes.len(), |p| line_start + p);
+        let line = &bytes[line_start..line_end];
+        line_start = line_end + 1;
+        match event_interval(line) {
+            Some((interval, rest)) => {
+                time += interval;
+                write!(converted.writer, "[{}", round_time(time))?;
+                converted.writer.write_all(rest)?;
+            }
+            None => converted.writer.write_all(line)?,
+        }
+        writeln!(converted.writer)?;
+    }
+
+    Ok((header, converted.into_mmap()?))
+}
+
+/// Interval of a v3 event line and the rest of the line from the comma after it. The interval is everything between the opening bracket and the first comma as numbers can't contain commas
+fn event_interval(line: &[u8]) -> Option<(f64, &[u8])> {
+    let rest = line.trim_ascii().strip_prefix(b"[")?;
+    let comma = rest.iter().position(|&b| b == b',')?;
+    let interval = std::str::from_utf8(&rest[..comma])
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some((interval, &rest[comma..]))
+}
+
+/// Reads a v3 header into the v2 header it converts to, keeping the v3 only fields that the v2 header line can't hold
+pub(crate) fn v3_header(json: serde_json::Value) -> Result<Header, serde_json::Error> {
+    let v3: V3Header = serde_json::from_value(json)?;
+    Ok(Header {
+        version: 2,
+        width: v3.term.cols,
+        height: v3.term.rows,
+        timestamp: v3.timestamp,
+        duration: None,
+        idle_time_limit: v3.idle_time_limit,
+        command: v3.command,
+        title: v3.title,
+        env: v3.env,
+        theme: v3.term.theme,
+        term_type: v3.term.term_type,
+        term_version: v3.term.version,
+        tags: v3.tags,
+    })
+}
+
+/// Writes the recording with modifications applied as a v3 file. Event times are turned back into intervals from the previous event. Fails with `CastError::Cancelled` once `progress` is cancelled
+pub fn write_v3(
+    cast: &Snapshot,
+    mut writer: impl Write,
+    progress: &Progress,
+) -> Result<(), CastError> {
+    let header = &cast.header;
+    let v3 = V3Header {
+        version: 3,
+        term: V3Term {
+            cols: header.width,
+            rows: header.height,
+            term_type: header
+                .term_type
+                .clone()
+                .or_else(|| header.env.as_ref().and_then(|env| env.get("TERM")).cloned()),
+            version: header.term_version.clone(),
+            theme: header.theme.clone(),
+        },
+        timestamp: header.timestamp,
+        idle_time_limit: header.idle_time_limit,
+        command: header.command.clone(),
+        title: header.title.clone(),
+        env: header.env.clone(),
+        tags: header.tags.clone(),
+    };
+    serde_json::to_writer(&mut writer, &v3)
+        .map_err(|e| CastError::SerializationError(e.to_string()))?;
+    writeln!(writer)?;
+
+    let mut previous = 0.0;
+    let mut written_to = cast.data_start();
+    for positioned in cast.events_tracked(progress) {
+        // Comments of converted v3 files go back in front of the line they were in front of
+        for comment in cast.comments_between(written_to, positioned.byte_location) {
+            writer.write_all(comment)?;
+            writeln!(writer)?;
+        }
+        written_to = written_to.max(positioned.byte_location);
+        let time = round_time(positioned.event.time);
+        // Out of order events can't be expressed as intervals, they are written as happening together with the previous event
+        let interval = Event {
+            time: round_time((time - previous).max(0.0)),
+            data: positioned.event.data,
+        };
+        previous = time.max(previous);
+        serde_json::to_writer(&mut writer, &interval)
+            .map_err(|e| CastError::SerializationError(e.to_string()))?;
+        writeln!(writer)?;
+    }
+    if progress.is_cancelled() {
+        return Err(CastError::Cancelled);
+    }
+    for comment in cast.comments_between(written_to, usize::MAX) {
+        writer.write_all(comment)?;
+        writeln!(writer)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// A v2 file converted from another version, written to the temporary directory and memory mapped like a file that was opened. Its pages are read from disk as they are used and can be dropped again, where an in memory copy would hold the whole recording. The file is removed once it is mapped, or on failure, which systems that keep mapped files open leave to the cleanup of the temporary directory
+struct ConvertedFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl ConvertedFile {
+    fn create() -> Result<Self, CastError> {
+        let path = std::env::temp_dir().join(format!(
+            "asciinema-editor-{}-{}.cast",
+            process::id(),
+            CONVERTED_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Self {
+            path,
+            writer: BufWriter::new(file),
+        })
+    }
+
+    fn into_mmap(mut self) -> Result<Mmap, CastError> {
+        self.writer.flush()?;
+        unsafe { Mmap::map(self.writer.get_ref()).map_err(|e| CastError::MmapError(e.to_string())) }
+    }
+}
+
+impl Drop for ConvertedFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asciicast_egui::EventData;
+    use crate::cast::{CastFile, ModificationAction};
+    use crate::test_util::open;
+
+    fn saved_v3(cast: &CastFile) -> String {
+        let mut bytes = Vec::new();
+        write_v3(&cast.snapshot(), &mut bytes, &Progress::default()).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    /// Lines after the header
+    fn body(file: &str) -> Vec<&str> {
+        file.lines().skip(1).collect()
+    }
+
+    const HEADER: &str = r#"{"version":3,"term":{"cols":80,"rows":24}}"#;
+
+    #[test]
+    fn round_trip_keeps_intervals_and_comments() {
+        let lines = [
+            "# recorded on a train",
+            r#"[0.5,"o","$ "]"#,
+            r#"[1.25,"i","ls\r"]"#,
+            "# slow disk",
+            r#"[0.125,"o","a b c\r\n"]"#,
+            r#"[0.0,"r","100x30"]"#,
+            r#"[2.0,"m","done"]"#,
+            r#"[0.25,"x","0"]"#,
+            "# the end",
+        ];
+        let cast = open("round-trip", HEADER, &lines);
+        let saved = saved_v3(&cast);
+        assert_eq!(body(&saved), lines);
+    }
+
+    #[test]
+    fn intervals_become_absolute_times() {
+        let cast = open(
+            "absolute",
+            HEADER,
+            &[r#"[0.5,"o","a"]"#, r#"[1.25,"o","b"]"#, r#"[0.25,"x","1"]"#],
+        );
+        let events: Vec<Event> = cast.events().map(|positioned| positioned.event).collect();
+        let times: Vec<f64> = events.iter().map(|event| event.time).collect();
+        assert_eq!(times, [0.5, 1.75, 2.0]);
+        assert!(matches!(events[2].data, EventData::Exit(1)));
+        assert_eq!(
+            (cast.header.version, cast.header.width, cast.header.height),
+            (2, 80, 24)
+        );
+    }
+
+    #[test]
+    fn exit_event_survives_an_edit() {
+        let mut cast = open("exit", HEADER, &[r#"[0.5,"o","a"]"#, r#"[1.5,"x","130"]"#]);
+        let first = cast.events().next().unwrap();
+        cast.action(ModificationAction::Deletion, 0, &first, None)
+            .unwrap();
+        assert_eq!(body(&saved_v3(&cast)), [r#"[2.0,"x","130"]"#]);
+    }
+
+    #[test]
+    fn bad_lines_are_kept_verbatim() {
+        let bad = r#"[oops,"o","not a time"]"#;
+        let lines = [r#"[0.5,"o","a"]"#, bad, r#"[1.0,"o","b"]"#];
+        let contents = format!("{}\n{}\n", HEADER, lines.join("\n"));
+        let (_, converted) = v3_to_v2(contents.as_bytes()).unwrap();
+        let converted = std::str::from_utf8(&converted).unwrap();
+        assert_eq!(
+            body(converted),
+            [r#"[0.5,"o","a"]"#, bad, r#"[1.5,"o","b"]"#]
+        );
+
+        let cast = open("bad-line", HEADER, &lines);
+        assert_eq!(cast.events().count(), 2);
+    }
+
+    #[test]
+    fn saving_v3_as_v2_leaves_out_exit_events() {
+        let cast = open(
+            "v3-as-v2",
+            HEADER,
+            &[
+                "# comment",
+                r#"[0.5,"o","a"]"#,
+                r#"[1.25,"m","done"]"#,
+                r#"[0.25,"x","0"]"#,
+            ],
+        );
+        let path = crate::test_util::temp_path("v3-as-v2-saved");
+        cast.save_to_file_as(&path, 2).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(path);
+        assert_eq!(body(&saved), [r#"[0.5,"o","a"]"#, r#"[1.75,"m","done"]"#]);
+        let report = crate::lint::lint(saved.as_bytes(), &Default::default()).unwrap();
+        assert_eq!(report.source_version, 2);
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+    }
+
+    #[test]
+    fn x_lines_without_a_status_survive_saving() {
+        // v2 has no exit events, so this is an unknown event there
+        let mut cast = open(
+            "v2-x",
+            crate::test_util::HEADER,
+            &[r#"[1.0,"o","a"]"#, r#"[2.0,"x","done"]"#],
+        );
+        let last = cast.events().last().unwrap();
+        assert!(matches!(last.event.data, EventData::Other('x', _)));
+        // Shifting the timeline makes saving write every event again instead of copying lines
+        cast.transform_time(crate::timing::TimeTransform::Offset {
+            start: 0.0,
+            end: f64::INFINITY,
+            offset: 0.5,
+        })
+        .unwrap();
+        let path = crate::test_util::temp_path("v2-x-saved");
+        cast.save_to_file(&path).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(path);
+        assert_eq!(body(&saved), [r#"[1.5,"o","a"]"#, r#"[2.5,"x","done"]"#]);
+
+        let cast = open("v3-x", HEADER, &[r#"[0.5,"o","a"]"#, r#"[1.0,"x","done"]"#]);
+        assert_eq!(
+            body(&saved_v3(&cast)),
+            [r#"[0.5,"o","a"]"#, r#"[1.0,"x","done"]"#]
+        );
+    }
+
+    #[test]
+    fn negative_intervals_are_written_as_zero() {
+        let cast = open(
+            "negative",
+            HEADER,
+            &[r#"[1.0,"o","a"]"#, r#"[-0.5,"o","b"]"#, r#"[1.0,"o","c"]"#],
+        );
+        assert_eq!(
+            body(&saved_v3(&cast)),
+            [r#"[1.0,"o","a"]"#, r#"[0.0,"o","b"]"#, r#"[0.5,"o","c"]"#]
+        );
+    }
+
+    const V1: &str = r#"{
+  "version": 1,
+  "width": 100,
+  "height": 30,
+  "duration": 2.0,
+  "command": "/bin/bash",
+  "title": "v1 demo",
+  "env": {"TERM": "xterm-256color", "SHELL": "/bin/bash"},
+  "stdout": [[0.5, "$ "], [1.25, "ls\r\n"], [0.25, "a b\r\n"]]
+}"#;
+
+    #[test]
+    fn v1_delays_become_absolute_times() {
+        let (_, converted) = v1_to_v2(V1.as_bytes()).unwrap();
+        let converted = std::str::from_utf8(&converted).unwrap();
+        assert_eq!(
+            body(converted),
+            [
+                r#"[0.5,"o","$ "]"#,
+                r#"[1.75,"o","ls\r\n"]"#,
+                r#"[2.0,"o","a b\r\n"]"#
+            ]
+        );
+
+        // Summed delays are rounded to the microsecond so float noise never reaches the file
+        let (_, converted) =
+            v1_to_v2(include_bytes!("../examples/examplev1.cast").as_slice()).unwrap();
+        let times: Vec<f64> = body(std::str::from_utf8(&converted).unwrap())
+            .into_iter()
+            .map(|line| serde_json::from_str::<Event>(line).unwrap().time)
+            .collect();
+        assert_eq!(times, [0.248848, 1.250224]);
+    }
+
+    #[test]
+    fn v1_header_maps_to_v2() {
+        let (header, _) = v1_to_v2(V1.as_bytes()).unwrap();
+        assert_eq!(
+            (header.version, header.width, header.height, header.duration),
+            (2, 100, 30, Some(2.0))
+        );
+        assert_eq!(header.command.as_deref(), Some("/bin/bash"));
+        assert_eq!(header.title.as_deref(), Some("v1 demo"));
+        let env = header.env.unwrap();
+        assert_eq!(env["TERM"], "xterm-256color");
+        assert_eq!(env["SHELL"], "/bin/bash");
+
+        // The example leaves the title empty, which v2 leaves out
+        let (header, _) =
+            v1_to_v2(include_bytes!("../examples/examplev1.cast").as_slice()).unwrap();
+        assert_eq!(header.title, None);
+        assert_eq!(header.command.as_deref(), Some("/bin/zsh"));
+    }
+
+    #[test]
+    fn v1_edits_are_saved_as_v2() {
+        let mut cast = open("v1", V1, &[]);
+        assert_eq!((cast.source_version, cast.save_version()), (1, 2));
+        let second = cast.events().nth(1).unwrap();
+        cast.action(
+            ModificationAction::ModifyData(EventData::Output("ls -l\\r\\n".to_string())),
+            cast.get_order(&second),
+            &second,
+            None,
+        )
+        .unwrap();
+
+        let path = crate::test_util::temp_path("v1-saved");
+        cast.save_to_file(&path).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        let report = crate::lint::lint(saved.as_bytes(), &Default::default()).unwrap();
+        assert_eq!(
+            (report.source_version, report.events, report.problems.len()),
+            (2, 3, 0)
+        );
+        assert_eq!(
+            body(&saved),
+            [
+                r#"[0.5,"o","$ "]"#,
+                r#"[1.75,"o","ls -l\r\n"]"#,
+                r#"[2.0,"o","a b\r\n"]"#
+            ]
+        );
+
+        let reopened = CastFile::new(path.clone()).unwrap();
+        let _ = fs::remove_file(path);
+        assert_eq!(reopened.source_version, 2);
+        assert_eq!(
+            (reopened.header.width, reopened.header.title.as_deref()),
+            (100, Some("v1 demo"))
+        );
+        assert_eq!(reopened.events().count(), 3);
+    }
+}
@@ -0,0 +1,519 @@
+use crate::asciicast_egui::EventData;
+use crate::cast::{CastError, Snapshot};
+use crate::color::Color32;
+use crate::scan::Progress;
+use crate::terminal::Terminal;
+use ab_glyph::{point, Font, FontRef, GlyphId, PxScale, ScaleFont};
+use gif::{Encoder, Repeat};
+use image::{GenericImageView, Rgba, RgbaImage};
+use std::{collections::HashMap, io::Write};
+
+/// Quantization speed handed to the GIF encoder, 1 is the best quality and 30 the fastest. Frames with at most 256 colors are always encoded exactly so this only matters for heavily anti-aliased or true color output
+const QUANTIZATION_SPEED: i32 = 10;
+/// How long the last frame is held before the GIF loops
+const FINAL_FRAME_SECONDS: f64 = 1.0;
+
+/// Settings for rendering a recording to an animated GIF
+#[derive(Debug, Clone)]
+pub struct GifOptions {
+    /// Font size in pixels, this decides the size of every cell and so the size of the image
+    pub font_size: f32,
+    /// Frames are never emitted closer together than `1 / max_fps` seconds. Output arriving faster is merged into one frame
+    pub max_fps: f64,
+    /// Number of times the animation repeats, `None` loops forever
+    pub loop_count: Option<u16>,
+    /// Caps pauses between events. Falls back to `Header::idle_time_limit` when `None`
+    pub idle_time_limit: Option<f64>,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self {
+            font_size: 16.0,
+            max_fps: 30.0,
+            loop_count: None,
+            idle_time_limit: None,
+        }
+    }
+}
+
+/// Renders every event of `cast`, modifications included, to an animated GIF written to `writer`. Fails with `CastError::ExportError` unless `max_fps` is a positive number and with `CastError::Cancelled` once `progress` is cancelled. The events are read twice, once for the size of the canvas and once to draw them
+pub fn write_gif(
+    cast: &Snapshot,
+    writer: impl Write,
+    options: &GifOptions,
+    progress: &Progress,
+) -> Result<(), CastError> {
+    if !options.max_fps.is_finite() || options.max_fps <= 0.0 {
+        return Err(CastError::ExportError(format!(
+            "the frame rate has to be more than 0, got {}",
+            options.max_fps
+        )));
+    }
+    let (columns, rows) = largest_size(cast, progress);
+    if progress.is_cancelled() {
+        return Err(CastError::Cancelled);
+    }
+    let mut rasterizer = ScreenRasterizer::new(options.font_size, columns, rows)?;
+    let mut frames = FrameWriter::new(
+        writer,
+        rasterizer.canvas_width,
+        rasterizer.canvas_height,
+        options.loop_count,
+    )?;
+
+    let idle_time_limit = options
+        .idle_time_limit
+        .or(cast.header.idle_time_limit)
+        .unwrap_or(f64::INFINITY);
+    let frame_interval = 1.0 / options.max_fps;
+
+    let mut terminal = Terminal::for_header(&cast.header);
+    // Start time of the frame currently accumulating events
+    let mut frame_time: Option<f64> = None;
+    let mut previous_frame_time = f64::NEG_INFINITY;
+    // Event times with pauses capped by the idle time limit
+    let mut previous_time = 0.0;
+    let mut adjusted_time = 0.0;
+
+    for positioned in cast.events_tracked(progress) {
+        let event = positioned.event;
+        adjusted_time += (event.time - previous_time).clamp(0.0, idle_time_limit);
+        previous_time = event.time;
+
+        if !Terminal::changes_screen(&event) {
+            continue;
+        }
+        // Everything before this event belongs to the frame being accumulated
+        if let Some(time) = frame_time {
+            if adjusted_time > time {
+                frames.push(rasterizer.render(&terminal), time)?;
+                previous_frame_time = time;
+                frame_time = None;
+            }
+        }
+        if frame_time.is_none() {
+            frame_time = Some(adjusted_time.max(previous_frame_time + frame_interval));
+        }
+        terminal.feed_event(&event);
+    }
+
+    if progress.is_cancelled() {
+        return Err(CastError::Cancelled);
+    }
+    if let Some(time) = frame_time {
+        frames.push(rasterizer.render(&terminal), time)?;
+    }
+    frames.finish()
+}
+
+/// Largest number of columns and rows the terminal has at any point of the recording. GIF frames share one canvas so it has to fit the terminal after every resize, smaller screens leave the rest of it in the background color
+fn largest_size(cast: &Snapshot, progress: &Progress) -> (u32, u32) {
+    cast.events_tracked(progress).fold(
+        (cast.header.width as u32, cast.header.height as u32),
+        |(columns, rows), positioned| match positioned.event.data {
+            EventData::Resize(width, height) => {
+                (columns.max(width as u32), rows.max(height as u32))
+            }
+            _ => (columns, rows),
+        },
+    )
+}
+
+/// Writes frames as the difference to the previously written frame. Terminal output usually changes a handful of cells at a time so only the changed rectangle is stored with unchanged pixels left transparent, which keeps both the file size and the quantization time small
+struct FrameWriter<W: Write> {
+    encoder: Encoder<W>,
+    /// Frame waiting for its delay, which is only known once the next frame starts
+    pending: Option<(RgbaImage, f64)>,
+    /// The canvas as it looks after every written frame
+    written: Option<RgbaImage>,
+}
+
+impl<W: Write> FrameWriter<W> {
+    /// Starts a GIF of `width` by `height` pixels. GIF sizes are 16 bit so larger canvases fail instead of being truncated into a corrupt image
+    fn new(writer: W, width: u32, height: u32, loop_count: Option<u16>) -> Result<Self, CastError> {
+        let (Ok(gif_width), Ok(gif_height)) = (u16::try_from(width), u16::try_from(height)) else {
+            return Err(CastError::ExportError(format!(
+                "the image would be {}x{} pixels but GIFs are at most {}x{}, use a smaller font size",
+                width,
+                height,
+                u16::MAX,
+                u16::MAX
+            )));
+        };
+        let mut encoder = Encoder::new(writer, gif_width, gif_height, &[])?;
+        encoder.set_repeat(match loop_count {
+            Some(count) => Repeat::Finite(count),
+            None => Repeat::Infinite,
+        })?;
+        Ok(Self {
+            encoder,
+            pending: None,
+            written: None,
+        })
+    }
+
+    /// Queues a frame starting at `time`, writing the previously queued frame now that its duration is known
+    fn push(&mut self, image: RgbaImage, time: f64) -> Result<(), CastError> {
+        match self.pending.take() {
+            // Unchanged screens extend the previous frame instead of adding an empty one
+            Some((previous, start)) if previous == image => self.pending = Some((previous, start)),
+            Some((previous, start)) => {
+                self.write(previous, start, time)?;
+                self.pending = Some((image, time));
+            }
+            None => self.pending = Some((image, time)),
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), CastError> {
+        if let Some((image, start)) = self.pending.take() {
+            self.write(image, start, start + FINAL_FRAME_SECONDS)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a frame shown from `start` to `end`. GIF delays are in hundredths of a second so both ends are rounded separately, which keeps rounding errors from adding up over long recordings
+    fn write(&mut self, image: RgbaImage, start: f64, end: f64) -> Result<(), CastError> {
+        let (left, top, width, height) = match &self.written {
+            Some(written) => changed_rect(written, &image).unwrap_or((0, 0, 1, 1)),
+            None => (0, 0, image.width(), image.height()),
+        };
+        let mut region = image.view(left, top, width, height).to_image();
+        // Pixels that didn't change are made transparent so the previous frame shows through. Long transparent runs compress far better than the repeated colors would
+        if let Some(written) = &self.written {
+            for (x, y, pixel) in region.enumerate_pixels_mut() {
+                if written.get_pixel(left + x, top + y) == pixel {
+                    // A single transparent color keeps the palette from filling up with invisible copies of every color
+                    *pixel = Rgba([0, 0, 0, 0]);
+                }
+            }
+        }
+        let mut pixels = region.into_raw();
+        let mut frame = gif::Frame::from_rgba_speed(
+            width as u16,
+            height as u16,
+            &mut pixels,
+            QUANTIZATION_SPEED,
+        );
+        frame.left = left as u16;
+        frame.top = top as u16;
+        frame.delay =
+            ((end * 100.0).round() - (start * 100.0).round()).clamp(1.0, u16::MAX as f64) as u16;
+        self.encoder.write_frame(&frame)?;
+        self.written = Some(image);
+        Ok(())
+    }
+}
+
+/// Bounding box as (left, top, width, height) of the pixels that differ between two equally sized images
+fn changed_rect(before: &RgbaImage, after: &RgbaImage) -> Option<(u32, u32, u32, u32)> {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+    for (x, y, pixel) in after.enumerate_pixels() {
+        if before.get_pixel(x, y) != pixel {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+    (min_x != u32::MAX).then(|| (min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+}
+
+/// Coverage bitmap of a rasterized glyph positioned relative to the top left of its cell
+struct RasterGlyph {
+    left: i32,
+    top: i32,
+    width: u32,
+    coverage: Vec<f32>,
+}
+
+/// Draws terminal screens into RGBA images using egui's bundled Hack font, with Noto Emoji as a fallback for characters Hack doesn't cover
+struct ScreenRasterizer {
+    fonts: [FontRef<'static>; 2],
+    scale: PxScale,
+    ascent: f32,
+    cell_width: u32,
+    cell_height: u32,
+    /// Size of every rendered image in pixels
+    canvas_width: u32,
+    canvas_height: u32,
+    glyphs: HashMap<char, Option<RasterGlyph>>,
+}
+
+impl ScreenRasterizer {
+    fn new(font_size: f32, columns: u32, rows: u32) -> Result<Self, CastError> {
+        let font_error = |e: ab_glyph::InvalidFont| CastError::ExportError(e.to_string());
+        let primary =
+            FontRef::try_from_slice(epaint_default_fonts::HACK_REGULAR).map_err(font_error)?;
+        let fallback = FontRef::try_from_slice(epaint_default_fonts::NOTO_EMOJI_REGULAR)
+            .map_err(font_error)?;
+
+        let scale = PxScale::from(font_size.max(1.0));
+        let scaled = primary.as_scaled(scale);
+        let cell_width = scaled.h_advance(primary.glyph_id('M')).ceil().max(1.0) as u32;
+        let cell_height = (scaled.ascent() - scaled.descent() + scaled.line_gap())
+            .ceil()
+            .max(1.0) as u32;
+
+        let too_large = || {
+            CastError::ExportError(format!(
+                "a {}x{} terminal at font size {} is too large to draw",
+                columns, rows, font_size
+            ))
+        };
+        Ok(Self {
+            ascent: scaled.ascent(),
+            fonts: [primary, fallback],
+            scale,
+            cell_width,
+            cell_height,
+            canvas_width: cell_width
+                .checked_mul(columns.max(1))
+                .ok_or_else(too_large)?,
+            canvas_height: cell_height.checked_mul(rows.max(1)).ok_or_else(too_large)?,
+            glyphs: HashMap::new(),
+        })
+    }
+
+    fn render(&mut self, terminal: &Terminal) -> RgbaImage {
+        let (width, height) = (self.canvas_width, self.canvas_height);
+        let mut image = RgbaImage::from_pixel(width, height, to_rgba(terminal.theme().bg));
+
+        for (row, cells) in terminal.rows().iter().enumerate() {
+            let y = row as u32 * self.cell_height;
+            for (col, cell) in cells.iter().enumerate() {
+                if cell.spacer {
+                    continue;
+                }
+                let x = col as u32 * self.cell_width;
+                let span = if cells.get(col + 1).is_some_and(|next| next.spacer) {
+                    2
+                } else {
+                    1
+                };
+                let (fg, bg) = terminal.cell_colors(cell);
+
+                if bg != terminal.theme().bg {
+                    fill_rect(
+                        &mut image,
+                        x,
+                        y,
+                        self.cell_width * span,
+                        self.cell_height,
+                        bg,
+                    );
+                }
+                if cell.ch != ' ' {
+                    self.draw_glyph(&mut image, cell.ch, x, y, fg);
+                    // Hack has no bold face so bold is drawn twice with a 1 pixel offset
+                    if cell.pen.attrs.bold {
+                        self.draw_glyph(&mut image, cell.ch, x + 1, y, fg);
+                    }
+                }
+                if cell.pen.attrs.underline {
+                    fill_rect(
+                        &mut image,
+                        x,
+                        y + self.cell_height - 1,
+                        self.cell_width * span,
+                        1,
+                        fg,
+                    );
+                }
+                if cell.pen.attrs.strikethrough {
+                    fill_rect(
+                        &mut image,
+                        x,
+                        y + self.cell_height / 2,
+                        self.cell_width * span,
+                        1,
+                        fg,
+                    );
+                }
+            }
+        }
+
+        if terminal.cursor_visible() {
+            let (col, row) = terminal.cursor();
+            let (x, y) = (col as u32 * self.cell_width, row as u32 * self.cell_height);
+            // The cursor is drawn as an inverted block like most terminals
+            for py in y..(y + self.cell_height).min(height) {
+                for px in x..(x + self.cell_width).min(width) {
+                    let pixel = image.get_pixel_mut(px, py);
+                    pixel.0 = [255 - pixel[0], 255 - pixel[1], 255 - pixel[2], 255];
+                }
+            }
+        }
+        image
+    }
+
+    fn draw_glyph(&mut self, image: &mut RgbaImage, ch: char, x: u32, y: u32, color: Color32) {
+        if !self.glyphs.contains_key(&ch) {
+            let glyph = self.rasterize(ch);
+            self.glyphs.insert(ch, glyph);
+        }
+        let Some(Some(glyph)) = self.glyphs.get(&ch) else {
+            return;
+        };
+
+        for (index, &coverage) in glyph.coverage.iter().enumerate() {
+            if coverage <= 0.0 {
+                continue;
+            }
+            let px = x as i32 + glyph.left + (index as u32 % glyph.width) as i32;
+            let py = y as i32 + glyph.top + (index as u32 / glyph.width) as i32;
+            if px < 0 || py < 0 || px as u32 >= image.width() || py as u32 >= image.height() {
+                continue;
+            }
+            let pixel = image.get_pixel_mut(px as u32, py as u32);
+            blend(pixel, color, coverage.min(1.0));
+        }
+    }
+
+    fn rasterize(&self, ch: char) -> Option<RasterGlyph> {
+        // Glyph id 0 is the font's missing glyph so the fallback font is tried before settling for it
+        let (font, glyph_id) = self
+            .fonts
+            .iter()
+            .map(|font| (font, font.glyph_id(ch)))
+            .find(|(_, id)| *id != GlyphId(0))
+            .unwrap_or((&self.fonts[0], GlyphId(0)));
+
+        let glyph = glyph_id.with_scale_and_position(self.scale, point(0.0, self.ascent));
+        let outlined = font.outline_glyph(glyph)?;
+        let bounds = outlined.px_bounds();
+        let width = bounds.width() as u32;
+        let height = bounds.height() as u32;
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let mut coverage = vec![0.0; (width * height) as usize];
+        outlined.draw(|gx, gy, c| {
+            if gx < width && gy < height {
+                coverage[(gy * width + gx) as usize] = c;
+            }
+        });
+        Some(RasterGlyph {
+            left: bounds.min.x as i32,
+            top: bounds.min.y as i32,
+            width,
+            coverage,
+        })
+    }
+}
+
+fn to_rgba(color: Color32) -> Rgba<u8> {
+    Rgba([color.r(), color.g(), color.b(), 255])
+}
+
+fn fill_rect(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Color32) {
+    let color = to_rgba(color);
+    for py in y..(y + height).min(image.height()) {
+        for px in x..(x + width).min(image.width()) {
+            image.put_pixel(px, py, color);
+        }
+    }
+}
+
+fn blend(pixel: &mut Rgba<u8>, color: Color32, alpha: f32) {
+    let mix =
+        |under: u8, over: u8| (under as f32 + (over as f32 - under as f32) * alpha).round() as u8;
+    pixel.0 = [
+        mix(pixel[0], color.r()),
+        mix(pixel[1], color.g()),
+        mix(pixel[2], color.b()),
+        255,
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::open;
+
+    #[test]
+    fn encodes_a_frame_per_screen_change() {
+        let cast = open(
+            "gif",
+            "{\"version\":2,\"width\":4,\"height\":2}",
+            &[
+                "[0.0,\"o\",\"a\"]",
+                "[0.5,\"i\",\"b\"]",
+                "[1.0,\"o\",\"b\"]",
+                "[2.0,\"r\",\"8x3\"]",
+                "[3.0,\"o\",\"\\u001b[3;8Hz\"]",
+            ],
+        );
+        let options = GifOptions::default();
+        let mut gif = Vec::new();
+        write_gif(&cast.snapshot(), &mut gif, &options, &Progress::default()).unwrap();
+
+        // The canvas fits the terminal after it grew, not the size in the header
+        let rasterizer = ScreenRasterizer::new(options.font_size, 8, 3).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(gif.as_slice()).unwrap();
+        assert_eq!(
+            (decoder.width() as u32, decoder.height() as u32),
+            (rasterizer.canvas_width, rasterizer.canvas_height)
+        );
+
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push((
+                frame.left,
+                frame.top,
+                frame.width,
+                frame.height,
+                frame.delay,
+            ));
+        }
+        // Input doesn't change the screen and the resize alone draws nothing new, so "a", "ab" and the "z" in the grown corner remain
+        assert_eq!(frames.len(), 3);
+        assert_eq!(
+            frames.iter().map(|frame| frame.4).collect::<Vec<_>>(),
+            [100, 200, 100]
+        );
+        let (left, top, width, height, _) = frames[2];
+        assert_eq!(left as u32 + width as u32, rasterizer.canvas_width);
+        assert_eq!(top as u32 + height as u32, rasterizer.canvas_height);
+    }
+
+    #[test]
+    fn canvases_larger_than_a_gif_fail() {
+        let cast = open(
+            "huge-gif",
+            "{\"version\":2,\"width\":10000,\"height\":2}",
+            &["[0.0,\"o\",\"a\"]"],
+        );
+        let result = write_gif(
+            &cast.snapshot(),
+            Vec::new(),
+            &GifOptions::default(),
+            &Progress::default(),
+        );
+        assert!(matches!(result, Err(CastError::ExportError(_))));
+    }
+
+    #[test]
+    fn frame_rates_have_to_be_positive() {
+        let cast = open(
+            "gif-fps",
+            "{\"version\":2,\"width\":4,\"height\":2}",
+            &["[0.0,\"o\",\"a\"]"],
+        );
+        for max_fps in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let options = GifOptions {
+                max_fps,
+                ..GifOptions::default()
+            };
+            let result = write_gif(&cast.snapshot(), Vec::new(), &options, &Progress::default());
+            assert!(
+                matches!(result, Err(CastError::ExportError(_))),
+                "{}",
+                max_fps
+            );
+        }
+    }
+}
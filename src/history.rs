@@ -0,0 +1,78 @@
+use crate::asciicast_egui::Header;
+use crate::cast::ModificationChain;
+use crate::timing::Timeline;
+
+/// A single reversible change to the edit state of a `CastFile`. Every change holds the state both before and after it was applied so that the history can be walked in either direction without recomputing anything
+#[derive(Clone)]
+pub enum Change {
+    /// The modification chain at `byte_location` was replaced. `None` means no chain existed at that location
+    Chain {
+        byte_location: usize,
+        before: Option<ModificationChain>,
+        after: Option<ModificationChain>,
+    },
+    /// The time transforms applied to the memory mapped events were replaced
+    Timeline { before: Timeline, after: Timeline },
+    /// The header was edited
+    Header {
+        before: Box<Header>,
+        after: Box<Header>,
+    },
+}
+
+/// One user level step in the history. Compound actions such as `AdvancedModificationAction::Swap` produce several changes which are grouped here so that they are undone and redone together
+pub struct HistoryEntry {
+    /// Human readable summary shown in the history panel
+    pub description: String,
+    pub(crate) changes: Vec<Change>,
+}
+
+/// Linear undo/redo history. Entries before `cursor` are applied to the file and entries at or after it are available for redo. Pushing a new entry discards anything that could have been redone, matching the behaviour of most editors
+#[derive(Default)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    cursor: usize,
+}
+
+impl History {
+    /// Record a completed entry, dropping any entries that were undone
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.entries.truncate(self.cursor);
+        self.entries.push(entry);
+        self.cursor = self.entries.len();
+    }
+
+    /// Moves the cursor back one entry and returns the entry that should be reverted
+    pub fn step_back(&mut self) -> Option<&HistoryEntry> {
+        if self.cursor == 0 {
+            return None;
+        }
+        self.cursor -= 1;
+        self.entries.get(self.cursor)
+    }
+
+    /// Moves the cursor forward one entry and returns the entry that should be reapplied
+    pub fn step_forward(&mut self) -> Option<&HistoryEntry> {
+        let entry = self.entries.get(self.cursor)?;
+        self.cursor += 1;
+        Some(entry)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.cursor > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.cursor < self.entries.len()
+    }
+
+    /// All recorded entries, both applied and undone, oldest first
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// Number of entries currently applied
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+}
@@ -0,0 +1,369 @@
+use crate::cast::{find_next_newline, parse_line};
+use crate::scan::{self, Progress};
+use crate::timing::Timeline;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Number of event lines between two checkpoints of a `LineIndex`. Finding an exact position reads at most this many lines past a checkpoint
+pub const INDEX_STRIDE: usize = 1024;
+
+/// Files smaller than this are indexed in well under a second so their index isn't worth a file on disk
+const CACHE_MIN_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Folder of the user's cache directory line indexes are saved to
+const CACHE_DIR: &str = "asciinema-editor";
+
+/// Version of the cached index format and of how lines are numbered. Bump it whenever either changes so indexes saved by older builds are built again instead of trusted
+const FORMAT_VERSION: u32 = 1;
+
+/// Sparse index over the event lines of the memory map. Every `INDEX_STRIDE`th line is recorded with its byte location and time as written in the file, which turns finding an event by number or time into a binary search followed by a short scan instead of reading the file from the start
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LineIndex {
+    /// Byte location and untransformed time of line `i * INDEX_STRIDE` at position `i`
+    checkpoints: Vec<(usize, f64)>,
+    /// Number of event lines in the memory map
+    line_count: usize,
+}
+
+/// An index saved in the cache directory together with what is needed to tell whether it belongs to a file and whether the file changed since
+#[derive(Serialize, Deserialize)]
+struct CachedIndex {
+    format_version: u32,
+    /// Canonical path of the file the index was built from, as cache file names are only a hash of it
+    source: PathBuf,
+    file_size: u64,
+    /// Modification time of the file as seconds and nanoseconds since the epoch
+    modified: (u64, u32),
+    /// Version of the recording as written. Version 1 and 3 recordings are indexed over their conversion to version 2, not over the file itself
+    source_version: u8,
+    /// Length of the memory map the index was built over
+    mmap_len: usize,
+    index: LineIndex,
+}
+
+impl LineIndex {
+    /// Reads every line of `bytes` after the header at `data_start`, counting the lines that read as events. Blocks of lines are read on several threads and numbered in order as they come back. Returns `None` if cancelled through `progress`
+    pub fn build(bytes: &[u8], data_start: usize, progress: &Progress) -> Option<Self> {
+        let mut index = Self::default();
+        scan::scan_blocks(
+            bytes,
+            data_start,
+            bytes.len(),
+            progress,
+            |block| {
+                event_lines(bytes, block.start, block.end)
+                    .map(|(line_start, _, time)| (line_start, time))
+                    .collect::<Vec<_>>()
+            },
+            |lines| {
+                for checkpoint in lines {
+                    if index.line_count % INDEX_STRIDE == 0 {
+                        index.checkpoints.push(checkpoint);
+                    }
+                    index.line_count += 1;
+                }
+                true
+            },
+        )?;
+        Some(index)
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_count
+    }
+
+    pub fn checkpoints(&self) -> &[(usize, f64)] {
+        &self.checkpoints
+    }
+
+    /// Position in `checkpoints` of the last checkpoint at or before `byte_location`, or 0 if there is none
+    pub fn checkpoint_before(&self, byte_location: usize) -> usize {
+        self.checkpoints
+            .partition_point(|&(line_start, _)| line_start <= byte_location)
+            .saturating_sub(1)
+    }
+
+    /// Counts the lines `timeline` removes before every checkpoint. The list has one more entry than there are checkpoints holding the total so it can be looked up with the same positions. Returns `None` if cancelled through `progress`
+    pub fn count_removed(
+        &self,
+        bytes: &[u8],
+        timeline: &Timeline,
+        progress: &Progress,
+    ) -> Option<Vec<usize>> {
+        let data_start = self
+            .checkpoints
+            .first()
+            .map_or(bytes.len(), |&(start, _)| start);
+        let mut counts = Vec::with_capacity(self.checkpoints.len() + 1);
+        let mut removed = 0;
+        let mut line = 0;
+        scan::scan_blocks(
+            bytes,
+            data_start,
+            bytes.len(),
+            progress,
+            |block| {
+                event_lines(bytes, block.start, block.end)
+                    .map(|(_, _, time)| timeline.apply(time).is_none())
+                    .collect::<Vec<bool>>()
+            },
+            |lines| {
+                for line_removed in lines {
+                    if line % INDEX_STRIDE == 0 {
+                        counts.push(removed);
+                    }
+                    removed += line_removed as usize;
+                    line += 1;
+                }
+                true
+            },
+        )?;
+        counts.push(removed);
+        Some(counts)
+    }
+
+    /// Loads the index saved for `source` if there is one, it was saved by this format over a memory map of `mmap_len` bytes read from a version `source_version` recording and the file hasn't changed since. An index whose checkpoints don't fall in order inside the memory map is never returned
+    pub fn load_cached(source: &Path, source_version: u8, mmap_len: usize) -> Option<Self> {
+        let (file_size, modified) = file_stamp(source)?;
+        let (source, path) = cache_path(source)?;
+        let file = File::open(path).ok()?;
+        let cached: CachedIndex = serde_json::from_reader(BufReader::new(file)).ok()?;
+        (cached.format_version == FORMAT_VERSION
+            && cached.source == source
+            && cached.file_size == file_size
+            && cached.modified == modified
+            && cached.source_version == source_version
+            && cached.mmap_len == mmap_len
+            && cached.index.fits(mmap_len))
+        .then_some(cached.index)
+    }
+
+    /// Whether the checkpoints are in order, inside a memory map of `mmap_len` bytes and as many as `line_count` calls for
+    fn fits(&self, mmap_len: usize) -> bool {
+        self.checkpoints.len() == self.line_count.div_ceil(INDEX_STRIDE)
+            && self
+                .checkpoints
+                .windows(2)
+                .all(|pair| pair[0].0 < pair[1].0)
+            && self
+                .checkpoints
+                .last()
+                .is_none_or(|&(line_start, _)| line_start < mmap_len)
+    }
+
+    /// Saves the index to the user's cache directory if `source` is large enough to benefit, along with what `load_cached` checks it against. Failing to write it only means the next open builds the index again so errors are ignored
+    pub fn save_cached(&self, source: &Path, source_version: u8, mmap_len: usize) {
+        let Some((file_size, modified)) = file_stamp(source) else {
+            return;
+        };
+        if file_size < CACHE_MIN_SIZE {
+            return;
+        }
+        let Some((source, path)) = cache_path(source) else {
+            return;
+        };
+        if path
+            .parent()
+            .is_some_and(|dir| fs::create_dir_all(dir).is_err())
+        {
+            return;
+        }
+        let Ok(file) = File::create(&path) else {
+            return;
+        };
+        let cached = CachedIndex {
+            format_version: FORMAT_VERSION,
+            source,
+            file_size,
+            modified,
+            source_version,
+            mmap_len,
+            index: self.clone(),
+        };
+        if serde_json::to_writer(BufWriter::new(file), &cached).is_err() {
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
+
+/// Canonical path of `source` and where its index is cached. Recordings are often in folders that are shared or checked in, so indexes go to the user's cache directory under a hash of the canonical path, which keeps one index per recording that is replaced when the recording changes. `None` if there is no cache directory or `source` can't be resolved
+fn cache_path(source: &Path) -> Option<(PathBuf, PathBuf)> {
+    let source = fs::canonicalize(source).ok()?;
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    let path = dirs::cache_dir()?
+        .join(CACHE_DIR)
+        .join(format!("{:016x}.index", hasher.finish()));
+    Some((source, path))
+}
+
+fn file_stamp(path: &Path) -> Option<(u64, (u64, u32))> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((
+        metadata.len(),
+        (modified.as_secs(), modified.subsec_nanos()),
+    ))
+}
+
+/// Streams the start, end and time of every line in `bytes[start..end]` that reads as an event. Damaged lines are skipped by the same rule reading events skips them with, so numbering these lines and numbering events agree. Every line is parsed in full, `line_times` is cheaper where a line is checked again anyway
+pub fn event_lines(
+    bytes: &[u8],
+    start: usize,
+    end: usize,
+) -> impl Iterator<Item = (usize, usize, f64)> + '_ {
+    let mut position = start;
+    std::iter::from_fn(move || {
+        while position < end {
+            let line_start = position;
+            position = find_next_newline(bytes, line_start);
+            if let Some(positioned) = parse_line(&bytes[line_start..position], line_start) {
+                return Some((line_start, position, positioned.event.time));
+            }
+        }
+        None
+    })
+}
+
+/// Streams the start, end and time of every line in `bytes[start..end]` with a readable time, without parsing the rest of the line. Empty lines are skipped but damaged events aren't, callers that count events use `event_lines`
+pub fn line_times(
+    bytes: &[u8],
+    start: usize,
+    end: usize,
+) -> impl Iterator<Item = (usize, usize, f64)> + '_ {
+    let mut position = start;
+    std::iter::from_fn(move || {
+        while position < end {
+            let line_start = position;
+            position = find_next_newline(bytes, line_start);
+            if let Some(time) = line_time(&bytes[line_start..position]) {
+                return Some((line_start, position, time));
+            }
+        }
+        None
+    })
+}
+
+/// Time of an event line, read from between the opening bracket and the first comma without parsing the rest of the line
+pub fn line_time(line: &[u8]) -> Option<f64> {
+    let rest = line.trim_ascii().strip_prefix(b"[")?;
+    let comma = rest.iter().position(|&b| b == b',')?;
+    std::str::from_utf8(&rest[..comma])
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Event code of an event line, read from the string after the first comma without parsing the rest of the line
+pub fn line_code(line: &[u8]) -> Option<&[u8]> {
+    let comma = line.iter().position(|&b| b == b',')?;
+    let rest = line[comma + 1..].trim_ascii_start().strip_prefix(b"\"")?;
+    let quote = rest.iter().position(|&b| b == b'"')?;
+    Some(&rest[..quote])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn damaged_lines_are_not_counted() {
+        let header = "{\"version\":2,\"width\":80,\"height\":24}\n";
+        let mut file = header.to_string();
+        let mut starts = Vec::new();
+        for line in 0..INDEX_STRIDE * 2 + 10 {
+            // A readable time in front of a line that isn't an event doesn't make it one
+            if line % 100 == 50 {
+                file.push_str("[1.0,\"o\" damaged\n\n");
+            }
+            starts.push(file.len());
+            file.push_str(&format!("[{}.0,\"o\",\"line {}\"]\n", line, line));
+        }
+
+        let index = LineIndex::build(file.as_bytes(), header.len(), &Progress::default()).unwrap();
+        assert_eq!(index.line_count(), starts.len());
+        assert_eq!(
+            index.checkpoints(),
+            [
+                (starts[0], 0.0),
+                (starts[INDEX_STRIDE], INDEX_STRIDE as f64),
+                (starts[INDEX_STRIDE * 2], (INDEX_STRIDE * 2) as f64),
+            ]
+        );
+        let timeline = Timeline::default();
+        let removed = index
+            .count_removed(file.as_bytes(), &timeline, &Progress::default())
+            .unwrap();
+        assert_eq!(removed, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn reads_the_time_without_the_event() {
+        assert_eq!(line_time(b" [ 1.5 , \"o\", \"a\"]"), Some(1.5));
+        assert_eq!(line_time(b"[1.5"), None);
+        assert_eq!(line_time(b"# comment"), None);
+        assert_eq!(line_code(b"[1.5, \"m\", \"a\"]"), Some(&b"m"[..]));
+    }
+
+    #[test]
+    fn caches_are_kept_away_from_the_recording() {
+        let dir = std::env::temp_dir();
+        let source = crate::test_util::temp_path("cache");
+        fs::write(&source, "{}").unwrap();
+        let (canonical, path) = cache_path(&source).unwrap();
+        let (_, same) = cache_path(&dir.join(".").join(source.file_name().unwrap())).unwrap();
+        let _ = fs::remove_file(&source);
+        assert_eq!(path, same);
+        assert_ne!(path.parent(), canonical.parent());
+    }
+
+    #[test]
+    fn caches_that_dont_match_the_memory_map_are_built_again() {
+        let source = crate::test_util::temp_path("stale-cache");
+        fs::write(&source, "{}").unwrap();
+        let (file_size, modified) = file_stamp(&source).unwrap();
+        let (canonical, path) = cache_path(&source).unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let index = LineIndex {
+            checkpoints: vec![(40, 0.0), (900, 5.0)],
+            line_count: INDEX_STRIDE + 1,
+        };
+        let load = |format_version, index: &LineIndex, source_version, mmap_len| {
+            let cached = CachedIndex {
+                format_version,
+                source: canonical.clone(),
+                file_size,
+                modified,
+                source_version: 2,
+                mmap_len: 1000,
+                index: index.clone(),
+            };
+            fs::write(&path, serde_json::to_vec(&cached).unwrap()).unwrap();
+            LineIndex::load_cached(&source, source_version, mmap_len).map(|index| index.checkpoints)
+        };
+
+        let loaded = [
+            load(FORMAT_VERSION, &index, 2, 1000),
+            load(FORMAT_VERSION - 1, &index, 2, 1000),
+            load(FORMAT_VERSION, &index, 3, 1000),
+            load(FORMAT_VERSION, &index, 2, 999),
+        ];
+        let outside = LineIndex {
+            checkpoints: vec![(40, 0.0), (1000, 5.0)],
+            ..index.clone()
+        };
+        let outside = load(FORMAT_VERSION, &outside, 2, 1000);
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&source);
+
+        assert_eq!(loaded, [Some(index.checkpoints.clone()), None, None, None]);
+        assert_eq!(outside, None);
+    }
+}
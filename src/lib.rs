@@ -0,0 +1,23 @@
+pub mod asciicast_egui;
+pub mod cast;
+pub mod cleanup;
+pub mod color;
+pub mod concat;
+pub mod convert;
+pub mod export;
+pub mod history;
+pub mod index;
+pub mod lint;
+pub mod redact;
+pub mod scan;
+pub mod search;
+pub mod split;
+pub mod terminal;
+#[cfg(test)]
+mod test_util;
+pub mod themes;
+pub mod timing;
+
+pub use asciicast_egui::{Event, EventData, Header, Theme};
+pub use cast::{CastError, CastFile, EventPositioned, ModificationAction};
+pub use color::Color32;
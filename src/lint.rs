@@ -0,0 +1,798 @@
+use crate::asciicast_egui::{EventData, Header, Theme};
+use crate::cast::{find_next_newline, CastError};
+use crate::convert::{self, V1Header};
+use crate::scan::{self, Progress};
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+use serde_json::{value::RawValue, Value};
+use std::{fmt, fs::File, path::Path};
+
+/// Seconds the header duration may be off from the time of the last event before it is reported, as recorders round what they write
+const DURATION_TOLERANCE: f64 = 0.01;
+
+/// Problems past this many are only counted so a file that is broken throughout doesn't fill memory with them
+pub const MAX_PROBLEMS: usize = 10_000;
+
+/// Event codes of the asciicast v2 format. Players skip events with other codes, v3 adds `x` for the exit status of the recorded process
+const KNOWN_CODES: [&str; 4] = ["o", "i", "r", "m"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The line is skipped or misplayed by players, or the file can't be opened at all
+    Error,
+    /// The file plays but not quite as its header or the format says it should
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    InvalidHeader,
+    InvalidTheme,
+    InvalidUtf8,
+    InvalidJson,
+    /// Valid JSON that isn't a `[time, code, data]` event or has data its code doesn't allow
+    InvalidEvent,
+    NegativeTime,
+    /// An event earlier than the event before it
+    TimeGoesBack,
+    UnknownEventCode,
+    InvalidResize,
+    DurationMismatch,
+    /// Text after the event on a line, or a last line cut off part way as happens when a recording is interrupted
+    TrailingGarbage,
+}
+
+/// Something wrong with one line of a recording
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    pub severity: Severity,
+    /// Line of the file counting from 1, the header being line 1
+    pub line: usize,
+    /// Byte offset of the start of the line
+    pub byte: usize,
+    pub message: String,
+}
+
+/// Every problem found in a recording in file order
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Version the file was written in
+    pub source_version: u8,
+    /// Number of lines that are valid events
+    pub events: usize,
+    pub problems: Vec<Problem>,
+    /// Problems past `MAX_PROBLEMS` that were counted but left out of `problems`
+    pub omitted: usize,
+}
+
+impl Report {
+    pub fn errors(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.problems
+            .iter()
+            .filter(|problem| problem.severity == severity)
+            .count()
+    }
+}
+
+/// Reads the file at `path` and checks it. Unlike opening it for editing this doesn't give up on a broken header, which is reported like any other problem. Only failing to read the file is an error. Returns `Ok(None)` if cancelled through `progress`
+pub fn lint_file(path: &Path, progress: &Progress) -> Result<Option<Report>, CastError> {
+    let file = File::open(path)?;
+    let mmap = unsafe { Mmap::map(&file).map_err(|e| CastError::MmapError(e.to_string()))? };
+    Ok(lint(&mmap, progress))
+}
+
+/// Checks the recording in `bytes` in the version it was written in, so lines and bytes of problems are those of the file. Returns `None` if cancelled through `progress`
+pub fn lint(bytes: &[u8], progress: &Progress) -> Option<Report> {
+    let source_version = convert::detect_version(bytes).unwrap_or_else(|_| guess_version(bytes));
+    let report = match source_version {
+        1 => lint_v1(bytes),
+        version => lint_lines(bytes, version, progress)?,
+    };
+    (!progress.is_cancelled()).then_some(report)
+}
+
+/// Bytes at the start of a file searched for its version when its header can't be read
+const VERSION_SEARCH_LENGTH: usize = 4096;
+
+/// Version of a file whose header can't be read, taken from the first `"version"` field near its start so a broken v1 document isn't read line by line. Files without one are checked as v2 so the header problem is reported
+fn guess_version(bytes: &[u8]) -> u8 {
+    let start = &bytes[..bytes.len().min(VERSION_SEARCH_LENGTH)];
+    let field = b"\"version\"";
+    start
+        .windows(field.len())
+        .position(|window| window == field)
+        .and_then(|position| {
+            let rest = start[position + field.len()..].trim_ascii_start();
+            let rest = rest.strip_prefix(b":")?.trim_ascii_start();
+            match rest.first()? {
+                digit @ b'1'..=b'9' => Some(digit - b'0'),
+                _ => None,
+            }
+        })
+        .unwrap_or(2)
+}
+
+/// Frames of a v1 document as they are written, so where each one starts can be found
+#[derive(Deserialize)]
+struct V1Frames<'a> {
+    #[serde(borrow)]
+    stdout: Vec<&'a RawValue>,
+}
+
+/// Checks a v1 document. It is a single JSON document usually printed over many lines, so it is read whole and each frame is found in the file by where its JSON starts
+fn lint_v1(bytes: &[u8]) -> Report {
+    let mut report = Report {
+        source_version: 1,
+        events: 0,
+        problems: Vec::new(),
+        omitted: 0,
+    };
+    let mut problem = |kind, line, byte, message| {
+        report.problems.push(Problem {
+            kind,
+            severity: Severity::Error,
+            line,
+            byte,
+            message,
+        })
+    };
+
+    let mut json: Value = match serde_json::from_slice(bytes) {
+        Ok(json) => json,
+        Err(e) => {
+            let line = e.line().max(1);
+            let byte = line_start(bytes, line).unwrap_or(bytes.len());
+            problem(
+                ProblemKind::InvalidJson,
+                line,
+                byte,
+                format!("not valid JSON: {}", e),
+            );
+            return report;
+        }
+    };
+    let Some(stdout) = json
+        .as_object_mut()
+        .and_then(|fields| fields.remove("stdout"))
+    else {
+        problem(
+            ProblemKind::InvalidHeader,
+            1,
+            0,
+            "expected an object with the frames in \"stdout\"".to_string(),
+        );
+        return report;
+    };
+    let duration = match serde_json::from_value::<V1Header>(json) {
+        Ok(header) => {
+            if header.width == 0 || header.height == 0 {
+                problem(
+                    ProblemKind::InvalidHeader,
+                    1,
+                    0,
+                    format!("terminal size {}x{} is empty", header.width, header.height),
+                );
+            }
+            if header.duration.is_some_and(|duration| duration < 0.0) {
+                problem(
+                    ProblemKind::InvalidHeader,
+                    1,
+                    0,
+                    "duration is negative".to_string(),
+                );
+            }
+            header.duration
+        }
+        Err(e) => {
+            problem(ProblemKind::InvalidHeader, 1, 0, e.to_string());
+            None
+        }
+    };
+    let frames = match (stdout.is_array(), serde_json::from_slice::<V1Frames>(bytes)) {
+        (true, Ok(frames)) => frames.stdout,
+        _ => {
+            problem(
+                ProblemKind::InvalidHeader,
+                1,
+                0,
+                "\"stdout\" isn't a list of frames".to_string(),
+            );
+            return report;
+        }
+    };
+
+    let mut time = 0.0;
+    // Lines are counted on from one frame to the next as they are in file order
+    let (mut line, mut line_begin, mut scanned) = (1, 0, 0);
+    for frame in frames {
+        let start = frame.get().as_ptr() as usize - bytes.as_ptr() as usize;
+        for (position, _) in bytes[scanned..start]
+            .iter()
+            .enumerate()
+            .filter(|&(_, &b)| b == b'\n')
+        {
+            line += 1;
+            line_begin = scanned + position + 1;
+        }
+        scanned = start;
+        match serde_json::from_str::<Value>(frame.get())
+            .ok()
+            .as_ref()
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+        {
+            Some([Value::Number(delay), Value::String(_)]) => {
+                let delay = delay.as_f64().unwrap_or(f64::NAN);
+                if delay < 0.0 {
+                    problem(
+                        ProblemKind::NegativeTime,
+                        line,
+                        line_begin,
+                        format!("delay {} is negative", delay),
+                    );
+                }
+                time += delay;
+                report.events += 1;
+            }
+            _ => problem(
+                ProblemKind::InvalidEvent,
+                line,
+                line_begin,
+                "expected a frame as [delay, data]".to_string(),
+            ),
+        }
+    }
+
+    if let Some(duration) = duration.filter(|duration| (duration - time).abs() > DURATION_TOLERANCE)
+    {
+        report.problems.insert(
+            0,
+            Problem {
+                kind: ProblemKind::DurationMismatch,
+                severity: Severity::Warning,
+                line: 1,
+                byte: 0,
+                message: format!(
+                    "duration is {}s but the frames add up to {}s",
+                    duration, time
+                ),
+            },
+        );
+    }
+    report
+}
+
+/// Byte offset of the start of line `line` counting from 1, or `None` if there aren't that many lines
+pub(crate) fn line_start(bytes: &[u8], line: usize) -> Option<usize> {
+    match line {
+        0 | 1 => Some(0),
+        line => bytes
+            .iter()
+            .enumerate()
+            .filter(|&(_, &b)| b == b'\n')
+            .nth(line - 2)
+            .map(|(newline, _)| newline + 1),
+    }
+}
+
+/// Checks the header and every event line of a v2 or v3 file. Blocks of lines are checked on several threads and their problems numbered in order as they come back. Returns `None` if cancelled through `progress`
+fn lint_lines(bytes: &[u8], source_version: u8, progress: &Progress) -> Option<Report> {
+    let intervals = source_version == 3;
+    let data_start = find_next_newline(bytes, 0);
+    let (header, mut problems) = lint_header(&bytes[..data_start], source_version);
+    let mut omitted = 0;
+    let mut events = 0;
+    // Lines before the current block, counting the header
+    let mut lines = 1;
+    let mut last_time: Option<f64> = None;
+
+    scan::scan_blocks(
+        bytes,
+        data_start,
+        bytes.len(),
+        progress,
+        |block| {
+            lint_block(
+                &bytes[..block.end],
+                block.start,
+                block.end == bytes.len(),
+                intervals,
+            )
+        },
+        |block| {
+            if let (Some(previous), Some(&(line, byte, time))) = (last_time, block.first.as_ref()) {
+                if time < previous {
+                    problems.push(Problem {
+                        kind: ProblemKind::TimeGoesBack,
+                        severity: Severity::Error,
+                        line: lines + line,
+                        byte,
+                        message: went_back(time, previous),
+                    });
+                }
+            }
+            problems.extend(block.problems.into_iter().map(|mut problem| {
+                problem.line += lines;
+                problem
+            }));
+            if problems.len() > MAX_PROBLEMS {
+                omitted += problems.len() - MAX_PROBLEMS;
+                problems.truncate(MAX_PROBLEMS);
+            }
+            events += block.events;
+            lines += block.lines;
+            last_time = block.last_time.or(last_time);
+            true
+        },
+    )?;
+    // Problems at the start of a block are found after those inside it
+    problems.sort_by_key(|problem| problem.byte);
+
+    if let (Some(duration), Some(last_time)) =
+        (header.and_then(|header| header.duration), last_time)
+    {
+        if (duration - last_time).abs() > DURATION_TOLERANCE {
+            problems.push(Problem {
+                kind: ProblemKind::DurationMismatch,
+                severity: Severity::Warning,
+                line: 1,
+                byte: 0,
+                message: format!(
+                    "header duration is {}s but the last event is at {}s",
+                    duration, last_time
+                ),
+            });
+            problems.sort_by_key(|problem| problem.byte);
+        }
+    }
+
+    Some(Report {
+        source_version,
+        events,
+        problems,
+        omitted,
+    })
+}
+
+/// Parses the header line of a file of `version`, returning it as a v2 header if it could be read together with what is wrong with it. The theme is checked on its own so a bad color is reported as such instead of failing the whole header
+fn lint_header(line: &[u8], version: u8) -> (Option<Header>, Vec<Problem>) {
+    let mut problems = Vec::new();
+    let mut problem = |kind, message: String| {
+        problems.push(Problem {
+            kind,
+            severity: Severity::Error,
+            line: 1,
+            byte: 0,
+            message,
+        })
+    };
+    let mut json: Value = match serde_json::from_slice(line) {
+        Ok(json) => json,
+        Err(e) => {
+            problem(
+                ProblemKind::InvalidHeader,
+                format!("header isn't valid JSON: {}", e),
+            );
+            return (None, problems);
+        }
+    };
+    let Some(fields) = json.as_object_mut() else {
+        problem(
+            ProblemKind::InvalidHeader,
+            "header isn't a JSON object".to_string(),
+        );
+        return (None, problems);
+    };
+    // v3 keeps the theme with the rest of the terminal description
+    let theme = match version {
+        3 => fields
+            .get_mut("term")
+            .and_then(Value::as_object_mut)
+            .and_then(|term| term.remove("theme")),
+        _ => fields.remove("theme"),
+    };
+    if let Some(theme) = theme.filter(|theme| !theme.is_null()) {
+        if let Err(e) = serde_json::from_value::<Theme>(theme) {
+            problem(ProblemKind::InvalidTheme, e.to_string());
+        }
+    }
+    let header = match version {
+        3 => convert::v3_header(json),
+        _ => serde_json::from_value::<Header>(json),
+    };
+    let header = match header {
+        Ok(header) => header,
+        Err(e) => {
+            problem(ProblemKind::InvalidHeader, e.to_string());
+            return (None, problems);
+        }
+    };
+    if !matches!(version, 2 | 3) {
+        problem(
+            ProblemKind::InvalidHeader,
+            format!(
+                "unsupported version {}, expected 1, 2 or 3, lines are checked as v2",
+                version
+            ),
+        );
+    }
+    if header.width == 0 || header.height == 0 {
+        problem(
+            ProblemKind::InvalidHeader,
+            format!("terminal size {}x{} is empty", header.width, header.height),
+        );
+    }
+    if header.duration.is_some_and(|duration| duration < 0.0) {
+        problem(
+            ProblemKind::InvalidHeader,
+            "duration is negative".to_string(),
+        );
+    }
+    (Some(header), problems)
+}
+
+/// What checking the lines of one block found. Line numbers count from 1 at the start of the block
+struct LintedBlock {
+    lines: usize,
+    events: usize,
+    /// Line, byte offset and time of the first event with a time that isn't negative
+    first: Option<(usize, usize, f64)>,
+    last_time: Option<f64>,
+    problems: Vec<Problem>,
+}
+
+/// Checks the lines from `start` to the end of `bytes`, which is the end of the file if `at_end`. With `intervals` the lines are those of a v3 file, whose times are intervals from the event before and which may have comment lines
+fn lint_block(bytes: &[u8], start: usize, at_end: bool, intervals: bool) -> LintedBlock {
+    let mut block = LintedBlock {
+        lines: 0,
+        events: 0,
+        first: None,
+        last_time: None,
+        problems: Vec::new(),
+    };
+    let mut position = start;
+    while position < bytes.len() {
+        let line_start = position;
+        position = find_next_newline(bytes, line_start);
+        block.lines += 1;
+        let mut problem = |kind, severity, message| {
+            block.problems.push(Problem {
+                kind,
+                severity,
+                line: block.lines,
+                byte: line_start,
+                message,
+            })
+        };
+
+        let line = match std::str::from_utf8(&bytes[line_start..position]) {
+            Ok(line) => line.trim(),
+            Err(e) => {
+                problem(
+                    ProblemKind::InvalidUtf8,
+                    Severity::Error,
+                    format!("invalid UTF-8 after {} bytes of the line", e.valid_up_to()),
+                );
+                continue;
+            }
+        };
+        if line.is_empty() || (intervals && line.starts_with('#')) {
+            continue;
+        }
+        let mut values = serde_json::Deserializer::from_str(line).into_iter::<Value>();
+        let value = match values.next() {
+            Some(Ok(value)) => value,
+            // A last line without a newline is where an interrupted recorder stopped writing
+            Some(Err(e)) if at_end && position == bytes.len() && !bytes.ends_with(b"\n") => {
+                problem(
+                    ProblemKind::TrailingGarbage,
+                    Severity::Error,
+                    format!(
+                        "the last line is cut off or isn't an event, the recording may have been interrupted: {}",
+                        e
+                    ),
+                );
+                continue;
+            }
+            Some(Err(e)) => {
+                problem(
+                    ProblemKind::InvalidJson,
+                    Severity::Error,
+                    format!("not valid JSON: {}", e),
+                );
+                continue;
+            }
+            None => continue,
+        };
+        let rest = &line[values.byte_offset()..];
+        if !rest.trim().is_empty() {
+            problem(
+                ProblemKind::TrailingGarbage,
+                Severity::Error,
+                format!(
+                    "unexpected {:?} after the event",
+                    rest.trim().chars().take(20).collect::<String>()
+                ),
+            );
+        }
+
+        let (time, code, data) = match value.as_array().map(Vec::as_slice) {
+            Some([Value::Number(time), Value::String(code), Value::String(data)]) => {
+                (time.as_f64().unwrap_or(f64::NAN), code, data)
+            }
+            _ => {
+                problem(
+                    ProblemKind::InvalidEvent,
+                    Severity::Error,
+                    "expected an event as [time, code, data]".to_string(),
+                );
+                continue;
+            }
+        };
+        // Negative times aren't compared with the events around them, one problem per line is enough. Intervals can't go back in time other than by being negative
+        if time < 0.0 {
+            let what = if intervals { "interval" } else { "time" };
+            problem(
+                ProblemKind::NegativeTime,
+                Severity::Error,
+                format!("{} {} is negative", what, time),
+            );
+        } else if !intervals {
+            if let Some(previous) = block.last_time.filter(|&previous| time < previous) {
+                problem(
+                    ProblemKind::TimeGoesBack,
+                    Severity::Error,
+                    went_back(time, previous),
+                );
+            }
+            block.first.get_or_insert((block.lines, line_start, time));
+            block.last_time = Some(time);
+        }
+
+        match code.as_str() {
+            "r" => match EventData::from_code('r', data.clone()) {
+                Ok(EventData::Resize(width, height)) if width > 0 && height > 0 => {}
+                _ => problem(
+                    ProblemKind::InvalidResize,
+                    Severity::Error,
+                    format!("resize to {:?} isn't a size like 80x24", data),
+                ),
+            },
+            "x" if !intervals => problem(
+                ProblemKind::UnknownEventCode,
+                Severity::Warning,
+                "exit events only exist in v3, v2 players skip them".to_string(),
+            ),
+            "x" if data.parse::<i32>().is_err() => problem(
+                ProblemKind::InvalidEvent,
+                Severity::Error,
+                format!("exit status {:?} isn't a whole number", data),
+            ),
+            "x" => {}
+            code if !KNOWN_CODES.contains(&code) => problem(
+                ProblemKind::UnknownEventCode,
+                Severity::Warning,
+                format!("unknown event code {:?} is skipped by players", code),
+            ),
+            _ => {}
+        }
+
+        block.events += 1;
+    }
+    block
+}
+
+fn went_back(time: f64, previous: f64) -> String {
+    format!("time {} is before {} of an earlier event", time, previous)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V2: &str = r#"{"version":2,"width":80,"height":24}"#;
+    const V3: &str = r#"{"version":3,"term":{"cols":80,"rows":24}}"#;
+
+    fn lint_str(file: &str) -> Report {
+        lint(file.as_bytes(), &Progress::default()).unwrap()
+    }
+
+    /// Kind, severity, line and byte of every problem
+    fn problems(file: &str) -> Vec<(ProblemKind, Severity, usize, usize)> {
+        lint_str(file)
+            .problems
+            .iter()
+            .map(|problem| (problem.kind, problem.severity, problem.line, problem.byte))
+            .collect()
+    }
+
+    /// A file of `header` and `lines` with the byte offset of the first line
+    fn recording(header: &str, lines: &[&str]) -> (String, usize) {
+        let mut file = format!("{}\n", header);
+        let first = file.len();
+        for line in lines {
+            file.push_str(line);
+            file.push('\n');
+        }
+        (file, first)
+    }
+
+    #[test]
+    fn finds_every_kind_of_problem() {
+        use ProblemKind::*;
+        use Severity::*;
+        let event = r#"[1.0,"o","a"]"#;
+        let cases: [(&str, &[&str], ProblemKind, Severity); 13] = [
+            ("{\"version\":2,", &[event], InvalidHeader, Error),
+            (
+                r#"{"version":2,"width":0,"height":24}"#,
+                &[event],
+                InvalidHeader,
+                Error,
+            ),
+            (
+                r##"{"version":2,"width":80,"height":24,"theme":{"fg":"#fff","bg":"#000","palette":"#000"}}"##,
+                &[event],
+                InvalidTheme,
+                Error,
+            ),
+            (V2, &[event, r#"[2.0,"o","a"] ,"#], TrailingGarbage, Error),
+            (V2, &[event, "[2.0, \"o\""], InvalidJson, Error),
+            (V2, &[event, r#"{"time":2.0}"#], InvalidEvent, Error),
+            (V2, &[event, r#"[-2.0,"o","a"]"#], NegativeTime, Error),
+            (V2, &[event, r#"[0.5,"o","a"]"#], TimeGoesBack, Error),
+            (V2, &[event, r#"[2.0,"q","a"]"#], UnknownEventCode, Warning),
+            (V2, &[event, r#"[2.0,"x","0"]"#], UnknownEventCode, Warning),
+            (
+                V2,
+                &[event, r#"[2.0,"r","80 by 24"]"#],
+                InvalidResize,
+                Error,
+            ),
+            (V2, &[event, r#"[2.0,"r","0x24"]"#], InvalidResize, Error),
+            (V3, &[event, r#"[2.0,"x","zero"]"#], InvalidEvent, Error),
+        ];
+        for (header, lines, kind, severity) in cases {
+            let (file, first) = recording(header, lines);
+            let expected = if kind == InvalidHeader || kind == InvalidTheme {
+                (kind, severity, 1, 0)
+            } else {
+                (kind, severity, 3, first + event.len() + 1)
+            };
+            assert_eq!(problems(&file), [expected], "{}", file);
+        }
+    }
+
+    #[test]
+    fn finds_bad_bytes_and_cut_off_lines() {
+        let mut bytes = format!("{}\n", V2).into_bytes();
+        bytes.extend_from_slice(b"[1.0,\"o\",\"\xff\"]\n[2.0,\"o\",\"a");
+        let report = lint(&bytes, &Progress::default()).unwrap();
+        let found: Vec<(ProblemKind, usize, usize)> = report
+            .problems
+            .iter()
+            .map(|problem| (problem.kind, problem.line, problem.byte))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (ProblemKind::InvalidUtf8, 2, V2.len() + 1),
+                (ProblemKind::TrailingGarbage, 3, V2.len() + 15),
+            ]
+        );
+        assert_eq!(report.events, 0);
+    }
+
+    #[test]
+    fn checks_the_duration_against_the_last_event() {
+        let (file, _) = recording(
+            r#"{"version":2,"width":80,"height":24,"duration":5.0}"#,
+            &[r#"[1.0,"o","a"]"#, r#"[2.0,"o","b"]"#],
+        );
+        assert_eq!(
+            problems(&file),
+            [(ProblemKind::DurationMismatch, Severity::Warning, 1, 0)]
+        );
+        let (file, _) = recording(
+            r#"{"version":2,"width":80,"height":24,"duration":2.005}"#,
+            &[r#"[1.0,"o","a"]"#, r#"[2.0,"o","b"]"#],
+        );
+        assert!(problems(&file).is_empty());
+    }
+
+    #[test]
+    fn v3_lines_are_intervals_with_comments() {
+        let (file, first) = recording(
+            V3,
+            &[
+                "# a comment",
+                r#"[1.0,"o","a"]"#,
+                r#"[0.5,"o","b"]"#,
+                r#"[-0.5,"o","c"]"#,
+                r#"[0.0,"x","0"]"#,
+            ],
+        );
+        let report = lint_str(&file);
+        assert_eq!(report.source_version, 3);
+        assert_eq!(report.events, 4);
+        // Smaller intervals don't go back in time, only negative ones do
+        let byte = first + "# a comment\n[1.0,\"o\",\"a\"]\n[0.5,\"o\",\"b\"]\n".len();
+        assert_eq!(
+            problems(&file),
+            [(ProblemKind::NegativeTime, Severity::Error, 5, byte)]
+        );
+    }
+
+    #[test]
+    fn v1_frames_are_found_on_their_lines() {
+        let file = concat!(
+            "{\n",
+            "  \"version\": 1, \"width\": 80, \"height\": 24, \"duration\": 9.0,\n",
+            "  \"stdout\": [\n",
+            "    [0.5, \"a\"],\n",
+            "    [-0.5, \"b\"],\n",
+            "    [\"c\"]\n",
+            "  ]\n",
+            "}\n"
+        );
+        let report = lint_str(file);
+        assert_eq!(report.source_version, 1);
+        assert_eq!(report.events, 2);
+        let line = |number: usize| line_start(file.as_bytes(), number).unwrap();
+        assert_eq!(
+            problems(file),
+            [
+                (ProblemKind::DurationMismatch, Severity::Warning, 1, 0),
+                (ProblemKind::NegativeTime, Severity::Error, 5, line(5)),
+                (ProblemKind::InvalidEvent, Severity::Error, 6, line(6)),
+            ]
+        );
+
+        // A broken v1 document is reported as one problem rather than line by line
+        let broken = "{\n  \"version\": 1,\n  \"stdout\": [[0.5, \"a\"],\n";
+        let report = lint_str(broken);
+        assert_eq!(report.source_version, 1);
+        assert_eq!(report.problems.len(), 1);
+        assert_eq!(report.problems[0].kind, ProblemKind::InvalidJson);
+    }
+
+    #[test]
+    fn example_like_files_are_clean() {
+        let (file, _) = recording(
+            V2,
+            &[
+                r#"[0.1,"o","$ "]"#,
+                r#"[0.2,"i","ls\r"]"#,
+                r#"[0.2,"r","100x30"]"#,
+                r#"[0.3,"m","chapter"]"#,
+                "",
+            ],
+        );
+        let report = lint_str(&file);
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+        assert_eq!(
+            (report.events, report.errors(), report.warnings()),
+            (4, 0, 0)
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use asciinema_editor::cast::CastFile;
+use asciinema_editor::terminal::Terminal;
+use eframe::egui::{self, Align2, FontId, Pos2, Rect, RichText, Sense, Stroke, Ui, Vec2};
+
+const PREVIEW_FONT_SIZE: f32 = 12.0;
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 8.0;
+
+/// Every this many events fed the terminal is saved, so seeking backwards replays from the closest save before the playhead instead of from the start
+const CHECKPOINT_EVENTS: usize = 1000;
+/// Saved terminals kept at most. Once there are this many every other one is dropped and saves are made half as often, which bounds the memory taken on recordings of any length
+const MAX_CHECKPOINTS: usize = 128;
+
+/// Terminal after feeding the events up to some point of the recording
+#[derive(Clone)]
+struct ScreenState {
+    terminal: Terminal,
+    /// Time of the last event fed into the terminal
+    fed_time: f64,
+    /// Where to continue reading events from as the byte location of the last fed event and how many events at that byte location were fed. Modification chains put several events at one byte location so the count is needed to resume between them
+    resume: Option<(usize, usize)>,
+}
+
+/// Terminal state replayed up to some point of the recording. Playing forward only feeds the events since the last frame, seeking backwards replays from the last checkpoint before the playhead and editing the file replays from the start
+struct ScreenCache {
+    state: ScreenState,
+    /// `CastFile::revision` the terminal was built from
+    revision: u64,
+    /// Copies of the state taken while feeding events, in order
+    checkpoints: Vec<ScreenState>,
+    /// Events fed since the last checkpoint
+    since_checkpoint: usize,
+    /// Events fed between two checkpoints, doubled whenever the checkpoints are thinned out
+    checkpoint_every: usize,
+}
+
+impl ScreenCache {
+    fn new(cast: &CastFile) -> Self {
+        Self {
+            state: Self::start(cast),
+            revision: cast.revision(),
+            checkpoints: Vec::new(),
+            since_checkpoint: 0,
+            checkpoint_every: CHECKPOINT_EVENTS,
+        }
+    }
+
+    fn start(cast: &CastFile) -> ScreenState {
+        ScreenState {
+            terminal: Terminal::for_header(&cast.header),
+            fed_time: 0.0,
+            resume: None,
+        }
+    }
+
+    /// Byte location to resume from and the number of events to skip there
+    fn resume_point(&self, cast: &CastFile) -> (usize, usize) {
+        self.state.resume.unwrap_or((cast.data_start(), 0))
+    }
+
+    /// Goes back to the last checkpoint at or before `time`, or to the start if there is none. Checkpoints after it are dropped as they are taken again on the way forward
+    fn rewind(&mut self, cast: &CastFile, time: f64) {
+        let kept = self
+            .checkpoints
+            .partition_point(|checkpoint| checkpoint.fed_time <= time);
+        self.checkpoints.truncate(kept);
+        self.state = match self.checkpoints.last() {
+            Some(checkpoint) => checkpoint.clone(),
+            None => Self::start(cast),
+        };
+        self.since_checkpoint = 0;
+    }
+
+    /// Feeds every event up to and including `time`
+    fn advance(&mut self, cast: &CastFile, time: f64) {
+        let (start, skip) = self.resume_point(cast);
+        for positioned in cast.events_from(start).skip(skip) {
+            if positioned.event.time > time {
+                break;
+            }
+            let state = &mut self.state;
+            state.terminal.feed_event(&positioned.event);
+            state.fed_time = positioned.event.time;
+            state.resume = match state.resume {
+                Some((byte_location, count)) if byte_location == positioned.byte_location => {
+                    Some((byte_location, count + 1))
+                }
+                _ => Some((positioned.byte_location, 1)),
+            };
+            self.since_checkpoint += 1;
+            if self.since_checkpoint >= self.checkpoint_every {
+                self.save_checkpoint();
+            }
+        }
+    }
+
+    fn save_checkpoint(&mut self) {
+        if self.checkpoints.len() >= MAX_CHECKPOINTS {
+            let mut position = 0;
+            self.checkpoints.retain(|_| {
+                position += 1;
+                position % 2 == 0
+            });
+            self.checkpoint_every *= 2;
+        }
+        self.checkpoints.push(self.state.clone());
+        self.since_checkpoint = 0;
+    }
+
+    /// The first event that hasn't been fed yet
+    fn next_event_time(&self, cast: &CastFile) -> Option<f64> {
+        let (start, skip) = self.resume_point(cast);
+        cast.events_from(start)
+            .nth(skip)
+            .map(|positioned| positioned.event.time)
+    }
+}
+
+/// Live playback of the recording with modifications applied. Renders the terminal screen at the playhead with play, pause, step and seek controls
+pub struct Preview {
+    /// Current position in seconds of event time
+    playhead: f64,
+    playing: bool,
+    speed: f64,
+    cache: Option<ScreenCache>,
+}
+
+impl Preview {
+    pub fn new() -> Self {
+        Self {
+            playhead: 0.0,
+            playing: false,
+            speed: 1.0,
+            cache: None,
+        }
+    }
+
+    /// Drops the cached screen, used when a different file is opened
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn playhead(&self) -> f64 {
+        self.playhead
+    }
+
+    /// Moves the playhead to `time` and pauses so the screen stays on what was jumped to
+    pub fn seek(&mut self, time: f64) {
+        self.playhead = time.max(0.0);
+        self.playing = false;
+    }
+
+    /// Brings the cached terminal up to the playhead, replaying from the start if the file changed or from the last checkpoint before the playhead if it moved backwards
+    fn update_screen(&mut self, cast: &CastFile) -> &ScreenCache {
+        if self
+            .cache
+            .as_ref()
+            .is_some_and(|cache| cache.revision != cast.revision())
+        {
+            self.cache = None;
+        }
+        let cache = self.cache.get_or_insert_with(|| ScreenCache::new(cast));
+        if self.playhead < cache.state.fed_time {
+            cache.rewind(cast, self.playhead);
+        }
+        cache.advance(cast, self.playhead);
+        cache
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, cast: &CastFile) {
+        let end_time = cast.end_time();
+
+        if self.playing {
+            self.playhead += ui.input(|i| i.stable_dt) as f64 * self.speed;
+            if self.playhead >= end_time {
+                self.playhead = end_time;
+                self.playing = false;
+            }
+            ui.ctx().request_repaint();
+        }
+
+        ui.horizontal(|ui| {
+            let play_label = if self.playing { "Pause" } else { "Play" };
+            if ui.button(play_label).clicked() {
+                // Playing from the end restarts the recording
+                if !self.playing && self.playhead >= end_time {
+                    self.playhead = 0.0;
+                }
+                self.playing = !self.playing;
+            }
+            if ui
+                .button("Step")
+                .on_hover_text("Jump to the next event")
+                .clicked()
+            {
+                self.playing = false;
+                if let Some(time) = self.update_screen(cast).next_event_time(cast) {
+                    self.playhead = time;
+                }
+            }
+            ui.label(RichText::new("Speed:").strong());
+            ui.add(
+                egui::Slider::new(&mut self.speed, MIN_SPEED..=MAX_SPEED)
+                    .logarithmic(true)
+                    .suffix("x"),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            ui.spacing_mut().slider_width = (ui.available_width() - 100.0).max(100.0);
+            ui.add(
+                egui::Slider::new(&mut self.playhead, 0.0..=end_time.max(f64::EPSILON))
+                    .suffix("s")
+                    .max_decimals(3),
+            );
+        });
+
+        let terminal = &self.update_screen(cast).state.terminal;
+        egui::ScrollArea::both()
+            .id_salt("preview_screen")
+            .show(ui, |ui| render_terminal(ui, terminal));
+    }
+}
+
+/// Paints the terminal grid cell by cell. Each character is placed at its exact cell position so glyphs from fallback fonts can't push the rest of the row out of alignment
+fn render_terminal(ui: &mut Ui, terminal: &Terminal) {
+    let font = FontId::monospace(PREVIEW_FONT_SIZE);
+    let (cell_width, cell_height) =
+        ui.fonts(|fonts| (fonts.glyph_width(&font, 'M'), fonts.row_height(&font)));
+    let size = Vec2::new(
+        cell_width * terminal.width() as f32,
+        cell_height * terminal.height() as f32,
+    );
+    let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, terminal.theme().bg);
+
+    for (row, cells) in terminal.rows().iter().enumerate() {
+        let y = rect.min.y + row as f32 * cell_height;
+        for (col, cell) in cells.iter().enumerate() {
+            if cell.spacer {
+                continue;
+            }
+            let (fg, bg) = terminal.cell_colors(cell);
+            let min = Pos2::new(rect.min.x + col as f32 * cell_width, y);
+            // Wide characters cover their spacer cell as well
+            let span = if cells.get(col + 1).is_some_and(|next| next.spacer) {
+                2.0
+            } else {
+                1.0
+            };
+            let cell_rect = Rect::from_min_size(min, Vec2::new(cell_width * span, cell_height));
+
+            if bg != terminal.theme().bg {
+                painter.rect_filled(cell_rect, 0.0, bg);
+            }
+            if cell.ch != ' ' {
+                painter.text(min, Align2::LEFT_TOP, cell.ch, font.clone(), fg);
+            }
+            let attrs = cell.pen.attrs;
+            if attrs.underline {
+                let y = cell_rect.max.y - 1.0;
+                painter.hline(cell_rect.x_range(), y, Stroke::new(1.0, fg));
+            }
+            if attrs.strikethrough {
+                painter.hline(
+                    cell_rect.x_range(),
+                    cell_rect.center().y,
+                    Stroke::new(1.0, fg),
+                );
+            }
+        }
+    }
+
+    if terminal.cursor_visible() {
+        let (col, row) = terminal.cursor();
+        let min = Pos2::new(
+            rect.min.x + col as f32 * cell_width,
+            rect.min.y + row as f32 * cell_height,
+        );
+        let cursor_rect = Rect::from_min_size(min, Vec2::new(cell_width, cell_height));
+        painter.rect_stroke(
+            cursor_rect,
+            0.0,
+            Stroke::new(1.0, terminal.theme().fg.gamma_multiply(0.8)),
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use crate::cast::{find_next_newline, BLOCK_SIZE};
+use std::{
+    num::NonZeroUsize,
+    ops::Range,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+};
+
+/// Blocks handed to each thread between two checks for cancellation. Threads are started again for every round so it has to be long enough for that to not matter while still stopping soon after a cancel
+const BLOCKS_PER_THREAD: usize = 16;
+
+/// Progress of scans over the memory map, shared between the threads doing them and whoever is waiting on them, which may cancel them
+#[derive(Debug, Default)]
+pub struct Progress {
+    /// Bytes scanned so far
+    done: AtomicUsize,
+    /// Bytes of every scan started with this progress
+    total: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl Progress {
+    /// Share of the bytes scanned so far between 0 and 1, 0 until a scan starts
+    pub fn fraction(&self) -> f32 {
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 {
+            return 0.0;
+        }
+        (self.done.load(Ordering::Relaxed) as f64 / total as f64).min(1.0) as f32
+    }
+
+    /// Makes scans using this progress stop after the blocks they are on
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Adds `bytes` to the bytes of every scan, for work that reads the file one event after another instead of through `scan_blocks` and reports how far it got with `advance`
+    pub fn expect(&self, bytes: usize) {
+        self.total.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Counts `bytes` more as scanned
+    pub fn advance(&self, bytes: usize) {
+        self.done.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+/// Splits `bytes[start..end]` into ranges of at least `BLOCK_SIZE` bytes that end directly after a newline, so every line is in exactly one block. Only the last block may be shorter
+pub fn blocks(bytes: &[u8], start: usize, end: usize) -> Vec<Range<usize>> {
+    let mut blocks = Vec::with_capacity((end - start) / BLOCK_SIZE + 1);
+    let mut position = start;
+    while position < end {
+        let block_end = match position + BLOCK_SIZE {
+            past if past >= end => end,
+            past => find_next_newline(bytes, past - 1).min(end),
+        };
+        blocks.push(position..block_end);
+        position = block_end;
+    }
+    blocks
+}
+
+/// Runs `scan` over the blocks of `bytes[start..end]` on as many threads as there are cores and hands the results to `combine` in the order of the blocks, so results can be merged as if the lines were read one after another. Only the results of a few blocks per thread are held at a time which keeps memory use flat on files of any size. Scanning stops early once `combine` returns false. Returns `None` if the scan was cancelled through `progress` before it finished
+pub fn scan_blocks<T: Send>(
+    bytes: &[u8],
+    start: usize,
+    end: usize,
+    progress: &Progress,
+    scan: impl Fn(Range<usize>) -> T + Sync,
+    mut combine: impl FnMut(T) -> bool,
+) -> Option<()> {
+    let start = start.min(end);
+    progress.total.fetch_add(end - start, Ordering::Relaxed);
+    let blocks = blocks(bytes, start, end);
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(blocks.len())
+        .max(1);
+
+    for round in blocks.chunks(threads * BLOCKS_PER_THREAD) {
+        if progress.is_cancelled() {
+            return None;
+        }
+        let per_thread = round.len().div_ceil(threads);
+        let results: Vec<T> = thread::scope(|scope| {
+            let workers: Vec<_> = round
+                .chunks(per_thread)
+                .map(|chunk| {
+                    let scan = &scan;
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|block| scan(block.clone()))
+                            .collect::<Vec<T>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        });
+        let scanned: usize = round.iter().map(|block| block.len()).sum();
+        progress.done.fetch_add(scanned, Ordering::Relaxed);
+        for result in results {
+            if !combine(result) {
+                return Some(());
+            }
+        }
+    }
+    (!progress.is_cancelled()).then_some(())
+}
@@ -0,0 +1,549 @@
+use crate::asciicast_egui::{escape_data, EventData};
+use crate::cast::{EventPositioned, Snapshot};
+use crate::scan::Progress;
+use regex::{Captures, Regex, RegexBuilder};
+use std::ops::Range;
+
+/// Searches stop after this many matching events so a pattern like `.` on a huge recording doesn't fill memory with results
+pub const MAX_MATCHES: usize = 10_000;
+
+/// What to look for in event data, as entered in the search bar
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    pub pattern: String,
+    /// Read the pattern as a regular expression instead of plain text
+    pub regex: bool,
+    pub case_sensitive: bool,
+    /// Match against the text left after removing terminal escape sequences so colored output can be found by what it shows on screen
+    pub ignore_escapes: bool,
+    /// Event codes to look in, every type is searched when this is empty
+    pub codes: Vec<char>,
+}
+
+impl SearchOptions {
+    /// Compiles the pattern. Plain text is escaped so both kinds of pattern go through the same matcher
+    pub fn compile(&self) -> Result<Search, regex::Error> {
+        let pattern = if self.regex {
+            self.pattern.clone()
+        } else {
+            regex::escape(&self.pattern)
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()?;
+        Ok(Search {
+            regex,
+            ignore_escapes: self.ignore_escapes,
+            codes: self.codes.clone(),
+            expand: self.regex,
+        })
+    }
+}
+
+/// A compiled search ready to be run over events
+#[derive(Debug, Clone)]
+pub struct Search {
+    regex: Regex,
+    ignore_escapes: bool,
+    codes: Vec<char>,
+    /// Whether replacements expand capture groups like `${1}`. The braces are needed when text follows, as `$1x` reads as the group named `1x`. Plain text searches insert the replacement as written
+    expand: bool,
+}
+
+impl Search {
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+
+    /// Whether events of this type are searched
+    pub fn includes(&self, data: &EventData) -> bool {
+        self.codes.is_empty() || self.codes.contains(&data.code())
+    }
+
+    /// Byte ranges of the matches in `text`. Empty matches, which patterns like `a*` find everywhere, are left out. With `ignore_escapes` the pattern runs over the text without escape sequences and the ranges are mapped back so they still index into `text`
+    pub fn find_in_text(&self, text: &str) -> Vec<Range<usize>> {
+        if !self.ignore_escapes {
+            return self
+                .regex
+                .find_iter(text)
+                .filter(|found| !found.is_empty())
+                .map(|found| found.range())
+                .collect();
+        }
+        let piece = Piece::new(self, text.to_string());
+        self.regex
+            .find_iter(&piece.haystack)
+            .filter(|found| !found.is_empty())
+            .map(|found| piece.text_range(found.range()))
+            .collect()
+    }
+
+    /// Byte ranges of the matches in the unescaped data of an event, empty for event types the search leaves out
+    pub fn find_in(&self, data: &EventData) -> Vec<Range<usize>> {
+        if !self.includes(data) {
+            return Vec::new();
+        }
+        self.find_in_text(&data.get_unescaped_data())
+    }
+
+    /// Only typed and printed text is replaced, markers and the other event types are left alone even if they match
+    fn replaces(&self, data: &EventData) -> bool {
+        matches!(data, EventData::Output(_) | EventData::Input(_)) && self.includes(data)
+    }
+
+    /// The text a match is replaced with
+    fn replacement(&self, captures: &Captures, replacement: &str) -> String {
+        let mut text = String::new();
+        if self.expand {
+            captures.expand(replacement, &mut text);
+        } else {
+            text.push_str(replacement);
+        }
+        text
+    }
+}
+
+/// A range of an event's unescaped data and the text it is replaced with
+type Edit = (Range<usize>, String);
+
+/// Unescaped data of an event together with the text the pattern runs over, which is the same text unless escape sequences are ignored
+struct Piece {
+    text: String,
+    haystack: String,
+    /// Position in `text` of each byte of `haystack` when the two differ
+    offsets: Option<Vec<usize>>,
+}
+
+impl Piece {
+    fn new(search: &Search, text: String) -> Self {
+        if !search.ignore_escapes {
+            return Self {
+                haystack: text.clone(),
+                text,
+                offsets: None,
+            };
+        }
+        let (haystack, offsets) = strip_escapes(&text);
+        Self {
+            text,
+            haystack,
+            offsets: Some(offsets),
+        }
+    }
+
+    /// Range of `text` covered by a non empty range of `haystack`. Escape sequences inside the range are covered too while those around it are not
+    fn text_range(&self, range: Range<usize>) -> Range<usize> {
+        match &self.offsets {
+            Some(offsets) => offsets[range.start]..offsets[range.end - 1] + 1,
+            None => range,
+        }
+    }
+
+    /// `text` with the replacements of the given ranges, which are in order and don't overlap
+    fn replaced(&self, edits: &[Edit]) -> String {
+        let mut replaced = String::with_capacity(self.text.len());
+        let mut position = 0;
+        for (range, replacement) in edits {
+            replaced.push_str(&self.text[position..range.start]);
+            replaced.push_str(replacement);
+            position = range.end;
+        }
+        replaced.push_str(&self.text[position..]);
+        replaced
+    }
+}
+
+/// An event with at least one match
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    pub positioned: EventPositioned,
+    /// Position of the event in the edited file counting from 0, the same numbering the event grid scrolls by
+    pub number: usize,
+    /// Byte ranges of the matches in the unescaped event data
+    pub ranges: Vec<Range<usize>>,
+}
+
+impl SearchMatch {
+    /// Text of the first match escaped the way event data is shown
+    pub fn first_match(&self) -> String {
+        let data = self.positioned.event.data.get_unescaped_data();
+        self.ranges
+            .first()
+            .map_or_else(String::new, |range| escape_data(&data[range.clone()]))
+    }
+}
+
+/// New data for one event of a replace
+#[derive(Debug, Clone)]
+pub struct Replacement {
+    pub positioned: EventPositioned,
+    pub data: EventData,
+}
+
+/// The edits a replace makes, worked out before anything is changed so they can be counted first
+#[derive(Debug, Clone, Default)]
+pub struct ReplacePlan {
+    pub replacements: Vec<Replacement>,
+    /// Number of matches replaced, which can be more than the number of events changed
+    pub count: usize,
+}
+
+/// Works out the new data of every output and input event with matches of `search`, memory mapped lines and pending modifications alike. With `across_events` a match may start in one event and end in the next event of the same type, as output is often split where the recording happened to flush. The whole replacement then goes into the first event and the rest of the match is removed from the second. `None` if cancelled through `progress`
+pub fn plan_replace(
+    cast: &Snapshot,
+    search: &Search,
+    replacement: &str,
+    across_events: bool,
+    progress: &Progress,
+) -> Option<ReplacePlan> {
+    let mut plan = ReplacePlan::default();
+    let mut events = cast.events_tracked(progress).peekable();
+    // An event whose start was already replaced by a match beginning in the event before it, with the edit made and the position in its haystack the search continues from
+    let mut carried: Option<(EventPositioned, Piece, Vec<Edit>, usize)> = None;
+    loop {
+        let (positioned, piece, mut edits, start) = match carried.take() {
+            Some(carried) => carried,
+            None => match events.next() {
+                Some(positioned) if search.replaces(&positioned.event.data) => {
+                    let piece = Piece::new(search, positioned.event.data.get_unescaped_data());
+                    (positioned, piece, Vec::new(), 0)
+                }
+                Some(_) => continue,
+                None => break,
+            },
+        };
+        let next = if across_events {
+            events
+                .next_if(|next| {
+                    next.event.data.code() == positioned.event.data.code()
+                        && search.replaces(&next.event.data)
+                })
+                .map(|next| {
+                    let piece = Piece::new(search, next.event.data.get_unescaped_data());
+                    (next, piece)
+                })
+        } else {
+            None
+        };
+
+        // The next event is searched together with this one so only matches starting in this one are taken here
+        let boundary = piece.haystack.len() - start;
+        let haystack = match &next {
+            Some((_, next_piece)) => format!("{}{}", &piece.haystack[start..], next_piece.haystack),
+            None => piece.haystack[start..].to_string(),
+        };
+        let mut next_edits = Vec::new();
+        let mut next_start = 0;
+        for captures in search.regex.captures_iter(&haystack) {
+            let found = captures.get(0).expect("group 0 is the whole match");
+            if found.is_empty() {
+                continue;
+            }
+            if found.start() >= boundary {
+                break;
+            }
+            let text = search.replacement(&captures, replacement);
+            plan.count += 1;
+            if found.end() <= boundary {
+                edits.push((
+                    piece.text_range(start + found.start()..start + found.end()),
+                    text,
+                ));
+                continue;
+            }
+            // `next` is there as the match wouldn't reach past the boundary otherwise
+            let (_, next_piece) = next.as_ref().expect("match crosses into the next event");
+            edits.push((
+                piece.text_range(start + found.start()..piece.haystack.len()),
+                text,
+            ));
+            next_start = found.end() - boundary;
+            next_edits.push((next_piece.text_range(0..next_start), String::new()));
+            break;
+        }
+
+        if !edits.is_empty() {
+            let text = escape_data(&piece.replaced(&edits));
+            let data = match positioned.event.data {
+                EventData::Input(_) => EventData::Input(text),
+                _ => EventData::Output(text),
+            };
+            plan.replacements.push(Replacement { positioned, data });
+        }
+        carried = next.map(|(next, next_piece)| (next, next_piece, next_edits, next_start));
+    }
+    (!progress.is_cancelled()).then_some(plan)
+}
+
+/// Scans every event of the recording, memory mapped lines and pending modifications alike, for `search`. Blocks of the file are searched on several threads and events are numbered once their results come back in order. The scan stops after `MAX_MATCHES` matching events and returns `None` if cancelled through `progress`
+pub fn find_matches(
+    snapshot: &Snapshot,
+    search: &Search,
+    progress: &Progress,
+) -> Option<Vec<SearchMatch>> {
+    let mut matches = Vec::new();
+    let mut events_before = 0;
+    snapshot.scan_events(
+        progress,
+        |events| {
+            let mut count = 0;
+            let mut found = Vec::new();
+            for positioned in events {
+                if found.len() < MAX_MATCHES {
+                    let ranges = search.find_in(&positioned.event.data);
+                    if !ranges.is_empty() {
+                        found.push(SearchMatch {
+                            positioned,
+                            number: count,
+                            ranges,
+                        });
+                    }
+                }
+                count += 1;
+            }
+            (count, found)
+        },
+        |(count, found)| {
+            matches.extend(found.into_iter().map(|mut found| {
+                found.number += events_before;
+                found
+            }));
+            matches.truncate(MAX_MATCHES);
+            events_before += count;
+            matches.len() < MAX_MATCHES
+        },
+    )?;
+    Some(matches)
+}
+
+/// Splits `text` at the match ranges into pieces escaped the way event data is shown, each paired with whether it is part of a match
+pub fn highlight_segments(text: &str, ranges: &[Range<usize>]) -> Vec<(String, bool)> {
+    let mut segments = Vec::with_capacity(ranges.len() * 2 + 1);
+    let mut position = 0;
+    for range in ranges {
+        if range.start > position {
+            segments.push((escape_data(&text[position..range.start]), false));
+        }
+        segments.push((escape_data(&text[range.clone()]), true));
+        position = range.end;
+    }
+    if position < text.len() {
+        segments.push((escape_data(&text[position..]), false));
+    }
+    segments
+}
+
+/// Removes terminal escape sequences from `text`. Returns what is left together with the position in `text` of each of its bytes
+pub(crate) fn strip_escapes(text: &str) -> (String, Vec<usize>) {
+    let mut visible = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c != '\u{1b}' {
+            visible.push(c);
+            offsets.extend(start..start + c.len_utf8());
+            continue;
+        }
+        match chars.next() {
+            // Control sequences end with a byte from `@` to `~` after their parameters
+            Some((_, '[')) => {
+                for (_, c) in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // Operating system commands like window titles and the other string sequences end with BEL or ESC \
+            Some((_, ']' | 'P' | 'X' | '^' | '_')) => {
+                while let Some((_, c)) = chars.next() {
+                    if c == '\u{7}' {
+                        break;
+                    }
+                    if c == '\u{1b}' {
+                        chars.next_if(|&(_, c)| c == '\\');
+                        break;
+                    }
+                }
+            }
+            // Character set selections take intermediate bytes before their final byte
+            Some((_, ' '..='/')) => {
+                chars.next();
+            }
+            // Everything else is ESC and a single character
+            _ => {}
+        }
+    }
+    (visible, offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cast::CastFile;
+    use crate::test_util::{open, HEADER};
+
+    fn search(pattern: &str, regex: bool, ignore_escapes: bool) -> Search {
+        SearchOptions {
+            pattern: pattern.to_string(),
+            regex,
+            case_sensitive: true,
+            ignore_escapes,
+            codes: Vec::new(),
+        }
+        .compile()
+        .unwrap()
+    }
+
+    fn replace(
+        cast: &CastFile,
+        search: &Search,
+        replacement: &str,
+        across_events: bool,
+    ) -> ReplacePlan {
+        plan_replace(
+            &cast.snapshot(),
+            search,
+            replacement,
+            across_events,
+            &Progress::default(),
+        )
+        .unwrap()
+    }
+
+    /// Unescaped new data of every event a replace changes together with the number of matches
+    fn replaced(plan: &ReplacePlan) -> (Vec<String>, usize) {
+        let data = plan
+            .replacements
+            .iter()
+            .map(|replacement| replacement.data.get_unescaped_data())
+            .collect();
+        (data, plan.count)
+    }
+
+    #[test]
+    fn replaces_matches_spanning_two_events() {
+        let cast = open(
+            "across",
+            HEADER,
+            &[
+                r#"[0.1,"o","$ hel"]"#,
+                r#"[0.2,"o","lo world"]"#,
+                r#"[0.3,"i","hello"]"#,
+            ],
+        );
+        let hello = search("hello", false, false);
+
+        let plan = replace(&cast, &hello, "bye", true);
+        assert_eq!(
+            replaced(&plan),
+            (
+                vec!["$ bye".to_string(), " world".to_string(), "bye".to_string()],
+                2
+            )
+        );
+        let times: Vec<f64> = plan
+            .replacements
+            .iter()
+            .map(|replacement| replacement.positioned.event.time)
+            .collect();
+        assert_eq!(times, [0.1, 0.2, 0.3]);
+
+        // Only whole events are searched without `across_events`
+        let plan = replace(&cast, &hello, "bye", false);
+        assert_eq!(replaced(&plan), (vec!["bye".to_string()], 1));
+    }
+
+    #[test]
+    fn ignoring_escapes_matches_what_is_on_screen() {
+        let cast = open(
+            "escapes",
+            HEADER,
+            &[r#"[0.1,"o","\u001b[1m$ \u001b[31mre\u001b[0md\r\n"]"#],
+        );
+        let red = search("red", false, true);
+        assert_eq!(
+            red.find_in_text("\u{1b}[31mre\u{1b}[0md red"),
+            [5..12, 13..16],
+            "escape sequences inside a match are part of it"
+        );
+        assert!(search("red", false, false)
+            .find_in_text("\u{1b}[31mre\u{1b}[0md")
+            .is_empty());
+
+        let plan = replace(&cast, &red, "blue", false);
+        assert_eq!(
+            replaced(&plan),
+            (vec!["\u{1b}[1m$ \u{1b}[31mblue\r\n".to_string()], 1)
+        );
+    }
+
+    #[test]
+    fn capture_groups_need_braces_before_text() {
+        let cast = open("groups", HEADER, &[r#"[0.1,"o","ab ab"]"#]);
+        let cases = [
+            // `$1x` is the group named `1x`, which doesn't exist and expands to nothing
+            ("$1x", "ab ab", true, " "),
+            ("${1}x", "ab ab", true, "ax ax"),
+            ("$1", "ab ab", true, "a a"),
+            ("$0$0", "ab ab", true, "abab abab"),
+            // Plain text searches insert the replacement as written
+            ("${1}x", "ab", false, "${1}x ${1}x"),
+        ];
+        for (template, pattern, regex, expected) in cases {
+            let pattern = if regex { "(a)b" } else { pattern };
+            let plan = replace(&cast, &search(pattern, regex, false), template, false);
+            assert_eq!(
+                replaced(&plan),
+                (vec![expected.to_string()], 2),
+                "{}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn replacements_may_change_the_length() {
+        let cast = open(
+            "lengths",
+            HEADER,
+            &[
+                r#"[0.1,"o","one two one"]"#,
+                r#"[0.2,"m","one"]"#,
+                r#"[0.3,"o","none"]"#,
+            ],
+        );
+        let one = search("one", false, false);
+        let plan = replace(&cast, &one, "1", false);
+        assert_eq!(
+            replaced(&plan),
+            (vec!["1 two 1".to_string(), "n1".to_string()], 3)
+        );
+        let plan = replace(&cast, &one, "three", false);
+        assert_eq!(
+            replaced(&plan),
+            (vec!["three two three".to_string(), "nthree".to_string()], 3)
+        );
+        let plan = replace(&cast, &one, "", false);
+        assert_eq!(
+            replaced(&plan),
+            (vec![" two ".to_string(), "n".to_string()], 3)
+        );
+    }
+
+    #[test]
+    fn strips_escape_sequences() {
+        let (visible, offsets) =
+            strip_escapes("a\u{1b}[1;31mb\u{1b}]2;title\u{7}c\u{1b}(Bd\u{1b}7e");
+        assert_eq!(visible, "abcde");
+        assert_eq!(offsets, [0, 8, 19, 23, 26]);
+        assert_eq!(
+            highlight_segments("a\u{1b}bc", &[1..2, 3..4]),
+            [
+                ("a".to_string(), false),
+                ("\\u001b".to_string(), true),
+                ("b".to_string(), false),
+                ("c".to_string(), true)
+            ]
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use crate::asciicast_egui::{escape_data, Event, EventData};
+use crate::cast::{write_replacing, CastError, CastFile};
+use crate::terminal::Terminal;
+use crate::timing::round_time;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Where a recording is split
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplitPoints {
+    /// At every marker so each chapter becomes its own file
+    Markers,
+    /// At the given times in seconds
+    Times(Vec<f64>),
+    /// At the first event after every pause longer than the given number of seconds. The pause itself is left out of both parts
+    IdleGaps(f64),
+}
+
+/// One file of a split recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitPart {
+    /// Time in the recording the part starts at, which becomes time 0 of its file
+    pub start: f64,
+    /// Time of the last event of the part
+    pub end: f64,
+    pub events: usize,
+}
+
+impl SplitPart {
+    pub fn duration(&self) -> f64 {
+        round_time(self.end - self.start)
+    }
+}
+
+/// Times new parts start at in order, not counting the start of the recording
+fn boundaries(cast: &CastFile, points: &SplitPoints) -> Result<Vec<f64>, CastError> {
+    let mut boundaries: Vec<f64> = match points {
+        SplitPoints::Markers => cast
+            .markers()
+            .iter()
+            .map(|marker| marker.event.time)
+            .collect(),
+        SplitPoints::Times(times) => {
+            if times.iter().any(|time| !time.is_finite() || *time < 0.0) {
+                return Err(CastError::TimingError);
+            }
+            times.clone()
+        }
+        SplitPoints::IdleGaps(limit) => {
+            if !limit.is_finite() || *limit <= 0.0 {
+                return Err(CastError::TimingError);
+            }
+            let mut previous: Option<f64> = None;
+            let mut boundaries = Vec::new();
+            for positioned in cast.events() {
+                let time = positioned.event.time;
+                if previous.is_some_and(|previous| time - previous > *limit) {
+                    boundaries.push(time);
+                }
+                previous = Some(time);
+            }
+            boundaries
+        }
+    };
+    boundaries.sort_by(f64::total_cmp);
+    boundaries.dedup();
+    Ok(boundaries)
+}
+
+/// Works out the parts a split makes by reading the recording once. Parts without events, like the one before a marker at the very start, are left out so every part is playable
+pub fn plan_split(cast: &CastFile, points: &SplitPoints) -> Result<Vec<SplitPart>, CastError> {
+    let boundaries = boundaries(cast, points)?;
+    let mut parts: Vec<SplitPart> = Vec::new();
+    let mut next = 0;
+    let mut start = 0.0;
+    for positioned in cast.events() {
+        let time = positioned.event.time;
+        while let Some(&boundary) = boundaries.get(next).filter(|&&boundary| boundary <= time) {
+            start = boundary;
+            next += 1;
+        }
+        match parts.last_mut() {
+            Some(part) if part.start == start => {
+                part.end = time;
+                part.events += 1;
+            }
+            _ => parts.push(SplitPart {
+                start,
+                end: time,
+                events: 1,
+            }),
+        }
+    }
+    Ok(parts)
+}
+
+/// Path of part `index` counting from 0 out of `count` parts saved next to `base`, like `demo_2.cast` for `demo.cast`. Numbers are padded so the files sort in order
+pub fn part_path(base: &Path, index: usize, count: usize) -> PathBuf {
+    let width = count.to_string().len();
+    let mut name = base.file_stem().unwrap_or_default().to_owned();
+    name.push(format!("_{:0width$}.cast", index + 1, width = width));
+    base.with_file_name(name)
+}
+
+/// Writes each part planned by `plan_split` as a v2 file to the path beside it. Every part gets a copy of the header with the terminal size and timestamp at its start and its own duration, and has its times moved so it starts at 0. Parts after the first begin with an output event redrawing the screen as it was where the part starts, as their output assumes a terminal the earlier parts drew. The recording is streamed once for all of the parts
+pub(crate) fn write_parts(
+    cast: &CastFile,
+    parts: &[SplitPart],
+    paths: &[PathBuf],
+) -> Result<(), CastError> {
+    let mut events = cast.events().peekable();
+    let mut terminal = Terminal::for_header(&cast.header);
+    for (index, (part, path)) in parts.iter().zip(paths).enumerate() {
+        let end = parts
+            .get(index + 1)
+            .map_or(f64::INFINITY, |next| next.start);
+        // Events the plan didn't put in a part, which only happens if the recording changed since, still shape the screen
+        while let Some(positioned) = events.next_if(|positioned| positioned.event.time < part.start)
+        {
+            terminal.feed_event(&positioned.event);
+        }
+
+        let mut header = cast.header.clone();
+        header.width = terminal.width() as u16;
+        header.height = terminal.height() as u16;
+        header.duration = Some(part.duration());
+        header.timestamp = header
+            .timestamp
+            .map(|timestamp| timestamp + part.start as u64);
+        let preamble = match index {
+            0 => String::new(),
+            _ => terminal.redraw(),
+        };
+
+        write_replacing(path, |mut writer| {
+            serde_json::to_writer(&mut writer, &header)
+                .map_err(|e| CastError::SerializationError(e.to_string()))?;
+            writeln!(writer)?;
+            if !preamble.is_empty() {
+                let redraw = Event {
+                    time: 0.0,
+                    data: EventData::Output(escape_data(&preamble)),
+                };
+                serde_json::to_writer(&mut writer, &redraw)
+                    .map_err(|e| CastError::SerializationError(e.to_string()))?;
+                writeln!(writer)?;
+            }
+            while let Some(positioned) = events.next_if(|positioned| positioned.event.time < end) {
+                terminal.feed_event(&positioned.event);
+                let mut event = positioned.event;
+                event.time = round_time(event.time - part.start);
+                serde_json::to_writer(&mut writer, &event)
+                    .map_err(|e| CastError::SerializationError(e.to_string()))?;
+                writeln!(writer)?;
+            }
+            writer.flush()?;
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asciicast_egui::Header;
+    use crate::test_util::{open, temp_path};
+    use std::fs;
+
+    const HEADER: &str = "{\"version\":2,\"width\":20,\"height\":3,\"timestamp\":1000}";
+
+    fn part(start: f64, end: f64, events: usize) -> SplitPart {
+        SplitPart { start, end, events }
+    }
+
+    const EVENTS: [&str; 6] = [
+        r#"[0.5,"o","$ make\r\n"]"#,
+        r#"[1.0,"m","build"]"#,
+        r#"[1.5,"o","\u001b[32mok\u001b[0m\r\n"]"#,
+        r#"[2.0,"r","30x4"]"#,
+        r#"[6.0,"m","test"]"#,
+        r#"[6.5,"o","done"]"#,
+    ];
+
+    #[test]
+    fn plans_parts_at_markers_times_and_pauses() {
+        let cast = open("split-plan", HEADER, &EVENTS);
+        let cases = [
+            (
+                SplitPoints::Markers,
+                vec![part(0.0, 0.5, 1), part(1.0, 2.0, 3), part(6.0, 6.5, 2)],
+            ),
+            // Times between events and past the end leave no empty parts
+            (
+                SplitPoints::Times(vec![7.0, 1.2, 0.0]),
+                vec![part(0.0, 1.0, 2), part(1.2, 6.5, 4)],
+            ),
+            (
+                SplitPoints::IdleGaps(3.0),
+                vec![part(0.0, 2.0, 4), part(6.0, 6.5, 2)],
+            ),
+        ];
+        for (points, parts) in cases {
+            assert_eq!(plan_split(&cast, &points).unwrap(), parts, "{:?}", points);
+        }
+        assert!(plan_split(&cast, &SplitPoints::Times(vec![-1.0])).is_err());
+        assert!(plan_split(&cast, &SplitPoints::IdleGaps(0.0)).is_err());
+    }
+
+    #[test]
+    fn parts_start_at_zero_on_the_screen_they_continue() {
+        let cast = open("split-write", HEADER, &EVENTS);
+        let parts = plan_split(&cast, &SplitPoints::Markers).unwrap();
+        let paths = cast.split(&parts, &temp_path("split")).unwrap();
+        let files: Vec<String> = paths
+            .iter()
+            .map(|path| {
+                let file = fs::read_to_string(path).unwrap();
+                let _ = fs::remove_file(path);
+                file
+            })
+            .collect();
+        assert_eq!(files.len(), 3);
+
+        let headers: Vec<Header> = files
+            .iter()
+            .map(|file| serde_json::from_str(file.lines().next().unwrap()).unwrap())
+            .collect();
+        let summary: Vec<(u16, u16, Option<f64>, Option<u64>)> = headers
+            .iter()
+            .map(|header| {
+                (
+                    header.width,
+                    header.height,
+                    header.duration,
+                    header.timestamp,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (20, 3, Some(0.5), Some(1000)),
+                (20, 3, Some(1.0), Some(1001)),
+                (30, 4, Some(0.5), Some(1006)),
+            ]
+        );
+
+        let events: Vec<Vec<Event>> = files
+            .iter()
+            .map(|file| {
+                file.lines()
+                    .skip(1)
+                    .map(|line| serde_json::from_str(line).unwrap())
+                    .collect()
+            })
+            .collect();
+        let times = |events: &[Event]| -> Vec<f64> { events.iter().map(|e| e.time).collect() };
+        assert_eq!(times(&events[0]), [0.5]);
+        // Later parts start with the redraw followed by their own events moved back to 0
+        assert_eq!(times(&events[1]), [0.0, 0.0, 0.5, 1.0]);
+        assert_eq!(times(&events[2]), [0.0, 0.0, 0.5]);
+
+        // Playing a part from its start shows what the whole recording shows at the end of it
+        let mut whole = Terminal::for_header(&cast.header);
+        for positioned in cast.events() {
+            whole.feed_event(&positioned.event);
+        }
+        let mut part = Terminal::for_header(&headers[2]);
+        for event in &events[2] {
+            part.feed_event(event);
+        }
+        assert_eq!(part.rows(), whole.rows());
+        assert_eq!(part.cursor(), whole.cursor());
+        assert!(matches!(events[2][0].data, EventData::Output(_)));
+    }
+
+    #[test]
+    fn part_paths_sort_in_order() {
+        let base = Path::new("/tmp/demo.cast");
+        assert_eq!(part_path(base, 0, 3), Path::new("/tmp/demo_1.cast"));
+        assert_eq!(part_path(base, 9, 12), Path::new("/tmp/demo_10.cast"));
+        assert_eq!(part_path(base, 0, 12), Path::new("/tmp/demo_01.cast"));
+    }
+}
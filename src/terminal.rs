@@ -0,0 +1,1406 @@
+use crate::asciicast_egui::{Event, EventData, Header, Theme};
+use crate::cast::CastFile;
+use crate::color::Color32;
+use std::mem;
+use unicode_width::UnicodeWidthChar;
+
+const TAB_WIDTH: usize = 8;
+/// Levels used by the 6x6x6 color cube of the 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+
+/// A color as set by SGR sequences. It stays symbolic until it's resolved against a `Theme` so that a theme change recolors the whole screen without replaying events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TermColor {
+    /// The theme foreground or background depending on where it's used
+    #[default]
+    Default,
+    /// An entry of the 256 color palette. 0-15 come from the theme and the rest are the standard xterm cube and grayscale ramp
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl TermColor {
+    /// Resolves the color against a theme. `default` is used for `TermColor::Default`
+    pub fn resolve(&self, theme: &Theme, default: Color32) -> Color32 {
+        match *self {
+            TermColor::Default => default,
+            TermColor::Indexed(index) => indexed_color(index, theme),
+            TermColor::Rgb(r, g, b) => Color32::from_rgb(r, g, b),
+        }
+    }
+}
+
+/// Resolves an index of the 256 color palette. Themes with only 8 colors reuse them for the bright half
+fn indexed_color(index: u8, theme: &Theme) -> Color32 {
+    match index {
+        0..=15 => {
+            let index = index as usize;
+            theme
+                .palette
+                .get(index)
+                .or_else(|| theme.palette.get(index % 8))
+                .copied()
+                .unwrap_or(theme.fg)
+        }
+        16..=231 => {
+            let index = index - 16;
+            Color32::from_rgb(
+                CUBE_LEVELS[(index / 36) as usize],
+                CUBE_LEVELS[((index / 6) % 6) as usize],
+                CUBE_LEVELS[(index % 6) as usize],
+            )
+        }
+        232..=255 => {
+            let level = 8 + (index - 232) * 10;
+            Color32::from_rgb(level, level, level)
+        }
+    }
+}
+
+/// Text attributes set by SGR sequences
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Attributes {
+    pub bold: bool,
+    pub faint: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub blink: bool,
+    pub inverse: bool,
+    pub hidden: bool,
+    pub strikethrough: bool,
+}
+
+/// The graphic rendition applied to newly written characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Pen {
+    pub fg: TermColor,
+    pub bg: TermColor,
+    pub attrs: Attributes,
+}
+
+/// A single character position of the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub pen: Pen,
+    /// Set on the right half of a double width character. The character itself lives in the cell to the left
+    pub spacer: bool,
+}
+
+impl Cell {
+    fn blank(pen: Pen) -> Self {
+        // Erasing keeps the background color but drops everything else, matching xterm's background color erase
+        Self {
+            ch: ' ',
+            pen: Pen {
+                bg: pen.bg,
+                ..Pen::default()
+            },
+            spacer: false,
+        }
+    }
+
+    /// Resolves the foreground and background of this cell against a theme. Bold brightens the 8 basic colors and inverse, faint and hidden are applied here
+    pub fn colors(&self, theme: &Theme) -> (Color32, Color32) {
+        let attrs = self.pen.attrs;
+        let fg = match self.pen.fg {
+            TermColor::Indexed(index) if attrs.bold && index < 8 => TermColor::Indexed(index + 8),
+            fg => fg,
+        };
+        let mut fg = fg.resolve(theme, theme.fg);
+        let mut bg = self.pen.bg.resolve(theme, theme.bg);
+        if attrs.inverse {
+            mem::swap(&mut fg, &mut bg);
+        }
+        if attrs.faint {
+            fg = fg.lerp_to_gamma(bg, 0.5);
+        }
+        if attrs.hidden {
+            fg = bg;
+        }
+        (fg, bg)
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self::blank(Pen::default())
+    }
+}
+
+/// Cursor state saved by DECSC and the alternate screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SavedCursor {
+    col: usize,
+    row: usize,
+    pen: Pen,
+    origin_mode: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParserState {
+    Ground,
+    Escape,
+    /// An escape sequence with intermediate bytes such as charset designation `ESC ( B`
+    EscapeIntermediate,
+    Csi,
+    /// Operating system command, terminated by BEL or ST
+    Osc,
+    /// Device control, privacy message and application program command strings which are ignored until ST
+    IgnoredString,
+    /// An ESC was read inside a string, if it's followed by `\` the string is terminated
+    StringEscape,
+}
+
+/// A VT100/xterm compatible terminal state machine. `Output` data is fed through an ANSI parser which updates a grid of cells holding characters, colors and attributes. This is the model every rendered view of a recording is built from
+#[derive(Debug, Clone)]
+pub struct Terminal {
+    width: usize,
+    height: usize,
+    primary: Vec<Vec<Cell>>,
+    alternate: Vec<Vec<Cell>>,
+    alternate_active: bool,
+    col: usize,
+    row: usize,
+    /// Set when a character was written to the last column. The wrap happens when the next character arrives so that writing exactly to the edge doesn't scroll
+    pending_wrap: bool,
+    pen: Pen,
+    saved_cursor: SavedCursor,
+    /// Cursor saved when entering the alternate screen with mode 1049
+    saved_primary_cursor: SavedCursor,
+    /// Inclusive scroll region rows
+    scroll_top: usize,
+    scroll_bottom: usize,
+    tab_stops: Vec<bool>,
+    cursor_visible: bool,
+    autowrap: bool,
+    origin_mode: bool,
+    insert_mode: bool,
+    title: Option<String>,
+    last_char: Option<char>,
+    state: ParserState,
+    /// Parameter and intermediate bytes of the CSI sequence being parsed
+    sequence: String,
+    /// Payload of the OSC string being parsed
+    osc: String,
+    /// The state to return to when a `StringEscape` turns out not to be a terminator
+    string_state: ParserState,
+    /// Colors the symbolic cell colors are resolved against
+    theme: Theme,
+}
+
+impl Terminal {
+    pub fn new(width: usize, height: usize) -> Self {
+        let width = width.max(1);
+        let height = height.max(1);
+        Self {
+            width,
+            height,
+            primary: blank_grid(width, height, Pen::default()),
+            alternate: blank_grid(width, height, Pen::default()),
+            alternate_active: false,
+            col: 0,
+            row: 0,
+            pending_wrap: false,
+            pen: Pen::default(),
+            saved_cursor: SavedCursor::default(),
+            saved_primary_cursor: SavedCursor::default(),
+            scroll_top: 0,
+            scroll_bottom: height - 1,
+            tab_stops: default_tab_stops(width),
+            cursor_visible: true,
+            autowrap: true,
+            origin_mode: false,
+            insert_mode: false,
+            title: None,
+            last_char: None,
+            state: ParserState::Ground,
+            sequence: String::new(),
+            osc: String::new(),
+            string_state: ParserState::Ground,
+            theme: Theme::default(),
+        }
+    }
+
+    /// Creates a terminal sized and themed by the header of a recording. Recordings without a theme use the asciinema default palette
+    pub fn for_header(header: &Header) -> Self {
+        let mut terminal = Self::new(header.width as usize, header.height as usize);
+        terminal.theme = header.theme.clone().unwrap_or_default();
+        terminal
+    }
+
+    /// Replays every event of `cast` up to and including `time`, pending modifications included, and returns the resulting screen
+    pub fn from_cast(cast: &CastFile, time: f64) -> Self {
+        let mut terminal = Self::for_header(&cast.header);
+        for positioned in cast.events() {
+            if positioned.event.time > time {
+                break;
+            }
+            terminal.feed_event(&positioned.event);
+        }
+        terminal
+    }
+
+    /// Whether feeding `event` can change the screen, only `Output` and `Resize` do
+    pub fn changes_screen(event: &Event) -> bool {
+        matches!(event.data, EventData::Output(_) | EventData::Resize(_, _))
+    }
+
+    /// Applies a single event. `Output` is parsed and `Resize` changes the screen size, other events don't affect the screen
+    pub fn feed_event(&mut self, event: &Event) {
+        match &event.data {
+            EventData::Output(_) => self.feed_str(&event.data.get_unescaped_data()),
+            EventData::Resize(width, height) => self.resize(*width as usize, *height as usize),
+            _ => (),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Resolved (foreground, background) colors of a cell using the terminal's theme
+    pub fn cell_colors(&self, cell: &Cell) -> (Color32, Color32) {
+        cell.colors(&self.theme)
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Rows of the screen currently shown, which is the alternate screen while it's active
+    pub fn rows(&self) -> &[Vec<Cell>] {
+        if self.alternate_active {
+            &self.alternate
+        } else {
+            &self.primary
+        }
+    }
+
+    /// Cursor position as (column, row)
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.col, self.row)
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Window title set through OSC 0 or 2
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Plain text of a row with trailing blanks removed
+    pub fn row_text(&self, row: usize) -> String {
+        self.rows()
+            .get(row)
+            .map(|cells| {
+                cells
+                    .iter()
+                    .filter(|cell| !cell.spacer)
+                    .map(|cell| cell.ch)
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Output that brings a fresh terminal of the same size to this state: the text and colors of the screen, the primary screen behind an active alternate screen, the saved cursor, scroll region, modes, window title, cursor position and pen. Returns an empty string for a terminal in its initial state. Tab stops and character sets aren't reproduced
+    pub fn redraw(&self) -> String {
+        let mut out = String::new();
+        draw_grid(&mut out, &self.primary);
+        // Drawing the grids leaves the pen reset but restoring the saved cursors sets theirs
+        let mut pen_set = false;
+        if self.alternate_active {
+            // Leaving the alternate screen restores the cursor it was entered with
+            let saved = self.saved_primary_cursor;
+            out.push_str(&format!("\x1b[{};{}H", saved.row + 1, saved.col + 1));
+            out.push_str(&sgr(&saved.pen));
+            out.push_str("\x1b[?1049h");
+            pen_set = true;
+            draw_grid(&mut out, &self.alternate);
+        }
+        if (self.scroll_top, self.scroll_bottom) != (0, self.height - 1) {
+            out.push_str(&format!(
+                "\x1b[{};{}r",
+                self.scroll_top + 1,
+                self.scroll_bottom + 1
+            ));
+        }
+        if self.saved_cursor != SavedCursor::default() {
+            let saved = self.saved_cursor;
+            out.push_str(&format!("\x1b[{};{}H", saved.row + 1, saved.col + 1));
+            out.push_str(&sgr(&saved.pen));
+            pen_set = true;
+            if saved.origin_mode {
+                // Origin mode homes the cursor so the position is set again after it
+                out.push_str("\x1b[?6h");
+                out.push_str(&format!(
+                    "\x1b[{};{}H",
+                    saved.row.saturating_sub(self.scroll_top) + 1,
+                    saved.col + 1
+                ));
+            }
+            out.push_str("\x1b7");
+            if saved.origin_mode {
+                out.push_str("\x1b[?6l");
+            }
+        }
+        if self.origin_mode {
+            out.push_str("\x1b[?6h");
+        }
+
+        let top = if self.origin_mode { self.scroll_top } else { 0 };
+        let cells = &self.rows()[self.row];
+        if self.pending_wrap {
+            // A pending wrap only comes from printing into the last column so that character is printed again to put the cursor past it
+            let col = if cells[self.col].spacer && self.col > 0 {
+                self.col - 1
+            } else {
+                self.col
+            };
+            out.push_str(&format!(
+                "\x1b[{};{}H",
+                self.row.saturating_sub(top) + 1,
+                col + 1
+            ));
+            out.push_str(&sgr(&cells[col].pen));
+            out.push(cells[col].ch);
+            pen_set = true;
+        } else if (self.col, self.row) != (0, 0) || !out.is_empty() {
+            out.push_str(&format!(
+                "\x1b[{};{}H",
+                self.row.saturating_sub(top) + 1,
+                self.col + 1
+            ));
+        }
+        if self.pen != Pen::default() || pen_set {
+            out.push_str(&sgr(&self.pen));
+        }
+
+        if self.insert_mode {
+            out.push_str("\x1b[4h");
+        }
+        if !self.autowrap {
+            out.push_str("\x1b[?7l");
+        }
+        if !self.cursor_visible {
+            out.push_str("\x1b[?25l");
+        }
+        if let Some(title) = &self.title {
+            out.push_str(&format!("\x1b]2;{}\x07", title));
+        }
+        out
+    }
+
+    /// Resizes the screen keeping content anchored to the top left. The scroll region is reset as it can't be meaningfully kept
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let width = width.max(1);
+        let height = height.max(1);
+        for grid in [&mut self.primary, &mut self.alternate] {
+            grid.resize_with(height, || vec![Cell::default(); width]);
+            for row in grid.iter_mut() {
+                row.resize(width, Cell::default());
+            }
+        }
+        self.width = width;
+        self.height = height;
+        self.scroll_top = 0;
+        self.scroll_bottom = height - 1;
+        self.tab_stops = default_tab_stops(width);
+        self.col = self.col.min(width - 1);
+        self.row = self.row.min(height - 1);
+        self.pending_wrap = false;
+    }
+
+    /// Feeds output text through the parser
+    pub fn feed_str(&mut self, text: &str) {
+        for ch in text.chars() {
+            self.feed_char(ch);
+        }
+    }
+
+    fn feed_char(&mut self, ch: char) {
+        match self.state {
+            ParserState::Ground => self.ground(ch),
+            ParserState::Escape => self.escape(ch),
+            ParserState::EscapeIntermediate => {
+                if ('\x20'..='\x2f').contains(&ch) {
+                    self.sequence.push(ch);
+                } else {
+                    // DECALN is the one sequence with intermediates that changes the screen, charset designations and the like are consumed without effect
+                    if self.sequence == "#" && ch == '8' {
+                        self.fill_alignment_pattern();
+                    }
+                    self.state = ParserState::Ground;
+                }
+            }
+            ParserState::Csi => self.csi(ch),
+            ParserState::Osc => match ch {
+                '\x07' => self.finish_osc(),
+                '\x1b' => {
+                    self.string_state = ParserState::Osc;
+                    self.state = ParserState::StringEscape;
+                }
+                _ => self.osc.push(ch),
+            },
+            ParserState::IgnoredString => {
+                if ch == '\x1b' {
+                    self.string_state = ParserState::IgnoredString;
+                    self.state = ParserState::StringEscape;
+                } else if ch == '\x07' {
+                    self.state = ParserState::Ground;
+                }
+            }
+            ParserState::StringEscape => {
+                if ch == '\\' {
+                    if self.string_state == ParserState::Osc {
+                        self.finish_osc();
+                    }
+                    self.state = ParserState::Ground;
+                } else {
+                    // Not a string terminator so the ESC starts a new sequence and the string is dropped
+                    self.osc.clear();
+                    self.state = ParserState::Escape;
+                    self.escape(ch);
+                }
+            }
+        }
+    }
+
+    fn ground(&mut self, ch: char) {
+        match ch {
+            '\x1b' => self.state = ParserState::Escape,
+            '\x07' | '\x00' | '\x0e' | '\x0f' => (),
+            '\x08' => {
+                self.col = self.col.saturating_sub(1);
+                self.pending_wrap = false;
+            }
+            '\t' => self.tab_forward(1),
+            '\n' | '\x0b' | '\x0c' => self.linefeed(),
+            '\r' => {
+                self.col = 0;
+                self.pending_wrap = false;
+            }
+            c if c.is_control() => (),
+            c => self.print(c),
+        }
+    }
+
+    fn escape(&mut self, ch: char) {
+        self.state = ParserState::Ground;
+        match ch {
+            '[' => {
+                self.sequence.clear();
+                self.state = ParserState::Csi;
+            }
+            ']' => {
+                self.osc.clear();
+                self.state = ParserState::Osc;
+            }
+            'P' | 'X' | '^' | '_' => self.state = ParserState::IgnoredString,
+            '7' => self.save_cursor(),
+            '8' => self.restore_cursor(),
+            'D' => self.linefeed(),
+            'E' => {
+                self.col = 0;
+                self.linefeed();
+            }
+            'M' => self.reverse_index(),
+            'H' => self.tab_stops[self.col] = true,
+            'c' => {
+                let theme = mem::take(&mut self.theme);
+                *self = Self::new(self.width, self.height);
+                self.theme = theme;
+            }
+            '\x20'..='\x2f' => {
+                self.sequence.clear();
+                self.sequence.push(ch);
+                self.state = ParserState::EscapeIntermediate;
+            }
+            _ => (),
+        }
+    }
+
+    fn finish_osc(&mut self) {
+        let osc = mem::take(&mut self.osc);
+        if let Some((command, text)) = osc.split_once(';') {
+            if command == "0" || command == "2" {
+                self.title = Some(text.to_string());
+            }
+        }
+        self.state = ParserState::Ground;
+    }
+
+    fn csi(&mut self, ch: char) {
+        match ch {
+            // Parameter and intermediate bytes
+            '\x20'..='\x3f' => self.sequence.push(ch),
+            // Final byte
+            '\x40'..='\x7e' => {
+                self.state = ParserState::Ground;
+                let sequence = mem::take(&mut self.sequence);
+                self.dispatch_csi(&sequence, ch);
+            }
+            '\x1b' => self.state = ParserState::Escape,
+            // C0 controls are executed in the middle of a sequence like real terminals do
+            c if c.is_control() => self.ground(c),
+            _ => self.state = ParserState::Ground,
+        }
+    }
+
+    fn dispatch_csi(&mut self, sequence: &str, action: char) {
+        let (private, rest) = match sequence.chars().next() {
+            Some(marker @ ('<' | '=' | '>' | '?')) => (Some(marker), &sequence[1..]),
+            _ => (None, sequence),
+        };
+        // Intermediate bytes such as the space in `CSI 2 SP q` change the meaning entirely so they aren't interpreted as parameters
+        let intermediates_start = rest
+            .find(|c: char| ('\x20'..='\x2f').contains(&c))
+            .unwrap_or(rest.len());
+        let (params_text, intermediates) = rest.split_at(intermediates_start);
+        let params = parse_params(params_text);
+        let param = |index: usize, default: usize| -> usize {
+            params
+                .get(index)
+                .and_then(|param| param.first())
+                .copied()
+                .filter(|&value| value != 0)
+                .unwrap_or(default)
+        };
+
+        if !intermediates.is_empty() {
+            return;
+        }
+
+        match (private, action) {
+            (None, '@') => self.insert_chars(param(0, 1)),
+            (None, 'A') => self.move_up(param(0, 1)),
+            (None, 'B' | 'e') => self.move_down(param(0, 1)),
+            (None, 'C' | 'a') => self.move_to_col(self.col.saturating_add(param(0, 1))),
+            (None, 'D') => self.move_to_col(self.col.saturating_sub(param(0, 1))),
+            (None, 'E') => {
+                self.move_down(param(0, 1));
+                self.col = 0;
+            }
+            (None, 'F') => {
+                self.move_up(param(0, 1));
+                self.col = 0;
+            }
+            (None, 'G' | '`') => self.move_to_col(param(0, 1) - 1),
+            (None, 'H' | 'f') => self.move_to(param(1, 1) - 1, param(0, 1) - 1),
+            (None, 'I') => self.tab_forward(param(0, 1)),
+            (None | Some('?'), 'J') => self.erase_display(params.first().map_or(0, |p| p[0])),
+            (None | Some('?'), 'K') => self.erase_line(params.first().map_or(0, |p| p[0])),
+            (None, 'L') => self.insert_lines(param(0, 1)),
+            (None, 'M') => self.delete_lines(param(0, 1)),
+            (None, 'P') => self.delete_chars(param(0, 1)),
+            (None, 'S') => self.scroll_up(param(0, 1)),
+            (None, 'T') => self.scroll_down(param(0, 1)),
+            (None, 'X') => self.erase_chars(param(0, 1)),
+            (None, 'Z') => self.tab_backward(param(0, 1)),
+            (None, 'b') => {
+                if let Some(ch) = self.last_char {
+                    for _ in 0..param(0, 1).min(self.width * self.height) {
+                        self.print(ch);
+                    }
+                }
+            }
+            (None, 'd') => {
+                let col = self.col;
+                self.move_to(col, param(0, 1) - 1);
+            }
+            (None, 'g') => match params.first().map_or(0, |p| p[0]) {
+                0 => self.tab_stops[self.col] = false,
+                3 => self.tab_stops.iter_mut().for_each(|stop| *stop = false),
+                _ => (),
+            },
+            (None, 'h') => self.set_modes(&params, true),
+            (None, 'l') => self.set_modes(&params, false),
+            (Some('?'), 'h') => self.set_private_modes(&params, true),
+            (Some('?'), 'l') => self.set_private_modes(&params, false),
+            (None, 'm') => self.select_graphic_rendition(&params),
+            (None, 'r') => {
+                let top = param(0, 1) - 1;
+                let bottom = param(1, self.height).min(self.height) - 1;
+                if top < bottom {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.move_to(0, 0);
+                }
+            }
+            (None, 's') => self.save_cursor(),
+            (None, 'u') => self.restore_cursor(),
+            // Reports, cursor styles, window manipulation and the like have no effect on the screen
+            _ => (),
+        }
+    }
+
+    fn set_modes(&mut self, params: &[Vec<usize>], enabled: bool) {
+        for param in params {
+            if param.first() == Some(&4) {
+                self.insert_mode = enabled;
+            }
+        }
+    }
+
+    fn set_private_modes(&mut self, params: &[Vec<usize>], enabled: bool) {
+        for param in params {
+            match param.first().copied().unwrap_or(0) {
+                6 => {
+                    self.origin_mode = enabled;
+                    self.move_to(0, 0);
+                }
+                7 => self.autowrap = enabled,
+                25 => self.cursor_visible = enabled,
+                47 | 1047 => self.switch_screen(enabled),
+                1048 => {
+                    if enabled {
+                        self.save_cursor();
+                    } else {
+                        self.restore_cursor();
+                    }
+                }
+                1049 => {
+                    if enabled {
+                        self.saved_primary_cursor = self.current_cursor();
+                        self.switch_screen(true);
+                        self.alternate = blank_grid(self.width, self.height, self.pen);
+                    } else {
+                        self.switch_screen(false);
+                        self.apply_saved_cursor(self.saved_primary_cursor);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn switch_screen(&mut self, alternate: bool) {
+        self.alternate_active = alternate;
+        self.pending_wrap = false;
+    }
+
+    fn select_graphic_rendition(&mut self, params: &[Vec<usize>]) {
+        if params.is_empty() {
+            self.pen = Pen::default();
+            return;
+        }
+
+        let mut index = 0;
+        while index < params.len() {
+            let param = &params[index];
+            let attrs = &mut self.pen.attrs;
+            match param.first().copied().unwrap_or(0) {
+                0 => self.pen = Pen::default(),
+                1 => attrs.bold = true,
+                2 => attrs.faint = true,
+                3 => attrs.italic = true,
+                4 | 21 => attrs.underline = true,
+                5 | 6 => attrs.blink = true,
+                7 => attrs.inverse = true,
+                8 => attrs.hidden = true,
+                9 => attrs.strikethrough = true,
+                22 => {
+                    attrs.bold = false;
+                    attrs.faint = false;
+                }
+                23 => attrs.italic = false,
+                24 => attrs.underline = false,
+                25 => attrs.blink = false,
+                27 => attrs.inverse = false,
+                28 => attrs.hidden = false,
+                29 => attrs.strikethrough = false,
+                code @ 30..=37 => self.pen.fg = TermColor::Indexed((code - 30) as u8),
+                38 => {
+                    let (color, consumed) = extended_color(params, index);
+                    if let Some(color) = color {
+                        self.pen.fg = color;
+                    }
+                    index += consumed;
+                }
+                39 => self.pen.fg = TermColor::Default,
+                code @ 40..=47 => self.pen.bg = TermColor::Indexed((code - 40) as u8),
+                48 => {
+                    let (color, consumed) = extended_color(params, index);
+                    if let Some(color) = color {
+                        self.pen.bg = color;
+                    }
+                    index += consumed;
+                }
+                49 => self.pen.bg = TermColor::Default,
+                code @ 90..=97 => self.pen.fg = TermColor::Indexed((code - 90 + 8) as u8),
+                code @ 100..=107 => self.pen.bg = TermColor::Indexed((code - 100 + 8) as u8),
+                _ => (),
+            }
+            index += 1;
+        }
+    }
+
+    fn print(&mut self, ch: char) {
+        let char_width = ch.width().unwrap_or(0);
+        // Combining characters are dropped as cells only hold a single character
+        if char_width == 0 {
+            return;
+        }
+
+        if self.pending_wrap {
+            self.pending_wrap = false;
+            if self.autowrap {
+                self.col = 0;
+                self.linefeed();
+            }
+        }
+        // A wide character that doesn't fit in the remaining space wraps early
+        if char_width == 2 && self.col + 1 >= self.width {
+            if self.autowrap && self.width > 1 {
+                self.col = 0;
+                self.linefeed();
+            } else {
+                return;
+            }
+        }
+
+        if self.insert_mode {
+            self.insert_chars(char_width);
+        }
+
+        let (col, row, pen) = (self.col, self.row, self.pen);
+        self.clear_wide_pair(col, row);
+        self.screen_mut()[row][col] = Cell {
+            ch,
+            pen,
+            spacer: false,
+        };
+        if char_width == 2 {
+            self.clear_wide_pair(col + 1, row);
+            self.screen_mut()[row][col + 1] = Cell {
+                ch: ' ',
+                pen,
+                spacer: true,
+            };
+        }
+        self.last_char = Some(ch);
+
+        self.col += char_width;
+        if self.col >= self.width {
+            self.col = self.width - 1;
+            self.pending_wrap = true;
+        }
+    }
+
+    /// Overwriting either half of a wide character blanks the other half so no orphaned halves are left
+    fn clear_wide_pair(&mut self, col: usize, row: usize) {
+        let width = self.width;
+        let cells = &mut self.screen_mut()[row];
+        if cells[col].spacer && col > 0 {
+            cells[col - 1] = Cell::blank(cells[col - 1].pen);
+        } else if col + 1 < width && cells[col + 1].spacer {
+            cells[col + 1] = Cell::blank(cells[col + 1].pen);
+        }
+    }
+
+    fn screen_mut(&mut self) -> &mut Vec<Vec<Cell>> {
+        if self.alternate_active {
+            &mut self.alternate
+        } else {
+            &mut self.primary
+        }
+    }
+
+    fn linefeed(&mut self) {
+        self.pending_wrap = false;
+        if self.row == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.row + 1 < self.height {
+            self.row += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        self.pending_wrap = false;
+        if self.row == self.scroll_top {
+            self.scroll_down(1);
+        } else if self.row > 0 {
+            self.row -= 1;
+        }
+    }
+
+    /// Scrolls the scroll region up, dropping lines at the top and adding blank lines at the bottom
+    fn scroll_up(&mut self, count: usize) {
+        let (top, bottom, width, pen) = (self.scroll_top, self.scroll_bottom, self.width, self.pen);
+        let count = count.min(bottom - top + 1);
+        let screen = self.screen_mut();
+        screen.drain(top..top + count);
+        for _ in 0..count {
+            screen.insert(bottom + 1 - count, vec![Cell::blank(pen); width]);
+        }
+    }
+
+    /// Scrolls the scroll region down, dropping lines at the bottom and adding blank lines at the top
+    fn scroll_down(&mut self, count: usize) {
+        let (top, bottom, width, pen) = (self.scroll_top, self.scroll_bottom, self.width, self.pen);
+        let count = count.min(bottom - top + 1);
+        let screen = self.screen_mut();
+        screen.drain(bottom + 1 - count..=bottom);
+        for _ in 0..count {
+            screen.insert(top, vec![Cell::blank(pen); width]);
+        }
+    }
+
+    fn insert_lines(&mut self, count: usize) {
+        if self.row < self.scroll_top || self.row > self.scroll_bottom {
+            return;
+        }
+        let top = mem::replace(&mut self.scroll_top, self.row);
+        self.scroll_down(count);
+        self.scroll_top = top;
+        self.col = 0;
+        self.pending_wrap = false;
+    }
+
+    fn delete_lines(&mut self, count: usize) {
+        if self.row < self.scroll_top || self.row > self.scroll_bottom {
+            return;
+        }
+        let top = mem::replace(&mut self.scroll_top, self.row);
+        self.scroll_up(count);
+        self.scroll_top = top;
+        self.col = 0;
+        self.pending_wrap = false;
+    }
+
+    fn insert_chars(&mut self, count: usize) {
+        let (col, row, width, pen) = (self.col, self.row, self.width, self.pen);
+        let count = count.min(width - col);
+        let cells = &mut self.screen_mut()[row];
+        cells.truncate(width - count);
+        for _ in 0..count {
+            cells.insert(col, Cell::blank(pen));
+        }
+        self.pending_wrap = false;
+    }
+
+    fn delete_chars(&mut self, count: usize) {
+        let (col, row, width, pen) = (self.col, self.row, self.width, self.pen);
+        let count = count.min(width - col);
+        let cells = &mut self.screen_mut()[row];
+        cells.drain(col..col + count);
+        cells.resize(width, Cell::blank(pen));
+        self.pending_wrap = false;
+    }
+
+    fn erase_chars(&mut self, count: usize) {
+        let (col, row, width, pen) = (self.col, self.row, self.width, self.pen);
+        let end = col.saturating_add(count).min(width);
+        self.screen_mut()[row][col..end].fill(Cell::blank(pen));
+        self.pending_wrap = false;
+    }
+
+    fn erase_line(&mut self, mode: usize) {
+        let (col, row, width, pen) = (self.col, self.row, self.width, self.pen);
+        let range = match mode {
+            0 => col..width,
+            1 => 0..col + 1,
+            2 => 0..width,
+            _ => return,
+        };
+        self.screen_mut()[row][range].fill(Cell::blank(pen));
+        self.pending_wrap = false;
+    }
+
+    fn erase_display(&mut self, mode: usize) {
+        let (row, height, width, pen) = (self.row, self.height, self.width, self.pen);
+        match mode {
+            0 => {
+                self.erase_line(0);
+                for line in &mut self.screen_mut()[row + 1..height] {
+                    line.fill(Cell::blank(pen));
+                }
+            }
+            1 => {
+                self.erase_line(1);
+                for line in &mut self.screen_mut()[..row] {
+                    line.fill(Cell::blank(pen));
+                }
+            }
+            // There is no scrollback so erasing saved lines is the same as erasing the screen
+            2 | 3 => *self.screen_mut() = blank_grid(width, height, pen),
+            _ => (),
+        }
+    }
+
+    fn fill_alignment_pattern(&mut self) {
+        let pen = Pen::default();
+        for line in self.screen_mut().iter_mut() {
+            line.fill(Cell {
+                ch: 'E',
+                pen,
+                spacer: false,
+            });
+        }
+        self.move_to(0, 0);
+    }
+
+    /// Moves to the `count`th tab stop to the right. Every column is passed within `width` stops so huge counts stop there
+    fn tab_forward(&mut self, count: usize) {
+        for _ in 0..count.min(self.width) {
+            let next = (self.col + 1..self.width).find(|&col| self.tab_stops[col]);
+            self.col = next.unwrap_or(self.width - 1);
+        }
+        self.pending_wrap = false;
+    }
+
+    fn tab_backward(&mut self, count: usize) {
+        for _ in 0..count.min(self.width) {
+            let previous = (0..self.col).rev().find(|&col| self.tab_stops[col]);
+            self.col = previous.unwrap_or(0);
+        }
+        self.pending_wrap = false;
+    }
+
+    fn move_up(&mut self, count: usize) {
+        // The cursor stops at the scroll region edge when it starts inside the region
+        let limit = if self.row >= self.scroll_top {
+            self.scroll_top
+        } else {
+            0
+        };
+        self.row = self.row.saturating_sub(count).max(limit);
+        self.pending_wrap = false;
+    }
+
+    fn move_down(&mut self, count: usize) {
+        let limit = if self.row <= self.scroll_bottom {
+            self.scroll_bottom
+        } else {
+            self.height - 1
+        };
+        self.row = self.row.saturating_add(count).min(limit);
+        self.pending_wrap = false;
+    }
+
+    fn move_to_col(&mut self, col: usize) {
+        self.col = col.min(self.width - 1);
+        self.pending_wrap = false;
+    }
+
+    /// Moves to an absolute position, relative to the scroll region in origin mode
+    fn move_to(&mut self, col: usize, row: usize) {
+        let (top, bottom) = if self.origin_mode {
+            (self.scroll_top, self.scroll_bottom)
+        } else {
+            (0, self.height - 1)
+        };
+        self.col = col.min(self.width - 1);
+        self.row = top.saturating_add(row).min(bottom);
+        self.pending_wrap = false;
+    }
+
+    fn current_cursor(&self) -> SavedCursor {
+        SavedCursor {
+            col: self.col,
+            row: self.row,
+            pen: self.pen,
+            origin_mode: self.origin_mode,
+        }
+    }
+
+    fn apply_saved_cursor(&mut self, saved: SavedCursor) {
+        self.col = saved.col.min(self.width - 1);
+        self.row = saved.row.min(self.height - 1);
+        self.pen = saved.pen;
+        self.origin_mode = saved.origin_mode;
+        self.pending_wrap = false;
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_cursor = self.current_cursor();
+    }
+
+    fn restore_cursor(&mut self) {
+        self.apply_saved_cursor(self.saved_cursor);
+    }
+}
+
+fn blank_grid(width: usize, height: usize, pen: Pen) -> Vec<Vec<Cell>> {
+    vec![vec![Cell::blank(pen); width]; height]
+}
+
+/// Writes the cells of a screen that aren't blank, each row starting with a cursor move to its first column. The pen is reset first as the grid is drawn over whatever was set before
+fn draw_grid(out: &mut String, rows: &[Vec<Cell>]) {
+    let mut pen = Pen::default();
+    let mut reset = false;
+    for (row, cells) in rows.iter().enumerate() {
+        let Some(last) = cells.iter().rposition(|cell| *cell != Cell::default()) else {
+            continue;
+        };
+        if !reset {
+            out.push_str("\x1b[0m");
+            reset = true;
+        }
+        out.push_str(&format!("\x1b[{};1H", row + 1));
+        for cell in cells[..=last].iter().filter(|cell| !cell.spacer) {
+            if cell.pen != pen {
+                out.push_str(&sgr(&cell.pen));
+                pen = cell.pen;
+            }
+            out.push(cell.ch);
+        }
+    }
+    if pen != Pen::default() {
+        out.push_str("\x1b[0m");
+    }
+}
+
+/// SGR sequence setting exactly `pen`, starting from a reset
+fn sgr(pen: &Pen) -> String {
+    let mut params = vec!["0".to_string()];
+    let attrs = pen.attrs;
+    for (enabled, code) in [
+        (attrs.bold, "1"),
+        (attrs.faint, "2"),
+        (attrs.italic, "3"),
+        (attrs.underline, "4"),
+        (attrs.blink, "5"),
+        (attrs.inverse, "7"),
+        (attrs.hidden, "8"),
+        (attrs.strikethrough, "9"),
+    ] {
+        if enabled {
+            params.push(code.to_string());
+        }
+    }
+    for (color, base, bright, extended) in [(pen.fg, 30, 90, 38), (pen.bg, 40, 100, 48)] {
+        match color {
+            TermColor::Default => {}
+            TermColor::Indexed(index @ 0..=7) => params.push((base + index as u16).to_string()),
+            TermColor::Indexed(index @ 8..=15) => {
+                params.push((bright + index as u16 - 8).to_string())
+            }
+            TermColor::Indexed(index) => params.push(format!("{};5;{}", extended, index)),
+            TermColor::Rgb(r, g, b) => params.push(format!("{};2;{};{};{}", extended, r, g, b)),
+        }
+    }
+    format!("\x1b[{}m", params.join(";"))
+}
+
+fn default_tab_stops(width: usize) -> Vec<bool> {
+    (0..width).map(|col| col % TAB_WIDTH == 0).collect()
+}
+
+/// Splits CSI parameters on `;` with `:` separated sub parameters kept together. Missing values are 0 which every sequence treats as its default
+fn parse_params(text: &str) -> Vec<Vec<usize>> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+    text.split(';')
+        .map(|param| {
+            param
+                .split(':')
+                .map(|value| value.parse().unwrap_or(0))
+                .collect()
+        })
+        .collect()
+}
+
+/// Parses a 256 color or true color SGR parameter at `index`, in either the `38;5;n` or the `38:5:n` form. Returns the color and how many extra `;` separated parameters it consumed
+fn extended_color(params: &[Vec<usize>], index: usize) -> (Option<TermColor>, usize) {
+    let param = &params[index];
+    if param.len() > 1 {
+        // Colon form keeps everything in one parameter. True color may include an empty color space id
+        let color = match param[1] {
+            5 => param.get(2).map(|&n| TermColor::Indexed(n as u8)),
+            2 => {
+                let rgb = if param.len() >= 6 {
+                    &param[3..6]
+                } else {
+                    &param[2..param.len().min(5)]
+                };
+                (rgb.len() == 3).then(|| TermColor::Rgb(rgb[0] as u8, rgb[1] as u8, rgb[2] as u8))
+            }
+            _ => None,
+        };
+        return (color, 0);
+    }
+
+    let value = |offset: usize| params.get(index + offset).and_then(|p| p.first()).copied();
+    match value(1) {
+        Some(5) => (value(2).map(|n| TermColor::Indexed(n as u8)), 2),
+        Some(2) => match (value(2), value(3), value(4)) {
+            (Some(r), Some(g), Some(b)) => (Some(TermColor::Rgb(r as u8, g as u8, b as u8)), 4),
+            _ => (None, params.len() - index - 1),
+        },
+        _ => (None, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fed(width: usize, height: usize, output: &str) -> Terminal {
+        let mut terminal = Terminal::new(width, height);
+        terminal.feed_str(output);
+        terminal
+    }
+
+    fn screen(terminal: &Terminal) -> Vec<String> {
+        (0..terminal.height())
+            .map(|row| terminal.row_text(row))
+            .collect()
+    }
+
+    /// Replays the redraw of `terminal` into a fresh terminal of the same size and checks everything it promises to reproduce
+    fn assert_redraws(terminal: &Terminal) {
+        let replayed = fed(terminal.width(), terminal.height(), &terminal.redraw());
+        assert_eq!(replayed.primary, terminal.primary);
+        assert_eq!(replayed.alternate_active, terminal.alternate_active);
+        assert_eq!(replayed.rows(), terminal.rows());
+        assert_eq!(replayed.cursor(), terminal.cursor());
+        assert_eq!(replayed.pending_wrap, terminal.pending_wrap);
+        assert_eq!(replayed.pen, terminal.pen);
+        assert_eq!(replayed.saved_cursor, terminal.saved_cursor);
+        assert_eq!(
+            (replayed.scroll_top, replayed.scroll_bottom),
+            (terminal.scroll_top, terminal.scroll_bottom)
+        );
+        assert_eq!(replayed.origin_mode, terminal.origin_mode);
+        assert_eq!(replayed.insert_mode, terminal.insert_mode);
+        assert_eq!(replayed.autowrap, terminal.autowrap);
+        assert_eq!(replayed.cursor_visible(), terminal.cursor_visible());
+        assert_eq!(replayed.title(), terminal.title());
+    }
+
+    #[test]
+    fn prints_and_wraps_text() {
+        let terminal = fed(5, 3, "hello world");
+        assert_eq!(screen(&terminal), ["hello", " worl", "d"]);
+        assert_eq!(terminal.cursor(), (1, 2));
+    }
+
+    #[test]
+    fn writing_the_last_column_defers_the_wrap() {
+        let terminal = fed(5, 2, "abcde");
+        assert_eq!(terminal.cursor(), (4, 0));
+        assert!(terminal.pending_wrap);
+        assert_redraws(&terminal);
+    }
+
+    #[test]
+    fn line_feeds_scroll_the_screen() {
+        let terminal = fed(5, 2, "one\r\ntwo\r\nthree");
+        assert_eq!(screen(&terminal), ["two", "three"]);
+    }
+
+    #[test]
+    fn csi_moves_the_cursor() {
+        let cases = [
+            ("\x1b[3;4H", (3, 2)),
+            ("\x1b[3;4H\x1b[A", (3, 1)),
+            ("\x1b[3;4H\x1b[2B", (3, 4)),
+            ("\x1b[3;4H\x1b[C", (4, 2)),
+            ("\x1b[3;4H\x1b[9D", (0, 2)),
+            ("\x1b[3;4H\x1b[G", (0, 2)),
+            ("\x1b[3;4H\x1b[5d", (3, 4)),
+            ("\x1b[99;99H", (9, 4)),
+            ("\x1b[3;4H\x1b[H", (0, 0)),
+            ("ab\x08", (1, 0)),
+            ("a\tb", (9, 0)),
+        ];
+        for (output, cursor) in cases {
+            assert_eq!(fed(10, 5, output).cursor(), cursor, "{:?}", output);
+        }
+    }
+
+    #[test]
+    fn huge_tab_counts_stop_at_the_edges() {
+        let mut terminal = fed(20, 2, "\x1b[2I");
+        assert_eq!(terminal.cursor(), (16, 0));
+        terminal.feed_str("\x1b[Z");
+        assert_eq!(terminal.cursor(), (8, 0));
+
+        let started = std::time::Instant::now();
+        terminal.feed_str("\x1b[999999999I");
+        assert_eq!(terminal.cursor(), (19, 0));
+        terminal.feed_str("\x1b[999999999Z");
+        assert_eq!(terminal.cursor(), (0, 0));
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn erases_lines_and_screen() {
+        let filled = "aaaaa\r\nbbbbb\r\nccccc\x1b[2;3H";
+        let cases = [
+            ("\x1b[K", ["aaaaa", "bb", "ccccc"]),
+            ("\x1b[1K", ["aaaaa", "   bb", "ccccc"]),
+            ("\x1b[2K", ["aaaaa", "", "ccccc"]),
+            ("\x1b[J", ["aaaaa", "bb", ""]),
+            ("\x1b[1J", ["", "   bb", "ccccc"]),
+            ("\x1b[2J", ["", "", ""]),
+            ("\x1b[2X", ["aaaaa", "bb  b", "ccccc"]),
+            ("\x1b[2P", ["aaaaa", "bbb", "ccccc"]),
+            ("\x1b[2@", ["aaaaa", "bb  b", "ccccc"]),
+        ];
+        for (erase, expected) in cases {
+            let terminal = fed(5, 3, &format!("{}{}", filled, erase));
+            assert_eq!(screen(&terminal), expected, "{:?}", erase);
+            assert_redraws(&terminal);
+        }
+    }
+
+    #[test]
+    fn scroll_region_keeps_lines_outside_it() {
+        let terminal = fed(5, 4, "top\r\n1\r\n2\r\nbot\x1b[2;3r\x1b[3;1H\n\nx");
+        assert_eq!(screen(&terminal), ["top", "", "x", "bot"]);
+        assert_eq!((terminal.scroll_top, terminal.scroll_bottom), (1, 2));
+        assert_redraws(&terminal);
+
+        let terminal = fed(5, 4, "top\r\n1\r\n2\r\nbot\x1b[2;3r\x1b[2;1H\x1bM");
+        assert_eq!(screen(&terminal), ["top", "", "1", "bot"]);
+    }
+
+    #[test]
+    fn insert_and_delete_lines_stay_in_the_scroll_region() {
+        let terminal = fed(5, 4, "a\r\nb\r\nc\r\nd\x1b[1;3r\x1b[2;1H\x1b[L");
+        assert_eq!(screen(&terminal), ["a", "", "b", "d"]);
+        let terminal = fed(5, 4, "a\r\nb\r\nc\r\nd\x1b[1;3r\x1b[1;1H\x1b[M");
+        assert_eq!(screen(&terminal), ["b", "c", "", "d"]);
+    }
+
+    #[test]
+    fn sgr_sets_colors_and_attributes() {
+        let terminal = fed(
+            20,
+            1,
+            "\x1b[1;4;31;42ma\x1b[0mb\x1b[38;5;200;48;2;1;2;3mc\x1b[91;7md\x1b[39;22;24me",
+        );
+        let cells = &terminal.rows()[0];
+        assert_eq!(cells[0].pen.fg, TermColor::Indexed(1));
+        assert_eq!(cells[0].pen.bg, TermColor::Indexed(2));
+        assert!(cells[0].pen.attrs.bold && cells[0].pen.attrs.underline);
+        assert_eq!(cells[1].pen, Pen::default());
+        assert_eq!(cells[2].pen.fg, TermColor::Indexed(200));
+        assert_eq!(cells[2].pen.bg, TermColor::Rgb(1, 2, 3));
+        assert_eq!(cells[3].pen.fg, TermColor::Indexed(9));
+        assert!(cells[3].pen.attrs.inverse);
+        assert_eq!(cells[4].pen.fg, TermColor::Default);
+        assert_eq!(cells[4].pen.bg, TermColor::Rgb(1, 2, 3));
+        assert!(!cells[4].pen.attrs.bold && cells[4].pen.attrs.inverse);
+        assert_redraws(&terminal);
+    }
+
+    #[test]
+    fn erasing_keeps_the_background_color() {
+        let terminal = fed(4, 1, "\x1b[44m\x1b[2K");
+        assert!(terminal.rows()[0]
+            .iter()
+            .all(|cell| cell.pen.bg == TermColor::Indexed(4)));
+    }
+
+    #[test]
+    fn wide_characters_take_two_cells() {
+        let terminal = fed(5, 2, "a漢b");
+        let cells = &terminal.rows()[0];
+        assert_eq!(cells[1].ch, '漢');
+        assert!(cells[2].spacer);
+        assert_eq!(cells[3].ch, 'b');
+        assert_eq!(terminal.row_text(0), "a漢b");
+        assert_eq!(terminal.cursor(), (4, 0));
+        assert_redraws(&terminal);
+
+        // A wide character that doesn't fit in the last column wraps whole
+        let terminal = fed(5, 2, "abcd漢");
+        assert_eq!(screen(&terminal), ["abcd", "漢"]);
+        assert_redraws(&terminal);
+    }
+
+    #[test]
+    fn alternate_screen_keeps_the_primary_screen() {
+        let terminal = fed(6, 2, "shell\x1b[?1049h\x1b[Hvim");
+        assert_eq!(screen(&terminal), ["vim", ""]);
+        assert_redraws(&terminal);
+
+        let terminal = fed(6, 2, "shell\x1b[?1049h\x1b[Hvim\x1b[?1049l");
+        assert_eq!(screen(&terminal), ["shell", ""]);
+        assert_eq!(terminal.cursor(), (5, 0));
+        assert_redraws(&terminal);
+    }
+
+    #[test]
+    fn resize_keeps_content_at_the_top_left() {
+        let mut terminal = fed(5, 3, "abcde\r\nfg\x1b[2;3r\x1b[2;3H");
+        terminal.resize(3, 2);
+        assert_eq!(screen(&terminal), ["abc", "fg"]);
+        assert_eq!((terminal.scroll_top, terminal.scroll_bottom), (0, 1));
+        assert_eq!(terminal.cursor(), (2, 1));
+        terminal.resize(6, 4);
+        assert_eq!(screen(&terminal), ["abc", "fg", "", ""]);
+        assert_eq!(terminal.rows()[0].len(), 6);
+
+        let mut terminal = Terminal::new(5, 3);
+        terminal.feed_event(&Event {
+            time: 0.0,
+            data: EventData::Resize(8, 4),
+        });
+        assert_eq!((terminal.width(), terminal.height()), (8, 4));
+    }
+
+    #[test]
+    fn only_output_and_resize_change_the_screen() {
+        let event = |data| Event { time: 0.0, data };
+        assert!(Terminal::changes_screen(&event(EventData::Output(
+            "a".into()
+        ))));
+        assert!(Terminal::changes_screen(&event(EventData::Resize(1, 1))));
+        assert!(!Terminal::changes_screen(&event(EventData::Input(
+            "a".into()
+        ))));
+        assert!(!Terminal::changes_screen(&event(EventData::Marker(
+            "a".into()
+        ))));
+    }
+
+    #[test]
+    fn redraw_of_a_fresh_terminal_is_empty() {
+        assert_eq!(Terminal::new(10, 5).redraw(), "");
+    }
+
+    #[test]
+    fn redraw_reproduces_modes_cursor_and_title() {
+        let outputs = [
+            "\x1b[31mred\x1b[0m plain \x1b[1;3;9;45mstyled",
+            "\x1b[3;3H\x1b[32m\x1b7\x1b[1;1Hmoved\x1b[0m",
+            "\x1b[2;4r\x1b[?6h\x1b[2;2Horigin",
+            "\x1b[4h\x1b[?7l\x1b[?25l\x1b]2;title\x07text",
+            "abc\x1b[?1049h\x1b[35malt\x1b[5;5H",
+            "line\r\nline\r\n\x1b[2Aover",
+            "\x1b[44m\x1b[2J\x1b[Hblue",
+        ];
+        for output in outputs {
+            let terminal = fed(10, 5, output);
+            assert_redraws(&terminal);
+        }
+    }
+
+    #[test]
+    fn parses_sequences_split_between_events() {
+        let mut terminal = Terminal::new(10, 2);
+        for part in ["\x1b", "[3", "1mred", "\x1b]2;ti", "tle\x07"] {
+            terminal.feed_str(part);
+        }
+        assert_eq!(terminal.row_text(0), "red");
+        assert_eq!(terminal.rows()[0][0].pen.fg, TermColor::Indexed(1));
+        assert_eq!(terminal.title(), Some("title"));
+    }
+}
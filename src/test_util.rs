@@ -0,0 +1,28 @@
+use crate::cast::CastFile;
+use std::{fs, path::PathBuf, process};
+
+/// Header of an 80 by 24 version 2 recording
+pub(crate) const HEADER: &str = "{\"version\":2,\"width\":80,\"height\":24}";
+
+/// A path in the temporary directory that no other test or test run uses
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "asciinema-editor-test-{}-{}.cast",
+        process::id(),
+        name
+    ))
+}
+
+/// Opens a recording of `header` followed by `lines`, saved in the temporary directory under `name`. Lines are written as given so comments and damaged events can be part of it
+pub(crate) fn open(name: &str, header: &str, lines: &[&str]) -> CastFile {
+    let path = temp_path(name);
+    let mut contents = format!("{}\n", header);
+    for line in lines {
+        contents.push_str(line);
+        contents.push('\n');
+    }
+    fs::write(&path, contents).unwrap();
+    let cast = CastFile::new(path.clone()).unwrap();
+    let _ = fs::remove_file(path);
+    cast
+}
@@ -0,0 +1,610 @@
+use crate::asciicast_egui::{Theme, ThemeError};
+use crate::color::Color32;
+use serde_json::Value;
+use std::{collections::HashMap, fs, path::Path};
+
+/// Built in themes as name, foreground, background and palette written the way `.cast` headers write them
+const PRESETS: [(&str, &str, &str, &str); 10] = [
+    (
+        "asciinema",
+        "#cccccc",
+        "#121314",
+        "#000000:#dd3c69:#4ebf22:#ddaf3c:#26b0d7:#b954e1:#54e1b9:#d9d9d9:#4d4d4d:#dd3c69:#4ebf22:#ddaf3c:#26b0d7:#b954e1:#54e1b9:#ffffff",
+    ),
+    (
+        "Solarized Dark",
+        "#839496",
+        "#002b36",
+        "#073642:#dc322f:#859900:#b58900:#268bd2:#d33682:#2aa198:#eee8d5:#002b36:#cb4b16:#586e75:#657b83:#839496:#6c71c4:#93a1a1:#fdf6e3",
+    ),
+    (
+        "Solarized Light",
+        "#657b83",
+        "#fdf6e3",
+        "#073642:#dc322f:#859900:#b58900:#268bd2:#d33682:#2aa198:#eee8d5:#002b36:#cb4b16:#586e75:#657b83:#839496:#6c71c4:#93a1a1:#fdf6e3",
+    ),
+    (
+        "Dracula",
+        "#f8f8f2",
+        "#282a36",
+        "#21222c:#ff5555:#50fa7b:#f1fa8c:#bd93f9:#ff79c6:#8be9fd:#f8f8f2:#6272a4:#ff6e6e:#69ff94:#ffffa5:#d6acff:#ff92df:#a4ffff:#ffffff",
+    ),
+    (
+        "Monokai",
+        "#f8f8f2",
+        "#272822",
+        "#272822:#f92672:#a6e22e:#f4bf75:#66d9ef:#ae81ff:#a1efe4:#f8f8f2:#75715e:#f92672:#a6e22e:#f4bf75:#66d9ef:#ae81ff:#a1efe4:#f9f8f5",
+    ),
+    (
+        "Tango",
+        "#d3d7cf",
+        "#2e3436",
+        "#000000:#cc0000:#4e9a06:#c4a000:#3465a4:#75507b:#06989a:#d3d7cf:#555753:#ef2929:#8ae234:#fce94f:#729fcf:#ad7fa8:#34e2e2:#eeeeec",
+    ),
+    (
+        "Nord",
+        "#d8dee9",
+        "#2e3440",
+        "#3b4252:#bf616a:#a3be8c:#ebcb8b:#81a1c1:#b48ead:#88c0d0:#e5e9f0:#4c566a:#bf616a:#a3be8c:#ebcb8b:#81a1c1:#b48ead:#8fbcbb:#eceff4",
+    ),
+    (
+        "Gruvbox Dark",
+        "#ebdbb2",
+        "#282828",
+        "#282828:#cc241d:#98971a:#d79921:#458588:#b16286:#689d6a:#a89984:#928374:#fb4934:#b8bb26:#fabd2f:#83a598:#d3869b:#8ec07c:#ebdbb2",
+    ),
+    (
+        "One Dark",
+        "#abb2bf",
+        "#282c34",
+        "#282c34:#e06c75:#98c379:#e5c07b:#61afef:#c678dd:#56b6c2:#abb2bf:#5c6370:#e06c75:#98c379:#e5c07b:#61afef:#c678dd:#56b6c2:#ffffff",
+    ),
+    (
+        "Tomorrow Night",
+        "#c5c8c6",
+        "#1d1f21",
+        "#1d1f21:#cc6666:#b5bd68:#f0c674:#81a2be:#b294bb:#8abeb7:#c5c8c6:#969896:#cc6666:#b5bd68:#f0c674:#81a2be:#b294bb:#8abeb7:#ffffff",
+    ),
+];
+
+/// Names of the ANSI colors in palette order as Alacritty and Windows Terminal spell them
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Names of the built in themes in the order they are offered
+pub fn preset_names() -> impl Iterator<Item = &'static str> {
+    PRESETS.iter().map(|(name, ..)| *name)
+}
+
+/// Built in theme by name. Case, spaces, dashes and underscores are ignored so `solarized-dark` finds "Solarized Dark"
+pub fn preset(name: &str) -> Option<Theme> {
+    let normalize = |name: &str| {
+        name.chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .collect::<String>()
+            .to_lowercase()
+    };
+    let wanted = normalize(name);
+    PRESETS
+        .iter()
+        .find(|(name, ..)| normalize(name) == wanted)
+        .map(|(_, fg, bg, palette)| {
+            Theme::from_hex(fg, bg, palette).expect("built in themes are valid")
+        })
+}
+
+/// Reads the color scheme of a terminal emulator config. The format is picked by file name: `.itermcolors` for iTerm2, `.json` for Windows Terminal, `.toml`, `.yml` and `.yaml` for Alacritty, `.conf` for kitty and anything else is read as Xresources
+pub fn import(path: &Path) -> Result<Theme, ThemeError> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| ThemeError::Import(format!("{}: {}", path.display(), e)))?;
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    let scheme = match extension.as_deref() {
+        Some("itermcolors") => iterm(&text),
+        Some("json") => windows_terminal(&text)?,
+        Some("toml") => alacritty(&text, false),
+        Some("yml" | "yaml") => alacritty(&text, true),
+        Some("conf") => kitty(&text),
+        _ => xresources(&text),
+    };
+    scheme.into_theme()
+}
+
+/// Colors found in a config so far. Configs may leave colors out so nothing is assumed until all of them are read
+#[derive(Default)]
+struct Scheme {
+    fg: Option<Color32>,
+    bg: Option<Color32>,
+    palette: [Option<Color32>; 16],
+}
+
+impl Scheme {
+    /// Sets the color named by a `foreground`, `background` or `colorN` key the way kitty and Xresources name them
+    fn set_numbered(&mut self, key: &str, value: &str) {
+        let color = parse_color(value);
+        match key {
+            "foreground" => self.fg = color.or(self.fg),
+            "background" => self.bg = color.or(self.bg),
+            _ => {
+                if let Some(slot) = key
+                    .strip_prefix("color")
+                    .and_then(|number| number.parse::<usize>().ok())
+                    .and_then(|number| self.palette.get_mut(number))
+                {
+                    *slot = color.or(*slot);
+                }
+            }
+        }
+    }
+
+    /// A theme needs the foreground, background and the 8 normal colors. The bright colors are only kept when all 8 of them are there
+    fn into_theme(self) -> Result<Theme, ThemeError> {
+        let missing = |name: &str| ThemeError::Import(format!("no {} color found", name));
+        let fg = self.fg.ok_or_else(|| missing("foreground"))?;
+        let bg = self.bg.ok_or_else(|| missing("background"))?;
+        let mut palette = Vec::with_capacity(16);
+        for (number, color) in self.palette[..8].iter().enumerate() {
+            palette.push(color.ok_or_else(|| missing(COLOR_NAMES[number]))?);
+        }
+        if self.palette[8..].iter().all(Option::is_some) {
+            palette.extend(self.palette[8..].iter().flatten());
+        }
+        Ok(Theme { fg, bg, palette })
+    }
+}
+
+/// Reads a color written as `#rrggbb`, `#rgb`, `0xrrggbb` or X11's `rgb:r/g/b`, with or without quotes around it
+fn parse_color(value: &str) -> Option<Color32> {
+    let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+    if let Some(components) = value.strip_prefix("rgb:") {
+        // X11 components have 1 to 4 hex digits each and are scaled to 8 bits
+        let channels: Vec<u8> = components
+            .split('/')
+            .map(|component| {
+                if component.is_empty() || component.len() > 4 {
+                    return None;
+                }
+                let max = 16u32.pow(component.len() as u32) - 1;
+                let component = u32::from_str_radix(component, 16).ok()?;
+                Some((component * 255 / max) as u8)
+            })
+            .collect::<Option<_>>()?;
+        return match channels[..] {
+            [r, g, b] => Some(Color32::from_rgb(r, g, b)),
+            _ => None,
+        };
+    }
+    let hex = value
+        .strip_prefix('#')
+        .or_else(|| value.strip_prefix("0x"))?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+        6 => Some(Color32::from_rgb(
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        )),
+        // Each digit of the short form is doubled, `#abc` is `#aabbcc`
+        3 => Some(Color32::from_rgb(
+            channel(&hex[0..1])? * 17,
+            channel(&hex[1..2])? * 17,
+            channel(&hex[2..3])? * 17,
+        )),
+        _ => None,
+    }
+}
+
+/// Xresources lines like `*color0: #000000`, `URxvt.foreground: #ffffff` and `*.background: BG` where `BG` comes from a `#define`
+fn xresources(text: &str) -> Scheme {
+    let mut defines = HashMap::new();
+    let mut scheme = Scheme::default();
+    for line in text.lines().map(str::trim) {
+        if let Some(define) = line.strip_prefix("#define") {
+            let mut parts = define.split_whitespace();
+            if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                defines.insert(name, value);
+            }
+            continue;
+        }
+        if line.starts_with('!') {
+            continue;
+        }
+        let Some((resource, value)) = line.split_once(':') else {
+            continue;
+        };
+        // Only the last part of the resource name matters, the class or instance before it is ignored
+        let key = resource
+            .rsplit(['*', '.'])
+            .next()
+            .unwrap_or(resource)
+            .trim();
+        let value = value.trim();
+        let value = defines.get(value).copied().unwrap_or(value);
+        scheme.set_numbered(key, value);
+    }
+    scheme
+}
+
+/// kitty.conf lines like `color0 #000000` and `foreground #ffffff`
+fn kitty(text: &str) -> Scheme {
+    let mut scheme = Scheme::default();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+            scheme.set_numbered(key, value);
+        }
+    }
+    scheme
+}
+
+/// Alacritty's `colors.primary`, `colors.normal` and `colors.bright` sections. TOML names the section in a `[colors.normal]` line while YAML nests the keys under `normal:`, which is followed by indentation. Only the lines that hold colors are read so neither format needs a full parser
+fn alacritty(text: &str, yaml: bool) -> Scheme {
+    let mut scheme = Scheme::default();
+    // Indentation and key of each YAML mapping the current line is nested in
+    let mut nesting: Vec<(usize, &str)> = Vec::new();
+    let mut section = "";
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !yaml {
+            if let Some(table) = trimmed.strip_prefix('[') {
+                section = table
+                    .trim_end_matches(']')
+                    .rsplit('.')
+                    .next()
+                    .unwrap_or_default()
+                    .trim();
+                continue;
+            }
+        }
+        let separator = if yaml { ':' } else { '=' };
+        let Some((key, value)) = trimmed.split_once(separator) else {
+            continue;
+        };
+        let key = key.trim().trim_matches(|c| c == '"' || c == '\'');
+        // Anything after the color, like a trailing comment, is left out. YAML colors are quoted so an unquoted `#` starts a comment
+        let value = value.split_whitespace().next().unwrap_or_default();
+        let value = if yaml && value.starts_with('#') {
+            ""
+        } else {
+            value
+        };
+        if yaml {
+            let indent = line.len() - line.trim_start().len();
+            while nesting.last().is_some_and(|&(outer, _)| outer >= indent) {
+                nesting.pop();
+            }
+            if value.is_empty() {
+                nesting.push((indent, key));
+                continue;
+            }
+            section = nesting.last().map_or("", |&(_, key)| key);
+        }
+        let number = COLOR_NAMES.iter().position(|&name| name == key);
+        match (section, key, number) {
+            ("primary", "foreground", _) => scheme.fg = parse_color(value).or(scheme.fg),
+            ("primary", "background", _) => scheme.bg = parse_color(value).or(scheme.bg),
+            ("normal", _, Some(number)) => scheme.palette[number] = parse_color(value),
+            ("bright", _, Some(number)) => scheme.palette[number + 8] = parse_color(value),
+            _ => {}
+        }
+    }
+    scheme
+}
+
+/// Windows Terminal `settings.json`, or a file holding a single scheme as the scheme websites share them. Settings holding several schemes are read for the one their profile defaults name in `colorScheme`, as there is no telling which of the others is wanted
+fn windows_terminal(text: &str) -> Result<Scheme, ThemeError> {
+    let json: Value = serde_json::from_str(text)
+        .map_err(|e| ThemeError::Import(format!("invalid JSON: {}", e)))?;
+    let scheme_json = match json.get("schemes").and_then(Value::as_array) {
+        Some(schemes) => {
+            let default = json
+                .pointer("/profiles/defaults/colorScheme")
+                .and_then(Value::as_str);
+            match (default, &schemes[..]) {
+                (Some(default), _) => schemes
+                    .iter()
+                    .find(|scheme| scheme_name(scheme) == Some(default))
+                    .ok_or_else(|| {
+                        ThemeError::Import(format!("settings have no color scheme {}", default))
+                    })?,
+                (None, [scheme]) => scheme,
+                (None, []) => {
+                    return Err(ThemeError::Import(
+                        "settings have no color schemes".to_string(),
+                    ))
+                }
+                (None, schemes) => {
+                    let names: Vec<&str> = schemes.iter().filter_map(scheme_name).collect();
+                    return Err(ThemeError::Import(format!(
+                        "settings have several color schemes ({}) and no default colorScheme to pick one",
+                        names.join(", ")
+                    )));
+                }
+            }
+        }
+        None => &json,
+    };
+    let color = |key: &str| scheme_json.get(key)?.as_str().and_then(parse_color);
+    let mut scheme = Scheme {
+        fg: color("foreground"),
+        bg: color("background"),
+        ..Scheme::default()
+    };
+    for (number, name) in COLOR_NAMES.iter().enumerate() {
+        // Windows Terminal calls magenta purple
+        let name = if *name == "magenta" { "purple" } else { name };
+        scheme.palette[number] = color(name);
+        let bright = format!("bright{}{}", name[..1].to_uppercase(), &name[1..]);
+        scheme.palette[number + 8] = color(&bright);
+    }
+    Ok(scheme)
+}
+
+/// Name a Windows Terminal scheme is picked by
+fn scheme_name(scheme: &Value) -> Option<&str> {
+    scheme.get("name").and_then(Value::as_str)
+}
+
+/// iTerm2 `.itermcolors` property lists, where every color is a dictionary of red, green and blue components between 0 and 1
+fn iterm(text: &str) -> Scheme {
+    let mut scheme = Scheme {
+        fg: plist_color(text, "Foreground Color"),
+        bg: plist_color(text, "Background Color"),
+        ..Scheme::default()
+    };
+    for (number, slot) in scheme.palette.iter_mut().enumerate() {
+        *slot = plist_color(text, &format!("Ansi {} Color", number));
+    }
+    scheme
+}
+
+/// Color stored under `key` in the top level dictionary of an `.itermcolors` file
+fn plist_color(text: &str, key: &str) -> Option<Color32> {
+    let start = text.find(&format!("<key>{}</key>", key))?;
+    let dict = &text[start..];
+    let dict = &dict[dict.find("<dict>")?..];
+    let dict = &dict[..dict.find("</dict>")?];
+    let component = |name: &str| -> Option<u8> {
+        let rest = &dict[dict.find(&format!("<key>{} Component</key>", name))?..];
+        let rest = &rest[rest.find("<real>")? + "<real>".len()..];
+        let value: f64 = rest[..rest.find("</real>")?].trim().parse().ok()?;
+        Some((value.clamp(0.0, 1.0) * 255.0).round() as u8)
+    };
+    Some(Color32::from_rgb(
+        component("Red")?,
+        component("Green")?,
+        component("Blue")?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Foreground, background and palette every fixture below describes
+    const FG: &str = "#dddddd";
+    const BG: &str = "#111111";
+    const PALETTE: [&str; 16] = [
+        "#000000", "#aa0000", "#00aa00", "#aa5500", "#0000aa", "#aa00aa", "#00aaaa", "#aaaaaa",
+        "#555555", "#ff5555", "#55ff55", "#ffff55", "#5555ff", "#ff55ff", "#55ffff", "#ffffff",
+    ];
+
+    fn expected() -> Theme {
+        Theme::from_hex(FG, BG, &PALETTE.join(":")).unwrap()
+    }
+
+    #[test]
+    fn reads_xresources() {
+        let text = "\
+! comments and defines are allowed
+#define BG #111111
+URxvt.foreground: rgb:dd/dd/dd
+*.background: BG
+*color0: #000
+*color1: #aa0000
+*color2: #00aa00
+*color3: #aa5500
+*color4: #0000aa
+*color5: #aa00aa
+*color6: #00aaaa
+*color7: #aaaaaa
+*.color8: #555555
+*.color9: #ff5555
+*.color10: #55ff55
+*.color11: #ffff55
+*.color12: #5555ff
+*.color13: #ff55ff
+*.color14: #55ffff
+*.color15: #ffffff
+";
+        assert_eq!(xresources(text).into_theme().unwrap(), expected());
+    }
+
+    #[test]
+    fn reads_kitty() {
+        let text = "\
+# vim:ft=kitty
+foreground #dddddd
+background #111111
+selection_foreground #000000
+color0 #000000
+color1 #aa0000
+color2 #00aa00
+color3 #aa5500
+color4 #0000aa
+color5 #aa00aa
+color6 #00aaaa
+color7 #aaaaaa
+color8 #555555
+color9 #ff5555
+color10 #55ff55
+color11 #ffff55
+color12 #5555ff
+color13 #ff55ff
+color14 #55ffff
+color15 #ffffff
+";
+        assert_eq!(kitty(text).into_theme().unwrap(), expected());
+    }
+
+    #[test]
+    fn reads_alacritty_toml() {
+        let text = r##"
+[colors.primary]
+foreground = "#dddddd"
+background = "#111111" # a comment
+
+[colors.cursor]
+text = "#000000"
+
+[colors.normal]
+black = "#000000"
+red = "#aa0000"
+green = "#00aa00"
+yellow = "#aa5500"
+blue = "#0000aa"
+magenta = "#aa00aa"
+cyan = "#00aaaa"
+white = "#aaaaaa"
+
+[colors.bright]
+black = "0x555555"
+red = "0xff5555"
+green = "0x55ff55"
+yellow = "0xffff55"
+blue = "0x5555ff"
+magenta = "0xff55ff"
+cyan = "0x55ffff"
+white = "0xffffff"
+"##;
+        assert_eq!(alacritty(text, false).into_theme().unwrap(), expected());
+    }
+
+    #[test]
+    fn reads_alacritty_yaml() {
+        let text = r##"
+colors:
+  primary:
+    foreground: '#dddddd'
+    background: '#111111' # a comment
+  cursor:
+    text: '#000000'
+  normal:
+    black:   '#000000'
+    red:     '#aa0000'
+    green:   '#00aa00'
+    yellow:  '#aa5500'
+    blue:    '#0000aa'
+    magenta: '#aa00aa'
+    cyan:    '#00aaaa'
+    white:   '#aaaaaa'
+  bright:
+    black:   '#555555'
+    red:     '#ff5555'
+    green:   '#55ff55'
+    yellow:  '#ffff55'
+    blue:    '#5555ff'
+    magenta: '#ff55ff'
+    cyan:    '#55ffff'
+    white:   '#ffffff'
+"##;
+        assert_eq!(alacritty(text, true).into_theme().unwrap(), expected());
+    }
+
+    /// A Windows Terminal scheme named `name` with the fixture colors, or all black but the foreground if `wanted` is false
+    fn windows_terminal_scheme(name: &str, wanted: bool) -> String {
+        let names = [
+            "black", "red", "green", "yellow", "blue", "purple", "cyan", "white",
+        ];
+        let mut fields = vec![
+            format!("\"name\": \"{}\"", name),
+            format!("\"foreground\": \"{}\"", FG),
+            format!("\"background\": \"{}\"", BG),
+        ];
+        for (number, name) in names.iter().enumerate() {
+            let bright = format!("bright{}{}", name[..1].to_uppercase(), &name[1..]);
+            for (key, color) in [
+                (name.to_string(), PALETTE[number]),
+                (bright, PALETTE[number + 8]),
+            ] {
+                let color = if wanted { color } else { "#000000" };
+                fields.push(format!("\"{}\": \"{}\"", key, color));
+            }
+        }
+        format!("{{{}}}", fields.join(", "))
+    }
+
+    #[test]
+    fn reads_windows_terminal() {
+        let scheme = windows_terminal_scheme("Fixture", true);
+        let other = windows_terminal_scheme("Other", false);
+        let theme = |text: &str| windows_terminal(text).and_then(Scheme::into_theme);
+
+        assert_eq!(theme(&scheme).unwrap(), expected());
+        let single = format!("{{\"schemes\": [{}]}}", scheme);
+        assert_eq!(theme(&single).unwrap(), expected());
+
+        // With several schemes only the one the profiles use is taken
+        let named = format!(
+            "{{\"profiles\": {{\"defaults\": {{\"colorScheme\": \"Fixture\"}}}}, \"schemes\": [{}, {}]}}",
+            other, scheme
+        );
+        assert_eq!(theme(&named).unwrap(), expected());
+        let unnamed = format!("{{\"schemes\": [{}, {}]}}", other, scheme);
+        assert!(theme(&unnamed).is_err());
+        let missing = named.replace("\"Fixture\"}", "\"Missing\"}");
+        assert!(theme(&missing).is_err());
+    }
+
+    #[test]
+    fn reads_iterm() {
+        let color = |key: &str, hex: &str| {
+            let component =
+                |at: usize| u8::from_str_radix(&hex[at..at + 2], 16).unwrap() as f64 / 255.0;
+            format!(
+                "<key>{}</key>\n<dict>\n<key>Alpha Component</key>\n<real>1</real>\n<key>Blue Component</key>\n<real>{}</real>\n<key>Green Component</key>\n<real>{}</real>\n<key>Red Component</key>\n<real>{}</real>\n</dict>\n",
+                key,
+                component(5),
+                component(3),
+                component(1)
+            )
+        };
+        let mut text = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\">\n<dict>\n",
+        );
+        for (number, hex) in PALETTE.iter().enumerate() {
+            text.push_str(&color(&format!("Ansi {} Color", number), hex));
+        }
+        text.push_str(&color("Background Color", BG));
+        text.push_str(&color("Foreground Color", FG));
+        text.push_str("</dict>\n</plist>\n");
+        assert_eq!(iterm(&text).into_theme().unwrap(), expected());
+    }
+
+    #[test]
+    fn presets_are_valid_themes() {
+        for name in preset_names() {
+            let theme = preset(name).unwrap();
+            assert!(Theme::validate_palette(&theme.palette).is_ok(), "{}", name);
+            assert_eq!(theme.palette.len(), 16, "{}", name);
+            // Names are found however they are written
+            assert!(
+                preset(&name.to_uppercase().replace(' ', "-")).is_some(),
+                "{}",
+                name
+            );
+        }
+    }
+}
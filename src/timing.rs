@@ -0,0 +1,407 @@
+use crate::cast::CastError;
+use std::fmt;
+
+/// A change to event times that is applied lazily as events are read instead of being stored per event. This is what allows operations touching every event of the file to stay cheap on files of any size
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeTransform {
+    /// Removes every event in `[start, end)` and moves later events back by the removed duration. `end` may be infinite to remove everything from `start` onwards
+    Cut { start: f64, end: f64 },
+    /// Moves every event in `[start, end)` by `offset` seconds. Events outside the range keep their times so the moved events must stay between their neighbours
+    Offset { start: f64, end: f64, offset: f64 },
+    /// Plays `[start, end)` `factor` times faster. Later events move by however much the range shrinks or grows
+    Scale { start: f64, end: f64, factor: f64 },
+    /// Maps times through straight lines between `(old, new)` points sorted by old time. Times before the first or after the last point move by that point's difference so the whole mapping stays continuous
+    Piecewise { points: Vec<(f64, f64)> },
+}
+
+impl TimeTransform {
+    /// New time of an event at `time` or `None` if the transform removes it
+    pub fn apply(&self, time: f64) -> Option<f64> {
+        match *self {
+            TimeTransform::Cut { start, end } => {
+                if time < start {
+                    Some(time)
+                } else if time < end {
+                    None
+                } else {
+                    Some(time - (end - start))
+                }
+            }
+            TimeTransform::Offset { start, end, offset } => {
+                if start <= time && time < end {
+                    Some(time + offset)
+                } else {
+                    Some(time)
+                }
+            }
+            TimeTransform::Scale { start, end, factor } => {
+                if time < start {
+                    Some(time)
+                } else if time < end {
+                    Some(start + (time - start) / factor)
+                } else {
+                    Some(time - (end - start) + (end - start) / factor)
+                }
+            }
+            TimeTransform::Piecewise { ref points } => {
+                let (Some(&(first_old, first_new)), Some(&(last_old, last_new))) =
+                    (points.first(), points.last())
+                else {
+                    return Some(time);
+                };
+                if time <= first_old {
+                    Some(time + first_new - first_old)
+                } else if time >= last_old {
+                    Some(time + last_new - last_old)
+                } else {
+                    let next = points.partition_point(|&(old, _)| old <= time);
+                    let (from_old, from_new) = points[next - 1];
+                    let (to_old, to_new) = points[next];
+                    Some(from_new + (time - from_old) * (to_new - from_new) / (to_old - from_old))
+                }
+            }
+        }
+    }
+
+    /// Like `apply` but times a cut removes are placed at the start of the cut instead of being dropped, so the result never goes back as `time` grows. This is what lets the file be searched by time with the removed lines still in it
+    pub fn position(&self, time: f64) -> f64 {
+        match (self.apply(time), self) {
+            (Some(time), _) => time,
+            (None, TimeTransform::Cut { start, .. }) => *start,
+            (None, _) => time,
+        }
+    }
+
+    /// Checks that the transform is well formed and can't reorder events on its own. Offsets also depend on the surrounding events which `CastFile` checks separately
+    pub fn validate(&self) -> Result<(), CastError> {
+        let valid = match *self {
+            TimeTransform::Cut { start, end } => 0.0 <= start && start < end,
+            TimeTransform::Offset { start, end, offset } => {
+                0.0 <= start && start < end && offset.is_finite()
+            }
+            TimeTransform::Scale { start, end, factor } => {
+                0.0 <= start && start < end && factor.is_finite() && factor > 0.0
+            }
+            TimeTransform::Piecewise { ref points } => {
+                !points.is_empty()
+                    && points
+                        .iter()
+                        .all(|&(old, new)| old.is_finite() && new.is_finite())
+                    && points
+                        .windows(2)
+                        .all(|pair| pair[0].0 < pair[1].0 && pair[0].1 <= pair[1].1)
+            }
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(CastError::TimingError)
+        }
+    }
+}
+
+impl fmt::Display for TimeTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeTransform::Cut { start, end } if end.is_infinite() => {
+                write!(f, "Cut from {}s to the end", start)
+            }
+            TimeTransform::Cut { start, end } => write!(f, "Cut {}s to {}s", start, end),
+            TimeTransform::Offset { start, end, offset } => {
+                write!(f, "Shift {}s to {}s by {}s", start, end, offset)
+            }
+            TimeTransform::Scale { start, end, factor } => {
+                write!(f, "Speed {}s to {}s by {}x", start, end, factor)
+            }
+            TimeTransform::Piecewise { points } => {
+                write!(f, "Retime through {} points", points.len())
+            }
+        }
+    }
+}
+
+/// Ordered stack of transforms applied to the times of the memory mapped events. Each transform sees the times produced by the ones before it, so a transform always works on the times that were shown when it was applied. Events held in modification chains already carry their final times and are not passed through the timeline
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timeline {
+    transforms: Vec<TimeTransform>,
+}
+
+impl Timeline {
+    pub fn push(&mut self, transform: TimeTransform) {
+        self.transforms.push(transform);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Runs `time` through every transform in order. Returns `None` if any of them removes the event
+    pub fn apply(&self, time: f64) -> Option<f64> {
+        if self.transforms.is_empty() {
+            return Some(time);
+        }
+        self.transforms
+            .iter()
+            .try_fold(time, |time, transform| transform.apply(time))
+            .map(round_time)
+    }
+
+    /// Runs `time` through every transform with `TimeTransform::position`, which keeps the times of removed events in order with the rest
+    pub fn position(&self, time: f64) -> f64 {
+        if self.transforms.is_empty() {
+            return time;
+        }
+        round_time(
+            self.transforms
+                .iter()
+                .fold(time, |time, transform| transform.position(time)),
+        )
+    }
+}
+
+/// Rounds to microseconds like asciinema writes times so floating point arithmetic doesn't leave long tails
+pub fn round_time(time: f64) -> f64 {
+    (time * 1_000_000.0).round() / 1_000_000.0
+}
+
+/// Settings for capping the pauses between events
+#[derive(Debug, Clone)]
+pub struct IdleCompression {
+    /// Longest gap in seconds allowed between two events
+    pub max_gap: f64,
+    /// Separate limit for gaps that follow an `Input` event, which are usually someone thinking rather than waiting on output
+    pub max_gap_after_input: Option<f64>,
+    /// Only gaps inside `[start, end]` are compressed
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Default for IdleCompression {
+    fn default() -> Self {
+        Self {
+            max_gap: 2.0,
+            max_gap_after_input: None,
+            start: 0.0,
+            end: f64::INFINITY,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn piecewise(points: &[(f64, f64)]) -> TimeTransform {
+        TimeTransform::Piecewise {
+            points: points.to_vec(),
+        }
+    }
+
+    #[test]
+    fn transforms_move_times() {
+        let cut = TimeTransform::Cut {
+            start: 2.0,
+            end: 5.0,
+        };
+        let cut_to_end = TimeTransform::Cut {
+            start: 2.0,
+            end: f64::INFINITY,
+        };
+        let offset = TimeTransform::Offset {
+            start: 2.0,
+            end: 5.0,
+            offset: 0.5,
+        };
+        let scale = TimeTransform::Scale {
+            start: 2.0,
+            end: 6.0,
+            factor: 2.0,
+        };
+        let retime = piecewise(&[(2.0, 3.0), (4.0, 4.0), (8.0, 10.0)]);
+        let cases = [
+            // Ranges include their start and exclude their end
+            (&cut, 1.999, Some(1.999)),
+            (&cut, 2.0, None),
+            (&cut, 4.999, None),
+            (&cut, 5.0, Some(2.0)),
+            (&cut, 7.0, Some(4.0)),
+            (&cut_to_end, 1.0, Some(1.0)),
+            (&cut_to_end, 1e9, None),
+            (&offset, 1.9, Some(1.9)),
+            (&offset, 2.0, Some(2.5)),
+            (&offset, 4.9, Some(5.4)),
+            (&offset, 5.0, Some(5.0)),
+            (&scale, 1.0, Some(1.0)),
+            (&scale, 2.0, Some(2.0)),
+            (&scale, 4.0, Some(3.0)),
+            (&scale, 6.0, Some(4.0)),
+            (&scale, 10.0, Some(8.0)),
+            // Outside its points a piecewise transform moves times like its closest point
+            (&retime, 0.0, Some(1.0)),
+            (&retime, 2.0, Some(3.0)),
+            (&retime, 3.0, Some(3.5)),
+            (&retime, 4.0, Some(4.0)),
+            (&retime, 6.0, Some(7.0)),
+            (&retime, 8.0, Some(10.0)),
+            (&retime, 9.0, Some(11.0)),
+        ];
+        for (transform, time, expected) in cases {
+            assert_eq!(transform.apply(time), expected, "{} at {}", transform, time);
+        }
+    }
+
+    #[test]
+    fn removed_times_are_positioned_at_the_cut() {
+        let cut = TimeTransform::Cut {
+            start: 2.0,
+            end: 5.0,
+        };
+        let positions: Vec<f64> = [1.0, 2.0, 3.0, 4.999, 5.0, 6.0]
+            .into_iter()
+            .map(|time| cut.position(time))
+            .collect();
+        assert_eq!(positions, [1.0, 2.0, 2.0, 2.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn validation_rejects_transforms_that_reorder_events() {
+        let cases = [
+            (
+                TimeTransform::Cut {
+                    start: 1.0,
+                    end: 2.0,
+                },
+                true,
+            ),
+            (
+                TimeTransform::Cut {
+                    start: 0.0,
+                    end: f64::INFINITY,
+                },
+                true,
+            ),
+            (
+                TimeTransform::Cut {
+                    start: 2.0,
+                    end: 2.0,
+                },
+                false,
+            ),
+            (
+                TimeTransform::Cut {
+                    start: -1.0,
+                    end: 2.0,
+                },
+                false,
+            ),
+            (
+                TimeTransform::Offset {
+                    start: 3.0,
+                    end: 1.0,
+                    offset: 1.0,
+                },
+                false,
+            ),
+            (
+                TimeTransform::Offset {
+                    start: 1.0,
+                    end: 3.0,
+                    offset: -0.5,
+                },
+                true,
+            ),
+            (
+                TimeTransform::Offset {
+                    start: 1.0,
+                    end: 3.0,
+                    offset: f64::NAN,
+                },
+                false,
+            ),
+            (
+                TimeTransform::Offset {
+                    start: 1.0,
+                    end: f64::INFINITY,
+                    offset: f64::NEG_INFINITY,
+                },
+                false,
+            ),
+            (
+                TimeTransform::Scale {
+                    start: 0.0,
+                    end: 1.0,
+                    factor: 0.5,
+                },
+                true,
+            ),
+            (
+                TimeTransform::Scale {
+                    start: 0.0,
+                    end: 1.0,
+                    factor: 0.0,
+                },
+                false,
+            ),
+            (
+                TimeTransform::Scale {
+                    start: 0.0,
+                    end: 1.0,
+                    factor: f64::INFINITY,
+                },
+                false,
+            ),
+            (piecewise(&[(0.0, 0.0), (1.0, 1.0)]), true),
+            // Points may bring events together but never swap them
+            (piecewise(&[(0.0, 0.0), (1.0, 0.0)]), true),
+            (piecewise(&[]), false),
+            (piecewise(&[(1.0, 0.0), (1.0, 1.0)]), false),
+            (piecewise(&[(2.0, 0.0), (1.0, 1.0)]), false),
+            (piecewise(&[(0.0, 1.0), (1.0, 0.0)]), false),
+            (piecewise(&[(0.0, f64::NAN)]), false),
+        ];
+        for (transform, valid) in cases {
+            assert_eq!(transform.validate().is_ok(), valid, "{:?}", transform);
+        }
+    }
+
+    #[test]
+    fn timeline_applies_transforms_in_order() {
+        let mut timeline = Timeline::default();
+        assert_eq!(timeline.apply(1.23456789), Some(1.23456789));
+        timeline.push(TimeTransform::Cut {
+            start: 1.0,
+            end: 2.0,
+        });
+        // The second cut works on the times after the first one
+        timeline.push(TimeTransform::Cut {
+            start: 1.0,
+            end: 2.0,
+        });
+        assert_eq!(timeline.apply(0.5), Some(0.5));
+        assert_eq!(timeline.apply(1.5), None);
+        assert_eq!(timeline.apply(2.5), None);
+        assert_eq!(timeline.apply(3.5), Some(1.5));
+        assert_eq!(timeline.position(2.5), 1.0);
+
+        timeline.push(TimeTransform::Scale {
+            start: 0.0,
+            end: 10.0,
+            factor: 3.0,
+        });
+        assert_eq!(timeline.apply(4.0), Some(0.666667));
+    }
+
+    #[test]
+    fn rounds_to_microseconds() {
+        let cases = [
+            (0.1 + 0.2, 0.3),
+            (1.0000004, 1.0),
+            (1.0000005, 1.000001),
+            (2.9999999, 3.0),
+            (123456.1234564, 123456.123456),
+        ];
+        for (time, rounded) in cases {
+            assert_eq!(round_time(time), rounded, "{}", time);
+        }
+    }
+}